@@ -0,0 +1,387 @@
+use std::{collections::BTreeMap, net::SocketAddr, sync::Arc};
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::{Request, StatusCode, header},
+    middleware::{self, Next},
+    response::Response,
+    routing::{get, post, put},
+};
+use ethers::{providers::Middleware, types::{Address, H256 as TxHash}};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    allowance::AllowanceView,
+    controls::{BotControls, OpportunityRecord},
+    execution_context::ExecutionContext,
+    dex_price_listener::multiplexer::LogMultiplexer,
+    paper_trading::{PaperLedger, PaperTrade},
+    pnl::PnlSummary,
+    supervisor::{Supervisor, TaskState, TaskStatus},
+    types::{DexPairKey, PriceTracker},
+};
+
+/// Everything the admin handlers read or steer
+pub struct AdminState<M> {
+    pub controls: Arc<BotControls>,
+    pub tracker: Arc<PriceTracker>,
+    pub multiplexer: Arc<LogMultiplexer<M>>,
+    pub paper: Arc<PaperLedger>,
+    /// For actions that sign transactions
+    pub ctx: Arc<ExecutionContext<M>>,
+    pub supervisor: Arc<Supervisor>,
+}
+
+#[derive(Serialize)]
+struct PoolView {
+    dex: String,
+    factory: Address,
+    pool: Address,
+    base: Address,
+    quote: Address,
+    price: Option<f64>,
+    age_secs: Option<f64>,
+    paused: bool,
+}
+
+#[derive(Serialize)]
+struct DexView {
+    name: String,
+    factory: Address,
+    pools: usize,
+    priced_pools: usize,
+    paused: bool,
+}
+
+#[derive(Serialize)]
+struct TaskView {
+    name: String,
+    state: TaskState,
+    restarts: u32,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+    /// Seconds since the task was last (re)started
+    uptime_secs: f64,
+}
+
+impl From<TaskStatus> for TaskView {
+    fn from(status: TaskStatus) -> Self {
+        Self {
+            name: status.name,
+            state: status.state,
+            restarts: status.restarts,
+            consecutive_failures: status.consecutive_failures,
+            last_error: status.last_error,
+            uptime_secs: status.last_started.elapsed().as_secs_f64(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct StatusView {
+    paused: bool,
+    paused_pairs: Vec<(Address, Address)>,
+    paused_dexes: Vec<Address>,
+    thresholds: Vec<ThresholdView>,
+    watched_pools: usize,
+}
+
+#[derive(Serialize)]
+struct ThresholdView {
+    base: Address,
+    quote: Address,
+    threshold: f64,
+}
+
+#[derive(Serialize)]
+struct PaperView {
+    totals: Vec<PaperTotal>,
+    trades: Vec<PaperTrade>,
+}
+
+#[derive(Serialize)]
+struct PaperTotal {
+    quote: Address,
+    profit: f64,
+}
+
+#[derive(Deserialize)]
+struct ThresholdBody {
+    threshold: f64,
+}
+
+async fn pools<M: Middleware + 'static>(
+    State(state): State<Arc<AdminState<M>>>,
+) -> Json<Vec<PoolView>> {
+    let prices: BTreeMap<_, _> = state
+        .tracker
+        .snapshot()
+        .await
+        .into_iter()
+        .map(|(key, info)| ((key.dex_factory, key.pair_address), info))
+        .collect();
+
+    let mut views: Vec<PoolView> = state
+        .multiplexer
+        .contexts()
+        .into_iter()
+        .map(|ctx| {
+            let key = DexPairKey { dex_factory: ctx.dex_factory, pair_address: ctx.pair.pair };
+            let info = prices.get(&(key.dex_factory, key.pair_address));
+            PoolView {
+                paused: state.controls.is_dex_paused(ctx.dex_factory)
+                    || state.controls.is_pair_paused(ctx.pair.base.id, ctx.pair.quote.id),
+                dex: ctx.dex_name,
+                factory: ctx.dex_factory,
+                pool: ctx.pair.pair,
+                base: ctx.pair.base.id,
+                quote: ctx.pair.quote.id,
+                price: info.map(|i| i.price),
+                age_secs: info.map(|i| i.updated_at.elapsed().as_secs_f64()),
+            }
+        })
+        .collect();
+    views.sort_by(|a, b| (&a.dex, a.pool).cmp(&(&b.dex, b.pool)));
+
+    Json(views)
+}
+
+async fn dexes<M: Middleware + 'static>(
+    State(state): State<Arc<AdminState<M>>>,
+) -> Json<Vec<DexView>> {
+    let priced: std::collections::HashSet<DexPairKey> =
+        state.tracker.snapshot().await.into_iter().map(|(key, _)| key).collect();
+
+    let mut by_dex: BTreeMap<(String, Address), DexView> = BTreeMap::new();
+    for ctx in state.multiplexer.contexts() {
+        let view = by_dex
+            .entry((ctx.dex_name.clone(), ctx.dex_factory))
+            .or_insert_with(|| DexView {
+                name: ctx.dex_name.clone(),
+                factory: ctx.dex_factory,
+                pools: 0,
+                priced_pools: 0,
+                paused: state.controls.is_dex_paused(ctx.dex_factory),
+            });
+        view.pools += 1;
+        if priced.contains(&DexPairKey { dex_factory: ctx.dex_factory, pair_address: ctx.pair.pair }) {
+            view.priced_pools += 1;
+        }
+    }
+
+    Json(by_dex.into_values().collect())
+}
+
+async fn opportunities<M: Middleware + 'static>(
+    State(state): State<Arc<AdminState<M>>>,
+) -> Json<Vec<OpportunityRecord>> {
+    Json(state.controls.recent())
+}
+
+async fn paper_trades<M: Middleware + 'static>(
+    State(state): State<Arc<AdminState<M>>>,
+) -> Json<PaperView> {
+    Json(PaperView {
+        totals: state
+            .paper
+            .totals()
+            .into_iter()
+            .map(|(quote, profit)| PaperTotal { quote, profit })
+            .collect(),
+        trades: state.paper.recent(),
+    })
+}
+
+async fn status<M: Middleware + 'static>(
+    State(state): State<Arc<AdminState<M>>>,
+) -> Json<StatusView> {
+    let controls = &state.controls;
+    Json(StatusView {
+        paused: controls.is_paused(),
+        paused_pairs: controls.paused_pairs(),
+        paused_dexes: controls.paused_dexes(),
+        thresholds: controls
+            .thresholds()
+            .into_iter()
+            .map(|((base, quote), threshold)| ThresholdView { base, quote, threshold })
+            .collect(),
+        watched_pools: state.multiplexer.watched_count(),
+    })
+}
+
+async fn tasks<M: Middleware + 'static>(State(state): State<Arc<AdminState<M>>>) -> Json<Vec<TaskView>> {
+    Json(state.supervisor.statuses().into_iter().map(TaskView::from).collect())
+}
+
+async fn task<M: Middleware + 'static>(
+    State(state): State<Arc<AdminState<M>>>,
+    Path(name): Path<String>,
+) -> Result<Json<TaskView>, StatusCode> {
+    state
+        .supervisor
+        .status(&name)
+        .map(|status| Json(status.into()))
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn pause<M: Middleware + 'static>(State(state): State<Arc<AdminState<M>>>) -> StatusCode {
+    state.controls.set_paused(true);
+    tracing::warn!("⏸️ Bot paused via admin API");
+    StatusCode::NO_CONTENT
+}
+
+async fn resume<M: Middleware + 'static>(State(state): State<Arc<AdminState<M>>>) -> StatusCode {
+    state.controls.set_paused(false);
+    tracing::info!("▶️ Bot resumed via admin API");
+    StatusCode::NO_CONTENT
+}
+
+async fn pause_pair<M: Middleware + 'static>(
+    State(state): State<Arc<AdminState<M>>>,
+    Path((base, quote)): Path<(Address, Address)>,
+) -> StatusCode {
+    state.controls.set_pair_paused(base, quote, true);
+    tracing::warn!("⏸️ Pair {:?}/{:?} paused via admin API", base, quote);
+    StatusCode::NO_CONTENT
+}
+
+async fn resume_pair<M: Middleware + 'static>(
+    State(state): State<Arc<AdminState<M>>>,
+    Path((base, quote)): Path<(Address, Address)>,
+) -> StatusCode {
+    state.controls.set_pair_paused(base, quote, false);
+    tracing::info!("▶️ Pair {:?}/{:?} resumed via admin API", base, quote);
+    StatusCode::NO_CONTENT
+}
+
+async fn set_threshold<M: Middleware + 'static>(
+    State(state): State<Arc<AdminState<M>>>,
+    Path((base, quote)): Path<(Address, Address)>,
+    Json(body): Json<ThresholdBody>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .controls
+        .set_threshold(base, quote, body.threshold)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    tracing::info!("Threshold for {:?}/{:?} set to {}", base, quote, body.threshold);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn pause_dex<M: Middleware + 'static>(
+    State(state): State<Arc<AdminState<M>>>,
+    Path(factory): Path<Address>,
+) -> StatusCode {
+    state.controls.set_dex_paused(factory, true);
+    tracing::warn!("⏸️ DEX {:?} paused via admin API", factory);
+    StatusCode::NO_CONTENT
+}
+
+async fn resume_dex<M: Middleware + 'static>(
+    State(state): State<Arc<AdminState<M>>>,
+    Path(factory): Path<Address>,
+) -> StatusCode {
+    state.controls.set_dex_paused(factory, false);
+    tracing::info!("▶️ DEX {:?} resumed via admin API", factory);
+    StatusCode::NO_CONTENT
+}
+
+async fn reload<M: Middleware + 'static>(State(state): State<Arc<AdminState<M>>>) -> StatusCode {
+    state.controls.request_reload();
+    tracing::info!("🔄 Config reload requested via admin API");
+    StatusCode::ACCEPTED
+}
+
+async fn pnl<M: Middleware + 'static>(State(state): State<Arc<AdminState<M>>>) -> Json<PnlSummary> {
+    Json(state.ctx.pnl.summary())
+}
+
+async fn approvals<M: Middleware + 'static>(
+    State(state): State<Arc<AdminState<M>>>,
+) -> Result<Json<Vec<AllowanceView>>, (StatusCode, String)> {
+    state
+        .ctx
+        .approvals
+        .snapshot(&state.ctx)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))
+}
+
+async fn revoke_approvals<M: Middleware + 'static>(
+    State(state): State<Arc<AdminState<M>>>,
+) -> Result<Json<Vec<TxHash>>, (StatusCode, String)> {
+    tracing::warn!("🔒 Approval revocation requested via admin API");
+    state
+        .ctx
+        .approvals
+        .revoke_all(&state.ctx)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))
+}
+
+/// Reject requests without `Authorization: Bearer <token>`
+async fn require_token<B>(
+    State(token): State<Arc<String>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Result<Response, StatusCode> {
+    let presented = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    if constant_time_eq(presented.as_bytes(), token.as_bytes()) {
+        Ok(next.run(req).await)
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn router<M: Middleware + 'static>(state: Arc<AdminState<M>>, token: String) -> Router {
+    Router::new()
+        .route("/status", get(status::<M>))
+        .route("/dexes", get(dexes::<M>))
+        .route("/pools", get(pools::<M>))
+        .route("/opportunities", get(opportunities::<M>))
+        .route("/paper-trades", get(paper_trades::<M>))
+        .route("/pnl", get(pnl::<M>))
+        .route("/tasks", get(tasks::<M>))
+        .route("/tasks/:name", get(task::<M>))
+        .route("/pause", post(pause::<M>))
+        .route("/resume", post(resume::<M>))
+        .route("/pairs/:base/:quote/pause", post(pause_pair::<M>))
+        .route("/pairs/:base/:quote/resume", post(resume_pair::<M>))
+        .route("/pairs/:base/:quote/threshold", put(set_threshold::<M>))
+        .route("/dexes/:factory/pause", post(pause_dex::<M>))
+        .route("/dexes/:factory/resume", post(resume_dex::<M>))
+        .route("/reload", post(reload::<M>))
+        .route("/approvals", get(approvals::<M>))
+        .route("/approvals/revoke", post(revoke_approvals::<M>))
+        .layer(middleware::from_fn_with_state(Arc::new(token), require_token))
+        .with_state(state)
+}
+
+/// Serve the admin API on `addr` until `shutdown` is cancelled
+pub async fn serve<M: Middleware + 'static>(
+    addr: SocketAddr,
+    state: Arc<AdminState<M>>,
+    token: String,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    tracing::info!("🛠️ Admin API listening on http://{}", addr);
+    axum::Server::try_bind(&addr)
+        .map_err(|e| anyhow::anyhow!("Failed to bind admin API on {}: {:?}", addr, e))?
+        .serve(router(state, token).into_make_service())
+        .with_graceful_shutdown(shutdown.cancelled_owned())
+        .await
+        .map_err(|e| anyhow::anyhow!("Admin API failed: {:?}", e))
+}
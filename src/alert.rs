@@ -0,0 +1,49 @@
+/// Destination for operator alerts
+#[async_trait::async_trait]
+pub trait Alerter: Send + Sync {
+    async fn alert(&self, message: &str);
+}
+
+/// Alerts only as error-level log lines
+pub struct LogAlerter;
+
+#[async_trait::async_trait]
+impl Alerter for LogAlerter {
+    async fn alert(&self, message: &str) {
+        tracing::error!("ALERT: {}", message);
+    }
+}
+
+/// POSTs alerts as `{"text": ...}` JSON (Slack/Discord-style incoming
+/// webhooks), falling back to the log if delivery fails
+pub struct WebhookAlerter {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookAlerter {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Alerter for WebhookAlerter {
+    async fn alert(&self, message: &str) {
+        let body = serde_json::json!({ "text": message }).to_string();
+        let res = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send()
+            .await;
+
+        if let Err(e) = res.and_then(|r| r.error_for_status()) {
+            tracing::error!("ALERT (webhook failed: {:?}): {}", e, message);
+        }
+    }
+}
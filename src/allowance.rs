@@ -0,0 +1,237 @@
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use ethers::{
+    abi::AbiEncode,
+    providers::Middleware,
+    types::{Address, H256 as TxHash, U256},
+};
+use serde::Serialize;
+
+use crate::{
+    bindings::erc20::{ApproveCall, IERC20},
+    config::{ApprovalMode, ExecutionMode, Venue},
+    execution_context::ExecutionContext,
+    inventory::Inventory,
+};
+
+/// A managed approval and what it currently allows
+#[derive(Debug, Clone, Serialize)]
+pub struct AllowanceView {
+    pub token: Address,
+    pub spender: Address,
+    pub allowance: U256,
+}
+
+/// Keeps the wallet's ERC20 approvals in line with what it trades.
+///
+/// Every configured (token, spender) pair is checked and approved at
+/// startup; swaps also top up their own allowance before they're built.
+/// Exact mode approves only the wallet's balance or the trade's amount,
+/// infinite mode approves `type(uint256).max` once.
+pub struct AllowanceManager {
+    mode: ApprovalMode,
+    spenders: Vec<(Address, Address)>,
+    /// Approvals sent but not yet seen on chain: (token, spender) -> (amount, tx)
+    pending: DashMap<(Address, Address), (U256, TxHash)>,
+}
+
+impl Default for AllowanceManager {
+    fn default() -> Self {
+        Self::new(ApprovalMode::Exact, Vec::new())
+    }
+}
+
+impl AllowanceManager {
+    pub fn new(mode: ApprovalMode, spenders: Vec<(Address, Address)>) -> Self {
+        Self {
+            mode,
+            spenders,
+            pending: DashMap::new(),
+        }
+    }
+
+    /// `APPROVAL_MODE`, and `APPROVALS` as `token:spender` pairs,
+    /// comma-separated. With an inventory, every held token is also
+    /// approved to every configured venue's router.
+    pub fn from_env(inventory: Option<&Inventory>) -> anyhow::Result<Self> {
+        let mut spenders = Vec::new();
+
+        let raw = std::env::var("APPROVALS").unwrap_or_default();
+        for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (token, spender) = entry
+                .split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("Invalid APPROVALS entry {:?} (expected token:spender)", entry))?;
+            spenders.push((token.trim().parse()?, spender.trim().parse()?));
+        }
+
+        if let Some(inventory) = inventory {
+            let venues = Venue::from_env()?;
+            for target in inventory.targets() {
+                spenders.extend(venues.iter().map(|venue| (target.token.id, venue.router)));
+            }
+        }
+
+        spenders.sort();
+        spenders.dedup();
+        Ok(Self::new(ApprovalMode::from_env()?, spenders))
+    }
+
+    pub fn mode(&self) -> ApprovalMode {
+        self.mode
+    }
+
+    /// Every managed (token, spender) pair
+    pub fn spenders(&self) -> &[(Address, Address)] {
+        &self.spenders
+    }
+
+    /// Make sure `spender` may pull `amount` of `token` from the wallet.
+    /// Returns the approval's hash while one is in flight; the spend won't
+    /// simulate until it's mined.
+    pub async fn ensure<M: Middleware + 'static>(
+        &self,
+        ctx: &ExecutionContext<M>,
+        token: Address,
+        spender: Address,
+        amount: U256,
+    ) -> anyhow::Result<Option<TxHash>> {
+        let provider = ctx.provider();
+        let owner = sender(&*provider)?;
+        if allowance(provider, token, owner, spender).await? >= amount {
+            self.pending.remove(&(token, spender));
+            return Ok(None);
+        }
+
+        if let Some(pending) = self.pending.get(&(token, spender))
+            && pending.0 >= amount
+        {
+            return Ok(Some(pending.1));
+        }
+
+        let target = match self.mode {
+            ApprovalMode::Exact => amount,
+            ApprovalMode::Infinite => U256::MAX,
+        };
+        let hash = self.approve(ctx, token, spender, target).await?;
+        tracing::info!("🔓 Approved {:?} to spend {} of {:?} ({:?})", spender, target, token, hash);
+        Ok(Some(hash))
+    }
+
+    /// Check every managed pair and approve whatever falls short. Exact
+    /// mode covers the wallet's current balance. Dry-run only reports.
+    pub async fn sync<M: Middleware + 'static>(&self, ctx: &ExecutionContext<M>) -> anyhow::Result<()> {
+        let provider = ctx.provider();
+        let owner = sender(&*provider)?;
+
+        for &(token, spender) in &self.spenders {
+            let wanted = match self.mode {
+                ApprovalMode::Exact => IERC20::new(token, provider.clone()).balance_of(owner).call().await?,
+                ApprovalMode::Infinite => infinite(),
+            };
+            let current = allowance(provider.clone(), token, owner, spender).await?;
+            if wanted.is_zero() || current >= wanted {
+                tracing::debug!("Allowance of {:?} for {:?} is {}; nothing to do", token, spender, current);
+                continue;
+            }
+
+            if ctx.mode == ExecutionMode::DryRun {
+                tracing::info!("📝 Would approve {:?} to spend {} of {:?} (now {})", spender, wanted, token, current);
+                continue;
+            }
+            let target = match self.mode {
+                ApprovalMode::Exact => wanted,
+                ApprovalMode::Infinite => U256::MAX,
+            };
+            let hash = self.approve(ctx, token, spender, target).await?;
+            tracing::info!("🔓 Approved {:?} to spend {} of {:?} ({:?})", spender, target, token, hash);
+        }
+
+        Ok(())
+    }
+
+    /// Reset every managed allowance that isn't already zero
+    pub async fn revoke_all<M: Middleware + 'static>(&self, ctx: &ExecutionContext<M>) -> anyhow::Result<Vec<TxHash>> {
+        let provider = ctx.provider();
+        let owner = sender(&*provider)?;
+
+        let mut revoked = Vec::new();
+        for &(token, spender) in &self.spenders {
+            if allowance(provider.clone(), token, owner, spender).await?.is_zero() {
+                continue;
+            }
+            let hash = self.approve(ctx, token, spender, U256::zero()).await?;
+            self.pending.remove(&(token, spender));
+            tracing::warn!("🔒 Revoked {:?}'s allowance of {:?} ({:?})", spender, token, hash);
+            revoked.push(hash);
+        }
+
+        Ok(revoked)
+    }
+
+    /// On-chain allowance of every managed pair
+    pub async fn snapshot<M: Middleware + 'static>(&self, ctx: &ExecutionContext<M>) -> anyhow::Result<Vec<AllowanceView>> {
+        let provider = ctx.provider();
+        let owner = sender(&*provider)?;
+
+        let mut views = Vec::with_capacity(self.spenders.len());
+        for &(token, spender) in &self.spenders {
+            views.push(AllowanceView {
+                token,
+                spender,
+                allowance: allowance(provider.clone(), token, owner, spender).await?,
+            });
+        }
+        Ok(views)
+    }
+
+    async fn approve<M: Middleware + 'static>(
+        &self,
+        ctx: &ExecutionContext<M>,
+        token: Address,
+        spender: Address,
+        amount: U256,
+    ) -> anyhow::Result<TxHash> {
+        if ctx.mode == ExecutionMode::DryRun {
+            anyhow::bail!("Dry-run mode never signs approvals");
+        }
+
+        let provider = ctx.provider();
+        let mut tx = ctx.gas.new_tx();
+        tx.set_from(sender(&*provider)?);
+        tx.set_to(token);
+        tx.set_data(ApproveCall { spender, amount }.encode().into());
+        provider
+            .fill_transaction(&mut tx, None)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fill approval of {:?}: {:?}", token, e))?;
+
+        let hash = ctx.nonces.send(&*provider, tx, U256::zero()).await?;
+        ctx.shutdown.track_tx(hash, ctx.connection.clone());
+        if !amount.is_zero() {
+            self.pending.insert((token, spender), (amount, hash));
+        }
+        Ok(hash)
+    }
+}
+
+async fn allowance<M: Middleware + 'static>(
+    provider: Arc<M>,
+    token: Address,
+    owner: Address,
+    spender: Address,
+) -> anyhow::Result<U256> {
+    Ok(IERC20::new(token, provider).allowance(owner, spender).call().await?)
+}
+
+/// Anything from here up counts as an infinite approval; some tokens
+/// count even `type(uint256).max` down as it's spent
+fn infinite() -> U256 {
+    U256::MAX >> 1
+}
+
+fn sender<M: Middleware>(provider: &M) -> anyhow::Result<Address> {
+    provider
+        .default_sender()
+        .ok_or_else(|| anyhow::anyhow!("Provider has no signer to approve from"))
+}
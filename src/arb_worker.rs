@@ -1,38 +1,49 @@
 
-use tokio::sync::broadcast;
-use ethers::types::H256;
+use tokio::sync::broadcast::{self, error::RecvError};
 use std::sync::Arc;
 use ethers::providers::Middleware;
+use tokio_util::sync::CancellationToken;
 
 use crate::{
+    config::ExecutionMode,
+    controls::OpportunityRecord,
+    execution_context::ExecutionContext,
     helpers::{compute_spread, handle_arb_opportunity, get_token_symbol},
-    types::{DexPairConfig, PriceTracker},
+    metrics::METRICS,
+    store::{self, StoreEvent},
+    types::{BlockEvent, DexPairConfig, PriceTracker},
 };
 
 /// ArbitrageWorker
 /// - Listens to new block broadcasts
 /// - Fetches latest prices for a given pair across all DEXes
 /// - Computes spreads
-/// - Executes profitable opportunities (> 0.2%)
+/// - Executes opportunities above the pair's threshold (0.2% by default)
+/// - Honours pauses and thresholds set through `BotControls`
+/// - Stops between blocks once its pair is removed or shutdown is triggered
 pub struct ArbWorker<M> {
-    receiver: broadcast::Receiver<H256>,
+    receiver: broadcast::Receiver<BlockEvent>,
     tracker: Arc<PriceTracker>,
     pair: DexPairConfig,
-    provider: Arc<M>,
+    ctx: Arc<ExecutionContext<M>>,
+    /// Cancelled when the pair leaves the config; a child of the shutdown token
+    stop: CancellationToken,
 }
 
 impl<M: Middleware + 'static> ArbWorker<M> {
     pub fn new(
-        receiver: broadcast::Receiver<H256>,
+        receiver: broadcast::Receiver<BlockEvent>,
         tracker: Arc<PriceTracker>,
         pair: DexPairConfig,
-        provider: Arc<M>,
+        ctx: Arc<ExecutionContext<M>>,
+        stop: CancellationToken,
     ) -> Self {
         Self {
             receiver,
             tracker,
             pair,
-            provider,
+            ctx,
+            stop,
         }
     }
 
@@ -43,14 +54,48 @@ impl<M: Middleware + 'static> ArbWorker<M> {
             self.pair.quote
         );
 
-        while let Ok(block_hash) = self.receiver.recv().await {
-            tracing::debug!("🔹 New block received: {:?}", block_hash);
+        let token = self.stop.clone();
 
-            // get all known DEX prices for the pair
-            let prices = self
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => break,
+                event = self.receiver.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(missed)) => {
+                        // Prices live in the tracker, so skipping stale heads is harmless
+                        METRICS.broadcast_lag.inc_by(missed);
+                        tracing::warn!("Worker lagged behind by {} block events", missed);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            };
+            let block = match event {
+                BlockEvent::NewBlock(block) => block,
+                BlockEvent::Reorg { common_ancestor, removed, .. } => {
+                    tracing::debug!(
+                        "Reorg above #{} ({} blocks orphaned); waiting for new head",
+                        common_ancestor,
+                        removed.len()
+                    );
+                    continue;
+                }
+            };
+            tracing::debug!("🔹 New block received: #{} {:?}", block.number, block.hash);
+            let provider = self.ctx.provider();
+            let controls = &self.ctx.controls;
+
+            if controls.is_pair_paused(self.pair.base.id, self.pair.quote.id) {
+                tracing::debug!("Pair paused; skipping block #{}", block.number);
+                continue;
+            }
+
+            // get all known DEX prices for the pair, minus paused venues
+            let mut prices = self
                 .tracker
                 .get_all_for_pair(self.pair.base.id, self.pair.quote.id)
                 .await?;
+            prices.retain(|(key, _)| !controls.is_dex_paused(key.dex_factory));
 
             if prices.len() < 2 {
                 tracing::debug!("Not enough DEX prices available for pair");
@@ -60,7 +105,10 @@ impl<M: Middleware + 'static> ArbWorker<M> {
             // compute spread between best buy/sell
             match compute_spread(&prices) {
                 Ok((spread, buy_key, sell_key)) => {
-                    if spread >= 0.002 {
+                    METRICS.spread_bps.observe(spread * 10_000.0);
+                    let threshold = controls.threshold(self.pair.base.id, self.pair.quote.id);
+                    if spread >= threshold {
+                        METRICS.opportunity("detected");
                         tracing::info!(
                             "💰 Profitable spread detected: {:.3}% between {:?} and {:?}",
                             spread * 100.0,
@@ -68,23 +116,52 @@ impl<M: Middleware + 'static> ArbWorker<M> {
                             sell_key.dex_factory
                         );
 
-                        if let Err(e) = handle_arb_opportunity(
+                        let result = handle_arb_opportunity(
                             buy_key,
                             sell_key,
                             spread,
                             &self.pair,
-                            self.provider.clone(),
+                            &block,
+                            &self.ctx,
                         )
-                        .await
-                        {
-                            tracing::warn!("Failed to handle arb opportunity: {:?}", e);
-                        }
+                        .await;
+
+                        let outcome = match &result {
+                            Ok(Some(_)) => "submitted".to_string(),
+                            Ok(None) if self.ctx.mode == ExecutionMode::DryRun => "paper".to_string(),
+                            Ok(None) => "skipped".to_string(),
+                            Err(e) => {
+                                tracing::warn!("Failed to handle arb opportunity: {:?}", e);
+                                format!("{:?}", e)
+                            }
+                        };
+                        let price_of = |key| {
+                            prices.iter().find(|(k, _)| *k == key).map_or(0.0, |(_, p)| p.price)
+                        };
+                        let record = OpportunityRecord {
+                            at: std::time::SystemTime::now()
+                                .duration_since(std::time::UNIX_EPOCH)
+                                .map_or(0, |d| d.as_secs()),
+                            block: block.number,
+                            base: self.pair.base.id,
+                            quote: self.pair.quote.id,
+                            buy_factory: buy_key.dex_factory,
+                            sell_factory: sell_key.dex_factory,
+                            buy_price: price_of(buy_key),
+                            sell_price: price_of(sell_key),
+                            spread,
+                            threshold,
+                            tx_hash: result.ok().flatten(),
+                            outcome,
+                        };
+                        store::record(StoreEvent::Opportunity(record.clone()));
+                        controls.record(record);
                     } else {
                         tracing::debug!(
                             "Spread {:.3}% below threshold for pair {:?}/{:?}",
                             spread * 100.0,
-                            get_token_symbol(self.pair.base.id, self.provider.clone()).await,
-                            get_token_symbol(self.pair.base.id, self.provider.clone()).await,
+                            get_token_symbol(self.pair.base.id, provider.clone()).await,
+                            get_token_symbol(self.pair.base.id, provider.clone()).await,
                         );
                     }
                 }
@@ -7,5 +7,9 @@ abigen!(
         function name() external view returns (string)
         function symbol() external view returns (string)
         function decimals() external view returns (uint8)
+        function balanceOf(address account) external view returns (uint256)
+        function allowance(address owner, address spender) external view returns (uint256)
+        function approve(address spender, uint256 amount) external returns (bool)
+        function transfer(address to, uint256 amount) external returns (bool)
     ]"#
 );
\ No newline at end of file
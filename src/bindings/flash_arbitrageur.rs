@@ -0,0 +1,11 @@
+use ethers::contract::abigen;
+
+abigen!(
+    FlashArbitrageur,
+    r#"[
+        error Unauthorized()
+        error InsufficientProfit(uint256 profit, uint256 minProfit)
+        error SwapFailed(address router, bytes reason)
+        error InsufficientOutput(uint256 amountOut, uint256 minAmountOut)
+    ]"#
+);
@@ -0,0 +1,25 @@
+use ethers::contract::abigen;
+
+abigen!(
+    AaveV3Pool,
+    r#"[
+        struct ReserveConfigurationMap { uint256 data; }
+        struct ReserveData { ReserveConfigurationMap configuration; uint128 liquidityIndex; uint128 currentLiquidityRate; uint128 variableBorrowIndex; uint128 currentVariableBorrowRate; uint128 currentStableBorrowRate; uint40 lastUpdateTimestamp; uint16 id; address aTokenAddress; address stableDebtTokenAddress; address variableDebtTokenAddress; address interestRateStrategyAddress; uint128 accruedToTreasury; uint128 unbacked; uint128 isolationModeTotalDebt; }
+        function FLASHLOAN_PREMIUM_TOTAL() external view returns (uint128)
+        function getReserveData(address asset) external view returns (ReserveData)
+    ]"#
+);
+
+abigen!(
+    BalancerVault,
+    r#"[
+        function getProtocolFeesCollector() external view returns (address)
+    ]"#
+);
+
+abigen!(
+    BalancerProtocolFeesCollector,
+    r#"[
+        function getFlashLoanFeePercentage() external view returns (uint256)
+    ]"#
+);
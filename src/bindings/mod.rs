@@ -1,4 +1,7 @@
 pub mod uniswapv2;
 pub mod uniswapv3;
 pub mod quickswapv3;
+pub mod sushiswapv3;
 pub mod erc20;
+pub mod flash_arbitrageur;
+pub mod flash_lenders;
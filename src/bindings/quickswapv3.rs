@@ -7,6 +7,8 @@ abigen!(
         event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 price,uint128 liquidity,int24 tick)
         function token0() external view returns (address)
         function token1() external view returns (address)
+        function liquidity() external view returns (uint128)
+        function globalState() external view returns (uint160 price, int24 tick, uint16 fee, uint16 timepointIndex, uint8 communityFeeToken0, uint8 communityFeeToken1, bool unlocked)
     ]"#
 );
 
@@ -16,4 +18,12 @@ abigen!(
         function poolByPair(address,address) external view returns (address)
         function createPool(address,address) external returns (address)
     ]"#
-);
\ No newline at end of file
+);
+
+abigen!(
+    AlgebraRouter,
+    r#"[
+        struct ExactInputSingleParams { address tokenIn; address tokenOut; address recipient; uint256 deadline; uint256 amountIn; uint256 amountOutMinimum; uint160 limitSqrtPrice; }
+        function exactInputSingle(ExactInputSingleParams params) external payable returns (uint256 amountOut)
+    ]"#
+);
@@ -0,0 +1,10 @@
+use ethers::contract::abigen;
+
+// The original Uniswap V3 SwapRouter, deadline in the params; SushiSwap V3 deploys this one
+abigen!(
+    SwapRouter,
+    r#"[
+        struct ExactInputSingleParams { address tokenIn; address tokenOut; uint24 fee; address recipient; uint256 deadline; uint256 amountIn; uint256 amountOutMinimum; uint160 sqrtPriceLimitX96; }
+        function exactInputSingle(ExactInputSingleParams params) external payable returns (uint256 amountOut)
+    ]"#
+);
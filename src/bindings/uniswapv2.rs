@@ -14,6 +14,14 @@ abigen!(
         function token0() external view returns (address)
         function token1() external view returns (address)
         function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast)
+        function swap(uint256 amount0Out, uint256 amount1Out, address to, bytes data) external
         event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to)
     ]"#
-);
\ No newline at end of file
+);
+
+abigen!(
+    UniswapV2Router,
+    r#"[
+        function swapExactTokensForTokens(uint256 amountIn, uint256 amountOutMin, address[] path, address to, uint256 deadline) external returns (uint256[] amounts)
+    ]"#
+);
@@ -12,7 +12,19 @@ abigen! (
     r#"[
         function token0() external view returns (address)
         function token1() external view returns (address)
+        function fee() external view returns (uint24)
+        function liquidity() external view returns (uint128)
+        function slot0() external view returns (uint160 sqrtPriceX96, int24 tick, uint16 observationIndex, uint16 observationCardinality, uint16 observationCardinalityNext, uint8 feeProtocol, bool unlocked)
+        function swap(address recipient, bool zeroForOne, int256 amountSpecified, uint160 sqrtPriceLimitX96, bytes data) external returns (int256 amount0, int256 amount1)
         event Swap(address indexed sender, address indexed recipient, int256 amount0,int256 amount1,uint160 sqrtPriceX96,uint128 liquidity,int24 tick)
 
     ]"#
-);
\ No newline at end of file
+);
+
+abigen!(
+    SwapRouter02,
+    r#"[
+        struct ExactInputSingleParams { address tokenIn; address tokenOut; uint24 fee; address recipient; uint256 amountIn; uint256 amountOutMinimum; uint160 sqrtPriceLimitX96; }
+        function exactInputSingle(ExactInputSingleParams params) external payable returns (uint256 amountOut)
+    ]"#
+);
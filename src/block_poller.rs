@@ -0,0 +1,68 @@
+use std::{sync::Arc, time::Duration};
+
+use ethers::{providers::Middleware, types::BlockNumber};
+
+use crate::{
+    block_watcher::BlockWatcher,
+    constants::MAX_POLL_BLOCK_RANGE,
+    dex_price_listener::multiplexer::LogMultiplexer,
+};
+
+/// Pubsub-free ingestion: polls `eth_blockNumber` and pulls pool logs for
+/// each new block range with `eth_getLogs`, then announces the new head.
+///
+/// Feeds the same multiplexer (decoding, tracker) and block broadcast as
+/// the websocket subscriptions, so workers cannot tell the modes apart.
+#[derive(Debug, Clone)]
+pub struct BlockPoller {
+    interval: Duration,
+}
+
+impl BlockPoller {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval }
+    }
+
+    /// Poll until an RPC call fails. `cursor` is the last fully processed
+    /// block and survives across calls, so a reconnect resumes where the
+    /// previous session stopped.
+    pub async fn run<M: Middleware + 'static>(
+        &self,
+        provider: Arc<M>,
+        block_watcher: &BlockWatcher,
+        multiplexer: &LogMultiplexer<M>,
+        cursor: &mut Option<u64>,
+    ) -> anyhow::Result<()> {
+        let mut ticker = tokio::time::interval(self.interval);
+        tracing::info!("🟢 Polling for blocks every {:?}", self.interval);
+
+        loop {
+            ticker.tick().await;
+
+            let head = provider
+                .get_block_number()
+                .await
+                .map_err(|e| anyhow::anyhow!("eth_blockNumber failed: {:?}", e))?
+                .as_u64();
+
+            let from = cursor.map(|c| c + 1).unwrap_or(head);
+            if from > head {
+                continue;
+            }
+
+            // Chunk long ranges to stay under provider eth_getLogs limits
+            let mut start = from;
+            while start <= head {
+                let end = (start + MAX_POLL_BLOCK_RANGE - 1).min(head);
+                multiplexer.poll_range(provider.clone(), start, end).await?;
+                *cursor = Some(end);
+                start = end + 1;
+            }
+
+            let header = block_watcher
+                .fetch_header(provider.as_ref(), BlockNumber::Number(head.into()).into())
+                .await?;
+            block_watcher.ingest(provider.clone(), header, multiplexer).await?;
+        }
+    }
+}
@@ -1,28 +1,196 @@
-use std::sync::Arc;
-use ethers::{providers::Middleware, types::H256};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, atomic::{AtomicU64, Ordering}},
+};
+use ethers::{providers::Middleware, types::{BlockId, H256}};
 use futures::StreamExt;
-use tokio::sync::broadcast;
+use tokio::sync::{Mutex, broadcast};
 
-pub struct BlockWatcher<M> {
-    provider: Arc<M>,
-    sender: broadcast::Sender<H256>,
+use crate::{
+    constants::CHAIN_BUFFER_LEN,
+    dex_price_listener::multiplexer::LogMultiplexer,
+    recorder::{self, RecordedEvent},
+    rpc_pool::RpcPool,
+    types::{BlockEvent, BlockInfo},
+};
+
+/// The most recent canonical headers, oldest first
+#[derive(Debug, Default)]
+pub struct ChainBuffer {
+    blocks: VecDeque<BlockInfo>,
+}
+
+impl ChainBuffer {
+    pub fn head(&self) -> Option<&BlockInfo> {
+        self.blocks.back()
+    }
+
+    pub fn oldest(&self) -> Option<&BlockInfo> {
+        self.blocks.front()
+    }
+
+    pub fn contains(&self, hash: H256) -> bool {
+        self.blocks.iter().any(|b| b.hash == hash)
+    }
+
+    /// Attach a contiguous segment (oldest first) to the buffer. If the
+    /// segment branches off below the current head, the orphaned blocks
+    /// are dropped and reported as a reorg.
+    pub fn extend(&mut self, segment: Vec<BlockInfo>) -> Option<BlockEvent> {
+        let first = segment.first()?;
+
+        let event = match self.blocks.iter().position(|b| b.hash == first.parent_hash) {
+            Some(ancestor) if ancestor + 1 < self.blocks.len() => {
+                let removed: Vec<BlockInfo> = self.blocks.drain(ancestor + 1..).collect();
+                Some(BlockEvent::Reorg {
+                    common_ancestor: self.blocks[ancestor].number,
+                    removed,
+                    added: segment.clone(),
+                })
+            }
+            Some(_) => None,
+            // Unconnected (first block, or a gap deeper than the buffer): start over
+            None => {
+                self.blocks.clear();
+                None
+            }
+        };
+
+        self.blocks.extend(segment);
+        while self.blocks.len() > CHAIN_BUFFER_LEN {
+            self.blocks.pop_front();
+        }
+
+        event
+    }
 }
 
-impl<M: Middleware + 'static> BlockWatcher<M> {
-    pub fn new(provider: Arc<M>, sender: broadcast::Sender<H256>) -> Self {
-        Self { provider, sender }
+/// Turns new block hashes (or polled heads) into `BlockInfo` broadcasts,
+/// tracking a short canonical chain to detect reorgs
+pub struct BlockWatcher {
+    sender: broadcast::Sender<BlockEvent>,
+    rpc_pool: Arc<RpcPool>,
+    chain: Mutex<ChainBuffer>,
+    /// Number of the last head ingested (0 = none yet)
+    last_head: AtomicU64,
+}
+
+impl BlockWatcher {
+    pub fn new(sender: broadcast::Sender<BlockEvent>, rpc_pool: Arc<RpcPool>) -> Self {
+        Self {
+            sender,
+            rpc_pool,
+            chain: Mutex::new(ChainBuffer::default()),
+            last_head: AtomicU64::new(0),
+        }
+    }
+
+    /// Last head ingested, used as the backfill cursor after a reconnect
+    pub fn last_head(&self) -> Option<u64> {
+        match self.last_head.load(Ordering::Relaxed) {
+            0 => None,
+            b => Some(b),
+        }
     }
 
-    pub async fn start(&self) -> anyhow::Result<()> {
-        let mut stream = self.provider.watch_blocks().await?;
+    /// Broadcast new blocks until the provider's block stream ends
+    pub async fn start<M: Middleware + 'static>(
+        &self,
+        provider: Arc<M>,
+        multiplexer: &LogMultiplexer<M>,
+    ) -> anyhow::Result<()> {
+        let mut stream = provider
+            .watch_blocks()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to watch blocks: {:?}", e))?;
         tracing::info!("🟢 Block watcher started");
 
         while let Some(block_hash) = stream.next().await {
-            if let Err(_) = self.sender.send(block_hash) {
-                tracing::warn!("⚠️ No active workers; block watcher idle");
+            let res = match self.fetch_header(provider.as_ref(), block_hash.into()).await {
+                Ok(header) => self.ingest(provider.clone(), header, multiplexer).await,
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = res {
+                tracing::warn!("Failed to process block {:?}: {:?}", block_hash, e);
             }
         }
 
+        anyhow::bail!("Block stream ended")
+    }
+
+    /// Fetch a header, raced across HTTP endpoints when enabled
+    pub async fn fetch_header<M: Middleware>(&self, provider: &M, id: BlockId) -> anyhow::Result<BlockInfo> {
+        let block = if self.rpc_pool.race_reads {
+            self.rpc_pool.race_get_block(id).await?
+        } else {
+            provider
+                .get_block(id)
+                .await
+                .map_err(|e| anyhow::anyhow!("eth_getBlock failed: {:?}", e))?
+                .ok_or_else(|| anyhow::anyhow!("Block {:?} not found", id))?
+        };
+
+        BlockInfo::try_from(&block)
+    }
+
+    /// Link a new head into the canonical chain, fetching any missing
+    /// ancestors. On a reorg, prices are rolled back and the new branch
+    /// replayed before the reorg and the new block are broadcast, so
+    /// workers never evaluate the new head against orphaned prices.
+    pub async fn ingest<M: Middleware + 'static>(
+        &self,
+        provider: Arc<M>,
+        header: BlockInfo,
+        multiplexer: &LogMultiplexer<M>,
+    ) -> anyhow::Result<()> {
+        recorder::record(|| RecordedEvent::Block(header.clone()));
+        let mut chain = self.chain.lock().await;
+
+        if chain.contains(header.hash) {
+            return Ok(());
+        }
+
+        // Walk back until the segment connects to a buffered block
+        let mut segment = vec![header];
+        loop {
+            let first = segment.last().expect("segment is never empty");
+            let connected = chain.contains(first.parent_hash);
+            let below_buffer = chain.oldest().is_none_or(|o| first.number <= o.number);
+
+            // A replay has only the recorded heads to go on
+            if connected || below_buffer || segment.len() >= CHAIN_BUFFER_LEN || multiplexer.is_replaying() {
+                break;
+            }
+
+            let parent = self.fetch_header(provider.as_ref(), first.parent_hash.into()).await?;
+            segment.push(parent);
+        }
+        segment.reverse();
+
+        let head = segment.last().cloned().expect("segment is never empty");
+
+        if let Some(event) = chain.extend(segment) {
+            if let BlockEvent::Reorg { common_ancestor, removed, added } = &event {
+                tracing::warn!(
+                    "🔀 Reorg detected: {} block(s) orphaned above #{}",
+                    removed.len(),
+                    common_ancestor
+                );
+                multiplexer.handle_reorg(provider.clone(), removed, added).await?;
+            }
+            self.publish(event);
+        }
+
+        self.last_head.store(head.number, Ordering::Relaxed);
+        self.publish(BlockEvent::NewBlock(head));
         Ok(())
     }
+
+    /// Announce a block event to every worker
+    pub fn publish(&self, event: BlockEvent) {
+        if self.sender.send(event).is_err() {
+            tracing::warn!("⚠️ No active workers; block watcher idle");
+        }
+    }
 }
@@ -1,23 +1,210 @@
 use ethers::{providers::Middleware, types::{Address, U256}};
 use crate::{
     constants::*,
-    dex_pool_resolver::{quickswapv3_resolver::QuickSwapV3Resolver, uniswapv2_resolver::UniswapV2Resolver, uniswapv3_resolver::UniswapV3Resolver},
+    dex_adapter::DexRegistry,
     helpers::make_pair,
-    types::{DexConfig, DexPairConfig, DexType, Token},
+    types::{DexConfig, DexPairConfig, Token},
 };
 use std::sync::Arc;
 use futures::future::join_all;
 use anyhow::Result;
 
-pub async fn build_target_configs<M: Middleware + 'static>(
-    provider: Arc<M>,
-) -> Result<Vec<DexConfig>> {
-    // --- Factories  ---
-    let quickswap_factory: Address = QUICKSWAP_FACTORY.parse()?;
-    let sushiswapv2_factory: Address = SUSHISWAPV2_FACTORY.parse()?;
-    let uniswapv3_factory: Address = UNISWAPV3_FACTORY.parse()?;
-    let quickswapv3_factory: Address = SUSHISWAPV3_FACTORY.parse()?;
+/// How pool logs and new blocks reach the bot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestionMode {
+    /// `eth_subscribe` over a websocket endpoint
+    Ws,
+    /// `eth_blockNumber` + `eth_getLogs` polling over HTTP
+    Http,
+    /// Recorded blocks and logs from `REPLAY_DIR`; state reads still go
+    /// over HTTP
+    Replay,
+}
+
+impl IngestionMode {
+    /// `INGESTION_MODE` env var: "ws" (default), "http" or "replay"
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("INGESTION_MODE")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "" | "ws" => Ok(Self::Ws),
+            "http" => Ok(Self::Http),
+            "replay" => Ok(Self::Replay),
+            other => anyhow::bail!("Unknown INGESTION_MODE {:?} (expected ws, http or replay)", other),
+        }
+    }
+}
+
+/// What `handle_arb_opportunity` does with an opportunity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionMode {
+    /// Sign and send the arbitrage transaction
+    Live,
+    /// Simulate with `eth_call` and record the would-be PnL; never signs
+    DryRun,
+}
+
+impl ExecutionMode {
+    /// `EXECUTION_MODE` env var: "live" (default) or "dry-run" / "paper"
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("EXECUTION_MODE")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "" | "live" => Ok(Self::Live),
+            "dry-run" | "dryrun" | "paper" => Ok(Self::DryRun),
+            other => anyhow::bail!("Unknown EXECUTION_MODE {:?} (expected live or dry-run)", other),
+        }
+    }
+}
+
+/// Where a live trade's working capital comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FundingMode {
+    /// Borrowed per trade by the arb contract
+    Flash,
+    /// The wallet's own balances, swapped leg by leg through the routers
+    Inventory,
+}
+
+impl FundingMode {
+    /// `FUNDING_MODE` env var: "flash" (default) or "inventory"
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("FUNDING_MODE")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "" | "flash" => Ok(Self::Flash),
+            "inventory" => Ok(Self::Inventory),
+            other => anyhow::bail!("Unknown FUNDING_MODE {:?} (expected flash or inventory)", other),
+        }
+    }
+}
+
+/// How much spenders are approved for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalMode {
+    /// Just what the wallet holds, or what the trade needs, at the time
+    Exact,
+    /// `type(uint256).max`, approved once
+    Infinite,
+}
+
+impl ApprovalMode {
+    /// `APPROVAL_MODE` env var: "exact" (default) or "infinite"
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("APPROVAL_MODE")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "" | "exact" => Ok(Self::Exact),
+            "infinite" | "max" => Ok(Self::Infinite),
+            other => anyhow::bail!("Unknown APPROVAL_MODE {:?} (expected exact or infinite)", other),
+        }
+    }
+}
+
+/// How arb transactions bid for inclusion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasMode {
+    /// Type-2 transactions with `maxFeePerGas` / `maxPriorityFeePerGas`
+    Eip1559,
+    /// Type-0 transactions with a single gas price
+    Legacy,
+}
+
+impl GasMode {
+    /// `GAS_MODE` env var: "eip1559" (default) or "legacy"
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("GAS_MODE")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "" | "eip1559" | "1559" => Ok(Self::Eip1559),
+            "legacy" => Ok(Self::Legacy),
+            other => anyhow::bail!("Unknown GAS_MODE {:?} (expected eip1559 or legacy)", other),
+        }
+    }
+}
+
+/// A DEX deployment the bot watches and trades on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Venue {
+    pub name: String,
+    pub factory: Address,
+    /// Router swaps go through, and the wallet approves
+    pub router: Address,
+    /// Registry key of the `DexAdapter` driving it
+    pub adapter: String,
+}
+
+impl Venue {
+    /// The Polygon venues the bot ships with
+    pub fn defaults() -> Result<Vec<Self>> {
+        [
+            ("quickswapv2", QUICKSWAP_FACTORY, QUICKSWAP_ROUTER, "uniswapv2"),
+            ("sushiswapv2", SUSHISWAPV2_FACTORY, SUSHISWAPV2_ROUTER, "uniswapv2"),
+            ("uniswapv3", UNISWAPV3_FACTORY, UNISWAPV3_ROUTER, "uniswapv3"),
+            ("sushiswapv3", SUSHISWAPV3_FACTORY, SUSHISWAPV3_ROUTER, "sushiswapv3"),
+            ("quickswapv3", QUICKSWAPV3_FACTORY, QUICKSWAPV3_ROUTER, "quickswapv3"),
+        ]
+        .into_iter()
+        .map(|(name, factory, router, adapter)| {
+            Ok(Self {
+                name: name.to_string(),
+                factory: factory.parse()?,
+                router: router.parse()?,
+                adapter: adapter.to_string(),
+            })
+        })
+        .collect()
+    }
+
+    /// `VENUES` as `name:factory:router:adapter` entries, comma-separated.
+    /// Unset or empty keeps the defaults.
+    pub fn from_env() -> Result<Vec<Self>> {
+        let raw = std::env::var("VENUES").unwrap_or_default();
+        if raw.trim().is_empty() {
+            return Self::defaults();
+        }
+
+        raw.split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(|entry| {
+                let parts: Vec<&str> = entry.split(':').map(str::trim).collect();
+                let [name, factory, router, adapter] = parts[..] else {
+                    anyhow::bail!("Invalid VENUES entry {:?} (expected name:factory:router:adapter)", entry);
+                };
+                Ok(Self {
+                    name: name.to_string(),
+                    factory: factory.parse()?,
+                    router: router.parse()?,
+                    adapter: adapter.to_string(),
+                })
+            })
+            .collect()
+    }
+}
 
+pub async fn build_target_configs<M>(
+    provider: Arc<M>,
+    registry: &DexRegistry<M>,
+) -> Result<Vec<DexConfig>>
+where
+    M: Middleware + 'static,
+{
     // --- Tokens ---
     let weth: Address = WETH.parse()?;
     let wbtc: Address = WBTC.parse()?;
@@ -66,112 +253,44 @@ pub async fn build_target_configs<M: Middleware + 'static>(
         }
     };
 
-    // --- Build QuickSwap pairs in parallel ---
-    let quick_pairs = join_all(token_pairs.iter().map(|(base, quote)| {
-        let provider = provider.clone();
-        async move {
-            make_pair::<M, UniswapV2Resolver>(
-                quickswap_factory,
-                base.clone(),
-                quote.clone(),
-                select_trade_size(quote.id),
-                provider.clone(),
-            )
-            .await
-        }
-    }))
-    .await
-    .into_iter()
-    .filter_map(Result::ok) // skip failing pairs
-    .collect::<Vec<DexPairConfig>>();
-
-    // --- Build SushiSwap pairs in parallel ---
-    let sushiv2_pairs = join_all(token_pairs.iter().map(|(base, quote)| {
-        let provider = provider.clone();
-        async move {
-            make_pair::<M, UniswapV2Resolver>(
-                sushiswapv2_factory,
-                base.clone(),
-                quote.clone(),
-                select_trade_size(quote.id),
-                provider.clone(),
-            )
-            .await
-        }
-    }))
-    .await
-    .into_iter()
-    .filter_map(Result::ok)
-    .collect::<Vec<DexPairConfig>>();
-
-    let uniswap_pairs = join_all(token_pairs.iter().map(|(base, quote)| {
-        let provider = provider.clone();
-        async move {
-            make_pair::<M, UniswapV3Resolver<3000>>(
-                uniswapv3_factory, 
-                base.clone(), 
-                quote.clone(), 
-                select_trade_size(quote.id), 
-                provider
-            )
-            .await
-        }
-    }))
-    .await
-    .into_iter()
-    .filter_map(Result::ok)
-    .collect::<Vec<DexPairConfig>>();
-
-
-    let quickv3_pairs = join_all(token_pairs.iter().map(|(base, quote)| {
-        let provider = provider.clone();
-        async move {
-            make_pair::<M, QuickSwapV3Resolver>(
-                quickswapv3_factory, 
-                base.clone(), 
-                quote.clone(), 
-                select_trade_size(quote.id), 
-                provider
-            )
-            .await
-        }
-    }))
-    .await
-    .into_iter()
-    .filter_map(Result::ok)
-    .collect::<Vec<DexPairConfig>>();
-
-
-
-    // --- Assemble into DexConfig structs ---
-    let configs = vec![
-        DexConfig {
-            name: String::from("quickswapv2"),
-            factory: quickswap_factory,
-            pairs: quick_pairs,
-            dex_type: DexType::V2
-        },
-        DexConfig {
-            name: String::from("sushiswapv2"),
-            factory: sushiswapv2_factory,
-            pairs: sushiv2_pairs,
-            dex_type: DexType::V2
-        },
-
-        DexConfig {
-            name: String::from("uniswapv3"),
-            factory: uniswapv3_factory,
-            pairs: uniswap_pairs,
-            dex_type: DexType::V3
-        },
-
-        DexConfig {
-            name: String::from("quickswapv3"),
-            factory: quickswapv3_factory,
-            pairs: quickv3_pairs,
-            dex_type: DexType::QuickSwap
-        }
-    ];
+    // --- Venues ---
+    let venues = Venue::from_env()?;
+
+    // --- Resolve every venue's pairs in parallel ---
+    let mut configs = Vec::with_capacity(venues.len());
+
+    for venue in venues {
+        let adapter = registry.get(&venue.adapter)?;
+        let factory = venue.factory;
+
+        let pairs = join_all(token_pairs.iter().map(|(base, quote)| {
+            let provider = provider.clone();
+            let adapter = adapter.clone();
+            async move {
+                make_pair(
+                    factory,
+                    base.clone(),
+                    quote.clone(),
+                    select_trade_size(quote.id),
+                    adapter,
+                    provider,
+                )
+                .await
+            }
+        }))
+        .await
+        .into_iter()
+        .filter_map(Result::ok) // skip failing pairs
+        .collect::<Vec<DexPairConfig>>();
+
+        configs.push(DexConfig {
+            name: venue.name,
+            factory,
+            router: venue.router,
+            pairs,
+            adapter: venue.adapter,
+        });
+    }
 
     Ok(configs)
 }
@@ -0,0 +1,164 @@
+use std::{
+    sync::{Arc, RwLock, atomic::{AtomicU64, Ordering}},
+    time::Duration,
+};
+
+use ethers::providers::{Middleware, PubsubClient};
+use futures::future::BoxFuture;
+
+use crate::{
+    block_poller::BlockPoller,
+    block_watcher::BlockWatcher,
+    constants::{RECONNECT_INITIAL_BACKOFF_MS, RECONNECT_MAX_BACKOFF_MS},
+    dex_price_listener::multiplexer::LogMultiplexer,
+    metrics::METRICS,
+};
+
+/// Builds a fresh provider connection
+pub type Connector<M> = Arc<dyn Fn() -> BoxFuture<'static, anyhow::Result<Arc<M>>> + Send + Sync>;
+
+/// Exponential backoff between reconnection attempts
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Backoff {
+    /// Delay before the given (zero-based) attempt: initial * 2^attempt, capped at max
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.initial
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max)
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(RECONNECT_INITIAL_BACKOFF_MS),
+            max: Duration::from_millis(RECONNECT_MAX_BACKOFF_MS),
+        }
+    }
+}
+
+/// Owns the live provider connection.
+///
+/// Subscriptions (blocks and pool logs) run against the current provider;
+/// when either ends the connection is considered dropped, a new one is
+/// established with exponential backoff, and both subscriptions are
+/// re-created. Logs missed while disconnected are backfilled with
+/// `eth_getLogs` from the last ingested head. In polling mode a failed
+/// poll triggers the same reconnect and polling resumes from its cursor.
+pub struct ConnectionManager<M> {
+    connector: Connector<M>,
+    current: RwLock<Arc<M>>,
+    backoff: Backoff,
+    reconnects: AtomicU64,
+}
+
+impl<M: Middleware + 'static> ConnectionManager<M> {
+    /// Establish the initial connection, retrying with backoff
+    pub async fn connect(connector: Connector<M>, backoff: Backoff) -> Self {
+        let provider = Self::connect_with_backoff(&connector, backoff).await;
+
+        Self {
+            connector,
+            current: RwLock::new(provider),
+            backoff,
+            reconnects: AtomicU64::new(0),
+        }
+    }
+
+    /// The provider of the current connection
+    pub fn provider(&self) -> Arc<M> {
+        self.current.read().expect("provider lock poisoned").clone()
+    }
+
+    /// Number of reconnections since startup
+    pub fn reconnect_count(&self) -> u64 {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+
+    /// Replace the current connection with a fresh one
+    pub async fn reconnect(&self) -> Arc<M> {
+        let provider = Self::connect_with_backoff(&self.connector, self.backoff).await;
+        *self.current.write().expect("provider lock poisoned") = provider.clone();
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+        METRICS.ws_reconnects.inc();
+        tracing::info!("🔌 Reconnected to RPC ({} reconnects so far)", self.reconnect_count());
+        provider
+    }
+
+    /// Keep HTTP polling alive, failing over to a new connection whenever a
+    /// poll fails
+    pub async fn run_polling(
+        &self,
+        poller: BlockPoller,
+        block_watcher: Arc<BlockWatcher>,
+        multiplexer: Arc<LogMultiplexer<M>>,
+    ) -> anyhow::Result<()> {
+        let mut provider = self.provider();
+        // Resume after the last head a previous run ingested, if any
+        let mut cursor = block_watcher.last_head();
+
+        loop {
+            if let Err(e) = poller
+                .run(provider.clone(), &block_watcher, &multiplexer, &mut cursor)
+                .await
+            {
+                tracing::warn!("⚠️ Block polling failed: {:?}", e);
+            }
+
+            provider = self.reconnect().await;
+        }
+    }
+
+    async fn connect_with_backoff(connector: &Connector<M>, backoff: Backoff) -> Arc<M> {
+        let mut attempt = 0;
+        loop {
+            match connector().await {
+                Ok(provider) => return provider,
+                Err(e) => {
+                    let delay = backoff.delay(attempt);
+                    tracing::warn!(
+                        "RPC connection attempt {} failed: {:?}; retrying in {:?}",
+                        attempt + 1,
+                        e,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt = attempt.saturating_add(1);
+                }
+            }
+        }
+    }
+}
+
+impl<M> ConnectionManager<M>
+where
+    M: Middleware + 'static,
+    <M as Middleware>::Provider: PubsubClient,
+{
+    /// Keep the block and log subscriptions alive across disconnects
+    pub async fn run(
+        &self,
+        block_watcher: Arc<BlockWatcher>,
+        multiplexer: Arc<LogMultiplexer<M>>,
+    ) -> anyhow::Result<()> {
+        let mut provider = self.provider();
+
+        loop {
+            tokio::select! {
+                res = block_watcher.start(provider.clone(), &multiplexer) => {
+                    tracing::warn!("⚠️ Block subscription dropped: {:?}", res);
+                }
+                res = multiplexer.run(provider.clone(), &block_watcher) => {
+                    tracing::warn!("⚠️ Log subscription dropped: {:?}", res);
+                }
+            }
+
+            provider = self.reconnect().await;
+        }
+    }
+}
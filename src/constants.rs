@@ -1,16 +1,7 @@
-use std::collections::HashMap;
-use ethers::{
-    signers::{LocalWallet, Signer}, 
-    types::Address,
-    providers::{Provider, Ws},
-    middleware::SignerMiddleware
-};
+use ethers::types::Address;
 use dashmap::DashMap;
 
-use crate::helpers::{load_private_key, load_rpc_url};
-
 use once_cell::sync::Lazy;
-use std::sync::Arc;
 
 pub const WETH: &str = "0x7ceB23fD6bC0adD59E62ac25578270cFf1b9f619";
 pub const WBTC: &str = "0x1BFD67037B42Cf73acF2047067bd4F2C47D9BfD6";
@@ -22,71 +13,108 @@ pub const MATICX: &str = "0xfa68FB4628DFF1028CFEc22b4162FCcd0d45efb6";
 pub const WPOL: &str = "0x0d500B1d8E8eF31E21C99d1Db9A6444d3ADf1270";
 
 pub const TRADE_SIZE: f64 = 15000.0;
-pub const SPREAD_THRESHOLD: f64 = 0.025;
+/// Per-leg tolerance below simulated output before the contract reverts
 pub const SLIPPAGE_BPS: u64 = 30;
 
 pub const QUICKSWAP_FACTORY: &str = "0x5757371414417b8C6CAad45bAeF941aBc7d3Ab32";
 pub const SUSHISWAPV2_FACTORY: &str = "0xc35dadb65012ec5796536bd9864ed8773abc74c4";
 pub const SUSHISWAPV3_FACTORY: &str = "0x917933899c6a5F8E37F31E19f92CdBFF7e8FF0e2";
 pub const UNISWAPV3_FACTORY: &str = "0x1F98431c8aD98523631AE4a59f267346ea31F984";
+pub const QUICKSWAPV3_FACTORY: &str = "0x411b0fAcC3489691f28ad58c47006AF5E3Ab3A28";
 
 pub const QUICKSWAP_ROUTER: &str = "0xa5E0829CaCED8fFDD4De3c43696c57F7D7A678ff";
 pub const SUSHISWAPV2_ROUTER: &str = "0x1b02da8cb0d097eb8d57a175b88c7d8b47997506";
-pub const UNISWAPV3_ROUTER: &str = "";
-pub const SUSHISWAPV3_ROUTER: &str = "";
+/// SwapRouter02
+pub const UNISWAPV3_ROUTER: &str = "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45";
+/// The original V3 SwapRouter
+pub const SUSHISWAPV3_ROUTER: &str = "0x0aF89E1620b96170e2a9D0b68fEebb767eD044c3";
+pub const QUICKSWAPV3_ROUTER: &str = "0xf5b509bB0909a69B1c207E495f687a596C168E12";
 
 
 pub const FLASH_ARBITRAGEUR: &str = "";
 
-pub const CHAIN_ID: u64 = 137;
-
-pub static  FACTORY_ROUTER_MAP: Lazy<HashMap<Address, Address>> = Lazy::new(|| {
-    let mut map = HashMap::new();
-
-    map.insert(
-        SUSHISWAPV2_FACTORY.parse::<Address>().unwrap(), 
-        SUSHISWAPV2_ROUTER.parse::<Address>().unwrap()
-     );
-
-    map.insert(
-        QUICKSWAP_FACTORY.parse::<Address>().unwrap(),
-         QUICKSWAP_ROUTER.parse::<Address>().unwrap()
-        );
+// --- Flash-loan lenders ---
+pub const AAVE_V3_POOL: &str = "0x794a61358D6845594F94dc1DB02A252b5b4814aD";
+pub const BALANCER_VAULT: &str = "0xBA12222222228d8Ba445958a75a0704d566BF2C8";
+/// Uniswap V3 fee tiers, in hundredths of a bip
+pub const UNISWAPV3_FEE_TIERS: [u32; 4] = [100, 500, 3_000, 10_000];
 
-    map.insert(
-        UNISWAPV3_FACTORY.parse::<Address>().unwrap(), 
-        UNISWAPV3_ROUTER.parse::<Address>().unwrap()
-    );
-
-    map.insert(
-        SUSHISWAPV3_FACTORY.parse::<Address>().unwrap(), 
-        SUSHISWAPV3_ROUTER.parse::<Address>().unwrap()
-    );
-
-    map
+pub const CHAIN_ID: u64 = 137;
 
-});
+pub const RECONNECT_INITIAL_BACKOFF_MS: u64 = 500;
+pub const RECONNECT_MAX_BACKOFF_MS: u64 = 30_000;
+
+pub const DEFAULT_RPC_CONNECT_TIMEOUT_MS: u64 = 10_000;
+pub const DEFAULT_RPC_REQUEST_TIMEOUT_MS: u64 = 5_000;
+
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 1_000;
+pub const MAX_POLL_BLOCK_RANGE: u64 = 500;
+
+/// Canonical headers kept for reorg detection (also the deepest reorg handled)
+pub const CHAIN_BUFFER_LEN: usize = 64;
+
+/// A supervised task that ran this long before stopping counts as healthy
+pub const SUPERVISOR_HEALTHY_AFTER_SECS: u64 = 60;
+pub const DEFAULT_SUPERVISOR_MAX_RESTARTS: u64 = 5;
+
+pub const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 60;
+pub const DEFAULT_METRICS_ADDR: &str = "0.0.0.0:9100";
+pub const DEFAULT_ADMIN_ADDR: &str = "0.0.0.0:8080";
+
+/// Minimum spread before a pair's opportunity is acted on, unless
+/// overridden through the admin API
+pub const DEFAULT_SPREAD_THRESHOLD: f64 = 0.002;
+/// Minimum simulated profit, in bps of the trade size
+pub const DEFAULT_MIN_PROFIT_BPS: u64 = 5;
+/// Gas limit set on arb transactions, so simulation sees the exact tx
+pub const DEFAULT_ARB_GAS_LIMIT: u64 = 1_500_000;
+/// Share of expected profit bid as priority fee, in bps
+pub const DEFAULT_PRIORITY_FEE_PROFIT_SHARE_BPS: u64 = 2_000;
+/// Polygon validators ignore tips under 25-30 gwei
+pub const DEFAULT_PRIORITY_FEE_FLOOR_GWEI: u64 = 30;
+pub const DEFAULT_PRIORITY_FEE_CEILING_GWEI: u64 = 2_000;
+pub const DEFAULT_FEE_HISTORY_BLOCKS: u64 = 10;
+/// Tip percentile read from `eth_feeHistory` when profit can't be valued
+pub const FEE_HISTORY_PERCENTILE: f64 = 60.0;
+/// Base fee multiple bid, so a tx survives the base fee rising before it lands
+pub const BASE_FEE_MULTIPLIER: u64 = 2;
+/// Inventory weight drift, in bps of total value, before rebalancing
+pub const DEFAULT_REBALANCE_DRIFT_BPS: u64 = 500;
+pub const DEFAULT_INVENTORY_REFRESH_SECS: u64 = 60;
+/// Gas limit of one router swap leg
+pub const DEFAULT_SWAP_GAS_LIMIT: u64 = 400_000;
+/// Router deadline, past the head's timestamp
+pub const SWAP_DEADLINE_SECS: u64 = 120;
+/// Evaluated opportunities kept for the admin API
+pub const RECENT_OPPORTUNITIES_LEN: usize = 100;
+pub const IN_FLIGHT_POLL_INTERVAL_MS: u64 = 2_000;
+/// How long a sent tx may be unknown to the node before it counts as dropped;
+/// load-balanced RPCs often don't know a fresh broadcast yet
+pub const DROPPED_TX_GRACE_SECS: u64 = 60;
+/// SQLite history of prices, opportunities and trades
+pub const DEFAULT_STORE_PATH: &str = "arb_bot.sqlite";
+/// Rows per store transaction
+pub const DEFAULT_STORE_BATCH_SIZE: u64 = 500;
+/// Longest a queued row waits before it's written
+pub const DEFAULT_STORE_FLUSH_MS: u64 = 1_000;
+/// Minimum gap between stored price samples of one pool
+pub const DEFAULT_STORE_PRICE_SAMPLE_MS: u64 = 5_000;
+/// Rows queued for the store writer; past that, pools, prices and
+/// opportunities are dropped while transactions, receipts and trades wait
+pub const STORE_QUEUE_LEN: usize = 50_000;
+/// Recording segment size that starts a new file
+pub const DEFAULT_RECORD_MAX_BYTES: u64 = 256 * 1024 * 1024;
+/// Recording segment age that starts a new file
+pub const DEFAULT_RECORD_ROTATE_SECS: u64 = 3_600;
+/// JSON-lines file settled trades are appended to
+pub const DEFAULT_PNL_LEDGER_PATH: &str = "pnl_ledger.jsonl";
+/// Seconds a sent tx may stay unmined before it's re-bumped or cancelled
+pub const DEFAULT_STUCK_TX_SECS: u64 = 20;
+pub const DEFAULT_MAX_FEE_BUMPS: u64 = 3;
+/// Fee increase per replacement; nodes require at least 10%
+pub const FEE_BUMP_PERCENT: u64 = 15;
+
+/// Price updates kept per pool for reorg rollback
+pub const PRICE_HISTORY_LEN: usize = 64;
 
 pub static TOKEN_SYMBOL_CACHE: Lazy<DashMap<Address, String>> = Lazy::new(|| {DashMap::new()});
-
-pub static PRIVATE_KEY: Lazy<String> = Lazy::new(|| {
-    load_private_key()
-});
-
-pub static RPC_URL: Lazy<String> = Lazy::new(|| {
-    load_rpc_url()
-});
-
-pub static WALLET: Lazy<LocalWallet> = Lazy::new(|| {
-    PRIVATE_KEY.parse::<LocalWallet>().unwrap().with_chain_id(CHAIN_ID)
-});
-
-pub static PROVIDER: Lazy<Arc<SignerMiddleware<Provider<Ws>, LocalWallet>>> = Lazy::new(|| {
-    let rt = tokio::runtime::Runtime::new().unwrap();
-    rt.block_on(async {
-        let ws = Ws::connect(&*RPC_URL).await.unwrap();
-        let provider = Provider::new(ws);
-        let signer = WALLET.clone();
-        Arc::new(SignerMiddleware::new(provider, signer))
-    })
-});
\ No newline at end of file
@@ -0,0 +1,144 @@
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, atomic::{AtomicBool, Ordering}},
+};
+
+use dashmap::{DashMap, DashSet};
+use ethers::types::{Address, H256};
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::constants::{DEFAULT_SPREAD_THRESHOLD, RECENT_OPPORTUNITIES_LEN};
+
+/// One evaluated opportunity, kept for the admin API
+#[derive(Debug, Clone, Serialize)]
+pub struct OpportunityRecord {
+    /// Unix seconds when the worker evaluated it
+    pub at: u64,
+    pub block: u64,
+    pub base: Address,
+    pub quote: Address,
+    pub buy_factory: Address,
+    pub sell_factory: Address,
+    pub buy_price: f64,
+    pub sell_price: f64,
+    pub spread: f64,
+    pub threshold: f64,
+    pub tx_hash: Option<H256>,
+    /// "submitted", "paper", "skipped" or the error that stopped it
+    pub outcome: String,
+}
+
+/// Runtime switches operators flip through the admin API. Workers consult
+/// these on every block, so changes apply from the next head.
+pub struct BotControls {
+    paused: AtomicBool,
+    paused_pairs: DashSet<(Address, Address)>,
+    paused_dexes: DashSet<Address>,
+    thresholds: DashMap<(Address, Address), f64>,
+    recent: Mutex<VecDeque<OpportunityRecord>>,
+    reload: watch::Sender<u64>,
+}
+
+impl Default for BotControls {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BotControls {
+    pub fn new() -> Self {
+        Self {
+            paused: AtomicBool::new(false),
+            paused_pairs: DashSet::new(),
+            paused_dexes: DashSet::new(),
+            thresholds: DashMap::new(),
+            recent: Mutex::new(VecDeque::with_capacity(RECENT_OPPORTUNITIES_LEN)),
+            reload: watch::channel(0).0,
+        }
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_pair_paused(&self, base: Address, quote: Address, paused: bool) {
+        if paused {
+            self.paused_pairs.insert((base, quote));
+        } else {
+            self.paused_pairs.remove(&(base, quote));
+        }
+    }
+
+    /// Whether the pair is paused, directly or through the whole bot
+    pub fn is_pair_paused(&self, base: Address, quote: Address) -> bool {
+        self.is_paused() || self.paused_pairs.contains(&(base, quote))
+    }
+
+    pub fn paused_pairs(&self) -> Vec<(Address, Address)> {
+        self.paused_pairs.iter().map(|p| *p).collect()
+    }
+
+    pub fn set_dex_paused(&self, factory: Address, paused: bool) {
+        if paused {
+            self.paused_dexes.insert(factory);
+        } else {
+            self.paused_dexes.remove(&factory);
+        }
+    }
+
+    pub fn is_dex_paused(&self, factory: Address) -> bool {
+        self.paused_dexes.contains(&factory)
+    }
+
+    pub fn paused_dexes(&self) -> Vec<Address> {
+        self.paused_dexes.iter().map(|f| *f).collect()
+    }
+
+    /// Minimum spread (as a fraction) before a pair's opportunity is acted on
+    pub fn threshold(&self, base: Address, quote: Address) -> f64 {
+        self.thresholds
+            .get(&(base, quote))
+            .map_or(DEFAULT_SPREAD_THRESHOLD, |t| *t)
+    }
+
+    pub fn set_threshold(&self, base: Address, quote: Address, threshold: f64) -> anyhow::Result<()> {
+        if !threshold.is_finite() || threshold < 0.0 {
+            anyhow::bail!("Threshold must be a non-negative number, got {}", threshold);
+        }
+        self.thresholds.insert((base, quote), threshold);
+        Ok(())
+    }
+
+    /// Pairs with a threshold other than the default
+    pub fn thresholds(&self) -> Vec<((Address, Address), f64)> {
+        self.thresholds.iter().map(|e| (*e.key(), *e.value())).collect()
+    }
+
+    pub fn record(&self, record: OpportunityRecord) {
+        let mut recent = self.recent.lock().expect("recent opportunities lock poisoned");
+        if recent.len() == RECENT_OPPORTUNITIES_LEN {
+            recent.pop_front();
+        }
+        recent.push_back(record);
+    }
+
+    /// Recorded opportunities, newest first
+    pub fn recent(&self) -> Vec<OpportunityRecord> {
+        let recent = self.recent.lock().expect("recent opportunities lock poisoned");
+        recent.iter().rev().cloned().collect()
+    }
+
+    /// Ask the reload task to rebuild target configs
+    pub fn request_reload(&self) {
+        self.reload.send_modify(|n| *n += 1);
+    }
+
+    pub fn subscribe_reload(&self) -> watch::Receiver<u64> {
+        self.reload.subscribe()
+    }
+}
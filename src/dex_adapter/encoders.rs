@@ -0,0 +1,318 @@
+//! Calldata for every swap entrypoint the arb can route through: each
+//! router family's exact-input call, and the pools' own `swap()` for the
+//! cheaper direct path. Pool swaps need a contract caller (V2 pairs must be
+//! paid first, V3 pools call back for payment), so they're never sent from
+//! the wallet.
+
+use ethers::{
+    abi::AbiEncode,
+    types::{Bytes, I256, U256},
+};
+
+use crate::{
+    bindings::{
+        quickswapv3::{
+            ExactInputSingleCall as AlgebraExactInputSingleCall,
+            ExactInputSingleParams as AlgebraExactInputSingleParams,
+        },
+        sushiswapv3::{
+            ExactInputSingleCall as SwapRouterExactInputSingleCall,
+            ExactInputSingleParams as SwapRouterExactInputSingleParams,
+        },
+        uniswapv2::{SwapCall as V2PairSwapCall, SwapExactTokensForTokensCall},
+        uniswapv3::{
+            ExactInputSingleCall as SwapRouter02ExactInputSingleCall,
+            ExactInputSingleParams as SwapRouter02ExactInputSingleParams, SwapCall as V3PoolSwapCall,
+        },
+    },
+    types::SwapParams,
+};
+
+/// `TickMath.MIN_SQRT_RATIO + 1`: no price limit when selling token0
+const MIN_SQRT_RATIO_PLUS_ONE: u64 = 4_295_128_740;
+/// `TickMath.MAX_SQRT_RATIO - 1`: no price limit when selling token1
+const MAX_SQRT_RATIO_MINUS_ONE: &str = "fffd8963efd1fc6a506488495d951d5263988d25";
+
+/// Which exact-input-single ABI a concentrated-liquidity router speaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum V3Router {
+    /// Uniswap SwapRouter02: no deadline in the params
+    SwapRouter02,
+    /// The original V3 SwapRouter, deadline in the params (SushiSwap V3)
+    SwapRouter,
+}
+
+/// V2 routers' `swapExactTokensForTokens` over a single hop
+pub fn v2_router_swap(params: &SwapParams) -> Bytes {
+    SwapExactTokensForTokensCall {
+        amount_in: params.amount_in,
+        amount_out_min: params.min_amount_out,
+        path: vec![params.token_in, params.token_out],
+        to: params.recipient,
+        deadline: params.deadline,
+    }
+    .encode()
+    .into()
+}
+
+/// `exactInputSingle` on a Uniswap V3 style router, for the pool of `fee`
+pub fn v3_router_swap(router: V3Router, fee: u32, params: &SwapParams) -> Bytes {
+    match router {
+        V3Router::SwapRouter02 => SwapRouter02ExactInputSingleCall {
+            params: SwapRouter02ExactInputSingleParams {
+                token_in: params.token_in,
+                token_out: params.token_out,
+                fee,
+                recipient: params.recipient,
+                amount_in: params.amount_in,
+                amount_out_minimum: params.min_amount_out,
+                sqrt_price_limit_x96: U256::zero(),
+            },
+        }
+        .encode()
+        .into(),
+        V3Router::SwapRouter => SwapRouterExactInputSingleCall {
+            params: SwapRouterExactInputSingleParams {
+                token_in: params.token_in,
+                token_out: params.token_out,
+                fee,
+                recipient: params.recipient,
+                deadline: params.deadline,
+                amount_in: params.amount_in,
+                amount_out_minimum: params.min_amount_out,
+                sqrt_price_limit_x96: U256::zero(),
+            },
+        }
+        .encode()
+        .into(),
+    }
+}
+
+/// Algebra's `exactInputSingle`; the pool's fee is dynamic so none is passed
+pub fn algebra_router_swap(params: &SwapParams) -> Bytes {
+    AlgebraExactInputSingleCall {
+        params: AlgebraExactInputSingleParams {
+            token_in: params.token_in,
+            token_out: params.token_out,
+            recipient: params.recipient,
+            deadline: params.deadline,
+            amount_in: params.amount_in,
+            amount_out_minimum: params.min_amount_out,
+            limit_sqrt_price: U256::zero(),
+        },
+    }
+    .encode()
+    .into()
+}
+
+/// V2 pair `swap()`, paying out exactly `amount_out` of `token_out`, the
+/// amount quoted for `amount_in`. Pairs pay whatever is asked as long as `k`
+/// holds, and anything the input buys beyond it is left for `skim()`, so
+/// asking for the slippage floor would give the slippage away;
+/// `min_amount_out` only bounds the quote. The input must already have been
+/// transferred to the pair; no callback data is passed, so it's a plain
+/// swap rather than a flash swap.
+pub fn v2_pool_swap(params: &SwapParams, amount_out: U256) -> anyhow::Result<Bytes> {
+    if amount_out < params.min_amount_out {
+        anyhow::bail!("Quoted {} out is below the minimum {}", amount_out, params.min_amount_out);
+    }
+
+    // Pairs sort their tokens by address
+    let (amount0_out, amount1_out) = if params.token_out < params.token_in {
+        (amount_out, U256::zero())
+    } else {
+        (U256::zero(), amount_out)
+    };
+
+    Ok(V2PairSwapCall {
+        amount_0_out: amount0_out,
+        amount_1_out: amount1_out,
+        to: params.recipient,
+        data: Bytes::new(),
+    }
+    .encode()
+    .into())
+}
+
+/// Exact-input pool `swap()`, shared by Uniswap V3 and Algebra pools. The
+/// pool calls back into the recipient for payment; the callback data is
+/// `abi.encode(tokenIn, tokenOut, minAmountOut)` for it to check against.
+pub fn v3_pool_swap(params: &SwapParams) -> Bytes {
+    let zero_for_one = params.token_in < params.token_out;
+    let sqrt_price_limit_x96 = if zero_for_one {
+        U256::from(MIN_SQRT_RATIO_PLUS_ONE)
+    } else {
+        U256::from_str_radix(MAX_SQRT_RATIO_MINUS_ONE, 16).expect("valid constant")
+    };
+    let callback = ethers::abi::encode(&[
+        ethers::abi::Token::Address(params.token_in),
+        ethers::abi::Token::Address(params.token_out),
+        ethers::abi::Token::Uint(params.min_amount_out),
+    ]);
+
+    V3PoolSwapCall {
+        recipient: params.recipient,
+        zero_for_one,
+        // Positive: exact input
+        amount_specified: I256::from_raw(params.amount_in),
+        sqrt_price_limit_x96,
+        data: callback.into(),
+    }
+    .encode()
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::Address;
+
+    use super::*;
+
+    // Calldata assembled word by word from the ABI spec and the published
+    // selectors, independently of the ethers encoder
+
+    const AMOUNT_IN: u64 = 1_000_000_000_000_000_000;
+    const MIN_OUT: u64 = 2_000_000;
+    const QUOTED_OUT: u64 = 2_100_000;
+    const DEADLINE: u64 = 1_700_000_000;
+
+    fn params(token_in: u8, token_out: u8) -> SwapParams {
+        SwapParams {
+            token_in: Address::repeat_byte(token_in),
+            token_out: Address::repeat_byte(token_out),
+            amount_in: U256::from(AMOUNT_IN),
+            min_amount_out: U256::from(MIN_OUT),
+            recipient: Address::repeat_byte(0x33),
+            deadline: U256::from(DEADLINE),
+        }
+    }
+
+    #[test]
+    fn v2_router_swap_exact_tokens_for_tokens() {
+        let expected = concat!(
+            "0x38ed1739",
+            "0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+            "00000000000000000000000000000000000000000000000000000000001e8480",
+            "00000000000000000000000000000000000000000000000000000000000000a0",
+            "0000000000000000000000003333333333333333333333333333333333333333",
+            "000000000000000000000000000000000000000000000000000000006553f100",
+            "0000000000000000000000000000000000000000000000000000000000000002",
+            "0000000000000000000000001111111111111111111111111111111111111111",
+            "0000000000000000000000002222222222222222222222222222222222222222",
+        );
+        assert_eq!(v2_router_swap(&params(0x11, 0x22)).to_string(), expected);
+    }
+
+    #[test]
+    fn v3_router_swap_router02_has_no_deadline() {
+        let expected = concat!(
+            "0x04e45aaf",
+            "0000000000000000000000001111111111111111111111111111111111111111",
+            "0000000000000000000000002222222222222222222222222222222222222222",
+            "00000000000000000000000000000000000000000000000000000000000001f4",
+            "0000000000000000000000003333333333333333333333333333333333333333",
+            "0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+            "00000000000000000000000000000000000000000000000000000000001e8480",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        );
+        assert_eq!(v3_router_swap(V3Router::SwapRouter02, 500, &params(0x11, 0x22)).to_string(), expected);
+    }
+
+    #[test]
+    fn v3_router_swap_router_has_deadline() {
+        let expected = concat!(
+            "0x414bf389",
+            "0000000000000000000000001111111111111111111111111111111111111111",
+            "0000000000000000000000002222222222222222222222222222222222222222",
+            "00000000000000000000000000000000000000000000000000000000000001f4",
+            "0000000000000000000000003333333333333333333333333333333333333333",
+            "000000000000000000000000000000000000000000000000000000006553f100",
+            "0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+            "00000000000000000000000000000000000000000000000000000000001e8480",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        );
+        assert_eq!(v3_router_swap(V3Router::SwapRouter, 500, &params(0x11, 0x22)).to_string(), expected);
+    }
+
+    #[test]
+    fn algebra_router_swap_has_no_fee() {
+        let expected = concat!(
+            "0xbc651188",
+            "0000000000000000000000001111111111111111111111111111111111111111",
+            "0000000000000000000000002222222222222222222222222222222222222222",
+            "0000000000000000000000003333333333333333333333333333333333333333",
+            "000000000000000000000000000000000000000000000000000000006553f100",
+            "0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+            "00000000000000000000000000000000000000000000000000000000001e8480",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        );
+        assert_eq!(algebra_router_swap(&params(0x11, 0x22)).to_string(), expected);
+    }
+
+    #[test]
+    fn v2_pool_swap_pays_out_token1() {
+        let expected = concat!(
+            "0x022c0d9f",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000000000000200b20",
+            "0000000000000000000000003333333333333333333333333333333333333333",
+            "0000000000000000000000000000000000000000000000000000000000000080",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        );
+        let calldata = v2_pool_swap(&params(0x11, 0x22), U256::from(QUOTED_OUT)).unwrap();
+        assert_eq!(calldata.to_string(), expected);
+    }
+
+    #[test]
+    fn v2_pool_swap_pays_out_token0() {
+        let expected = concat!(
+            "0x022c0d9f",
+            "0000000000000000000000000000000000000000000000000000000000200b20",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "0000000000000000000000003333333333333333333333333333333333333333",
+            "0000000000000000000000000000000000000000000000000000000000000080",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        );
+        let calldata = v2_pool_swap(&params(0x22, 0x11), U256::from(QUOTED_OUT)).unwrap();
+        assert_eq!(calldata.to_string(), expected);
+    }
+
+    #[test]
+    fn v2_pool_swap_rejects_quote_below_minimum() {
+        assert!(v2_pool_swap(&params(0x11, 0x22), U256::from(MIN_OUT - 1)).is_err());
+    }
+
+    #[test]
+    fn v3_pool_swap_zero_for_one_uses_min_sqrt_limit() {
+        let expected = concat!(
+            "0x128acb08",
+            "0000000000000000000000003333333333333333333333333333333333333333",
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+            "00000000000000000000000000000000000000000000000000000001000276a4",
+            "00000000000000000000000000000000000000000000000000000000000000a0",
+            "0000000000000000000000000000000000000000000000000000000000000060",
+            "0000000000000000000000001111111111111111111111111111111111111111",
+            "0000000000000000000000002222222222222222222222222222222222222222",
+            "00000000000000000000000000000000000000000000000000000000001e8480",
+        );
+        assert_eq!(v3_pool_swap(&params(0x11, 0x22)).to_string(), expected);
+    }
+
+    #[test]
+    fn v3_pool_swap_one_for_zero_uses_max_sqrt_limit() {
+        let expected = concat!(
+            "0x128acb08",
+            "0000000000000000000000003333333333333333333333333333333333333333",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+            "000000000000000000000000fffd8963efd1fc6a506488495d951d5263988d25",
+            "00000000000000000000000000000000000000000000000000000000000000a0",
+            "0000000000000000000000000000000000000000000000000000000000000060",
+            "0000000000000000000000002222222222222222222222222222222222222222",
+            "0000000000000000000000001111111111111111111111111111111111111111",
+            "00000000000000000000000000000000000000000000000000000000001e8480",
+        );
+        assert_eq!(v3_pool_swap(&params(0x22, 0x11)).to_string(), expected);
+    }
+}
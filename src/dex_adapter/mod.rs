@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use ethers::{
+    providers::Middleware,
+    types::{Address, Bytes, H256, Log, U256},
+};
+
+use crate::{
+    bindings::uniswapv2::UniswapV2Pair,
+    helpers::detect_token_ordering,
+    types::{DexPairConfig, PoolContext, PriceTracker, SwapParams, SwapSimulation},
+};
+
+pub mod encoders;
+pub mod registry;
+pub mod uniswapv2_adapter;
+pub mod uniswapv3_adapter;
+pub mod quickswapv3_adapter;
+
+pub use registry::DexRegistry;
+
+/// A trading venue: everything the bot needs to resolve, watch, quote and
+/// trade against one family of pools.
+///
+/// Adapters are registered by name in a [`DexRegistry`]; `DexConfig::adapter`
+/// selects one per venue.
+#[async_trait::async_trait]
+pub trait DexAdapter<M>: Send + Sync
+where
+    M: Middleware + 'static,
+{
+    /// Registry key, e.g. `"uniswapv2"`
+    fn name(&self) -> &str;
+
+    /// Look up the pool for a token pair in the venue's factory
+    async fn resolve_pool(
+        &self,
+        factory: Address,
+        base: Address,
+        quote: Address,
+        provider: Arc<M>,
+    ) -> anyhow::Result<Address>;
+
+    /// topic0 of every pool event this venue's listener decodes
+    fn event_topics(&self) -> Vec<H256>;
+
+    /// Quote-per-base price carried by a pool log, if any
+    fn decode_price(&self, log: &Log, pool: &PoolContext) -> anyhow::Result<Option<f64>>;
+
+    /// Resolve token ordering and decimals for a pool about to be watched.
+    /// `token0()`/`token1()` share selectors across V2, V3 and Algebra pools.
+    async fn pool_context(
+        &self,
+        dex_name: String,
+        dex_factory: Address,
+        pair: DexPairConfig,
+        provider: Arc<M>,
+    ) -> anyhow::Result<PoolContext> {
+        let pool = UniswapV2Pair::new(pair.pair, provider);
+        let token0: Address = pool.token_0().call().await?;
+        let token1: Address = pool.token_1().call().await?;
+
+        let (token0_is_base, _) =
+            detect_token_ordering(token0, token1, pair.base.id, pair.quote.id, pair.pair)?;
+
+        let (token0_decimals, token1_decimals) = if token0_is_base {
+            (pair.base.decimals, pair.quote.decimals)
+        } else {
+            (pair.quote.decimals, pair.base.decimals)
+        };
+
+        Ok(PoolContext {
+            dex_name,
+            dex_factory,
+            pair,
+            token0_is_base,
+            token0_decimals,
+            token1_decimals,
+        })
+    }
+
+    /// Seed the tracker with the pool's current on-chain price
+    async fn load_initial_state(
+        &self,
+        provider: Arc<M>,
+        tracker: Arc<PriceTracker>,
+        dex_factory: Address,
+        pair: &DexPairConfig,
+    ) -> anyhow::Result<()>;
+
+    /// Expected output for an exact-input swap through the pool
+    async fn quote(
+        &self,
+        provider: Arc<M>,
+        pair: &DexPairConfig,
+        token_in: Address,
+        amount_in: U256,
+    ) -> anyhow::Result<U256>;
+
+    /// Exact-input swap against the pool's current state, including the
+    /// pool price it would leave behind
+    async fn simulate_swap(
+        &self,
+        provider: Arc<M>,
+        pair: &DexPairConfig,
+        token_in: Address,
+        amount_in: U256,
+    ) -> anyhow::Result<SwapSimulation>;
+
+    /// Calldata of the pool's state getter, compared across providers for
+    /// quorum reads before execution
+    fn state_call(&self) -> Bytes;
+
+    /// Router calldata for a single exact-input swap
+    fn encode_swap(&self, pair: &DexPairConfig, params: &SwapParams) -> anyhow::Result<Bytes>;
+}
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use ethers::{
+    abi::AbiEncode,
+    providers::Middleware,
+    types::{Address, Bytes, H256, Log, U256},
+};
+
+use crate::{
+    bindings::quickswapv3::{AlgebraPool, GlobalStateCall},
+    dex_adapter::{DexAdapter, encoders},
+    dex_pool_resolver::{DexPoolResolver, quickswapv3_resolver::QuickSwapV3Resolver},
+    dex_price_listener::{DexPriceListener, quickswapv3_price_listener::QuickSwapV3Listener},
+    helpers::{
+        detect_token_ordering, spot_amount_out_sqrt_x96, sqrt_price_x96_to_price_f64,
+        swap_within_tick_sqrt_x96,
+    },
+    types::{DexPairConfig, PoolContext, PriceTracker, SwapParams, SwapSimulation},
+};
+
+/// Algebra-based (QuickSwap V3) pools with dynamic fees
+pub struct QuickSwapV3Adapter;
+
+#[async_trait::async_trait]
+impl<M> DexAdapter<M> for QuickSwapV3Adapter
+where
+    M: Middleware + 'static,
+{
+    fn name(&self) -> &str {
+        "quickswapv3"
+    }
+
+    async fn resolve_pool(
+        &self,
+        factory: Address,
+        base: Address,
+        quote: Address,
+        provider: Arc<M>,
+    ) -> anyhow::Result<Address> {
+        QuickSwapV3Resolver::resolve_pool(factory, base, quote, provider).await
+    }
+
+    fn event_topics(&self) -> Vec<H256> {
+        QuickSwapV3Listener::event_topics()
+    }
+
+    fn decode_price(&self, log: &Log, pool: &PoolContext) -> anyhow::Result<Option<f64>> {
+        QuickSwapV3Listener::decode_price(log, pool)
+    }
+
+    async fn load_initial_state(
+        &self,
+        provider: Arc<M>,
+        tracker: Arc<PriceTracker>,
+        dex_factory: Address,
+        pair: &DexPairConfig,
+    ) -> anyhow::Result<()> {
+        let pool = AlgebraPool::new(pair.pair, provider);
+        let token0: Address = pool.token_0().call().await?;
+        let token1: Address = pool.token_1().call().await?;
+        let (token0_is_base, _) =
+            detect_token_ordering(token0, token1, pair.base.id, pair.quote.id, pair.pair)?;
+
+        let (token0_decimals, token1_decimals) = if token0_is_base {
+            (pair.base.decimals, pair.quote.decimals)
+        } else {
+            (pair.quote.decimals, pair.base.decimals)
+        };
+
+        let (price_x96, ..) = pool.global_state().call().await?;
+
+        let price = sqrt_price_x96_to_price_f64(
+            price_x96,
+            token0_is_base,
+            token0_decimals as i32,
+            token1_decimals as i32,
+        )?;
+
+        tracker
+            .update(dex_factory, pair.pair, pair.base.id, pair.quote.id, price)
+            .await
+    }
+
+    /// Spot quote from `globalState`, using the pool's current dynamic fee
+    async fn quote(
+        &self,
+        provider: Arc<M>,
+        pair: &DexPairConfig,
+        token_in: Address,
+        amount_in: U256,
+    ) -> anyhow::Result<U256> {
+        let pool = AlgebraPool::new(pair.pair, provider);
+        let token0: Address = pool.token_0().call().await?;
+        let (price_x96, _, fee, ..) = pool.global_state().call().await?;
+
+        spot_amount_out_sqrt_x96(amount_in, price_x96, token_in == token0, fee as u32)
+    }
+
+    /// Within-tick approximation: exact unless the swap crosses a tick
+    async fn simulate_swap(
+        &self,
+        provider: Arc<M>,
+        pair: &DexPairConfig,
+        token_in: Address,
+        amount_in: U256,
+    ) -> anyhow::Result<SwapSimulation> {
+        let pool = AlgebraPool::new(pair.pair, provider);
+        let token0: Address = pool.token_0().call().await?;
+        let token1: Address = pool.token_1().call().await?;
+        let (token0_is_base, _) =
+            detect_token_ordering(token0, token1, pair.base.id, pair.quote.id, pair.pair)?;
+        let (token0_decimals, token1_decimals) = if token0_is_base {
+            (pair.base.decimals, pair.quote.decimals)
+        } else {
+            (pair.quote.decimals, pair.base.decimals)
+        };
+
+        let (sqrt_price_x96, _, fee, ..) = pool.global_state().call().await?;
+        let liquidity: u128 = pool.liquidity().call().await?;
+        let (amount_out, sqrt_after) = swap_within_tick_sqrt_x96(
+            amount_in,
+            sqrt_price_x96,
+            liquidity,
+            token_in == token0,
+            fee as u32,
+        )?;
+        let price = |sqrt_price| {
+            sqrt_price_x96_to_price_f64(sqrt_price, token0_is_base, token0_decimals as i32, token1_decimals as i32)
+        };
+
+        Ok(SwapSimulation {
+            amount_out,
+            price_before: price(sqrt_price_x96)?,
+            price_after: price(sqrt_after)?,
+        })
+    }
+
+    fn state_call(&self) -> Bytes {
+        Bytes::from(GlobalStateCall.encode())
+    }
+
+    fn encode_swap(&self, _pair: &DexPairConfig, params: &SwapParams) -> anyhow::Result<Bytes> {
+        Ok(encoders::algebra_router_swap(params))
+    }
+}
@@ -0,0 +1,95 @@
+use std::{collections::HashMap, sync::Arc};
+
+use dashmap::DashMap;
+use ethers::{providers::Middleware, types::Address};
+
+use super::{
+    DexAdapter,
+    quickswapv3_adapter::QuickSwapV3Adapter,
+    uniswapv2_adapter::UniswapV2Adapter,
+    uniswapv3_adapter::UniswapV3Adapter,
+};
+
+/// Adapters keyed by name. Venues pick theirs through `DexConfig::adapter`.
+pub struct DexRegistry<M> {
+    adapters: HashMap<String, Arc<dyn DexAdapter<M>>>,
+    /// factory -> venue, bound once venues are configured
+    venues: DashMap<Address, VenueBinding>,
+}
+
+/// What a configured venue's factory resolves to
+#[derive(Debug, Clone)]
+struct VenueBinding {
+    adapter: String,
+    router: Address,
+}
+
+impl<M> DexRegistry<M>
+where
+    M: Middleware + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            adapters: HashMap::new(),
+            venues: DashMap::new(),
+        }
+    }
+
+    /// Registry pre-loaded with the built-in V2, Uniswap/Sushi V3 and Algebra adapters
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(Arc::new(UniswapV2Adapter));
+        registry.register(Arc::new(UniswapV3Adapter::uniswap()));
+        registry.register(Arc::new(UniswapV3Adapter::sushiswap()));
+        registry.register(Arc::new(QuickSwapV3Adapter));
+        registry
+    }
+
+    /// Add an adapter, replacing any previously registered under the same name
+    pub fn register(&mut self, adapter: Arc<dyn DexAdapter<M>>) {
+        self.adapters.insert(adapter.name().to_string(), adapter);
+    }
+
+    pub fn get(&self, name: &str) -> anyhow::Result<Arc<dyn DexAdapter<M>>> {
+        self.adapters
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No DEX adapter registered under {:?}", name))
+    }
+
+    /// Record which adapter and router drive the venue behind `factory`
+    pub fn bind_venue(&self, factory: Address, adapter: &str, router: Address) {
+        self.venues.insert(factory, VenueBinding { adapter: adapter.to_string(), router });
+    }
+
+    /// Adapter of the venue behind `factory`
+    pub fn for_factory(&self, factory: Address) -> anyhow::Result<Arc<dyn DexAdapter<M>>> {
+        let name = self.venue(factory)?.adapter;
+        self.get(&name)
+    }
+
+    /// Router of the venue behind `factory`
+    pub fn router(&self, factory: Address) -> anyhow::Result<Address> {
+        Ok(self.venue(factory)?.router)
+    }
+
+    fn venue(&self, factory: Address) -> anyhow::Result<VenueBinding> {
+        self.venues
+            .get(&factory)
+            .map(|v| v.value().clone())
+            .ok_or_else(|| anyhow::anyhow!("No venue bound for factory {:?}", factory))
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.adapters.keys().cloned().collect()
+    }
+}
+
+impl<M> Default for DexRegistry<M>
+where
+    M: Middleware + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
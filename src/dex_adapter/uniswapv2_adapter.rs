@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use ethers::{
+    abi::AbiEncode,
+    providers::Middleware,
+    types::{Address, Bytes, H256, Log, U256},
+};
+
+use crate::{
+    bindings::uniswapv2::{GetReservesCall, UniswapV2Pair},
+    dex_adapter::{DexAdapter, encoders},
+    dex_pool_resolver::{DexPoolResolver, uniswapv2_resolver::UniswapV2Resolver},
+    dex_price_listener::{DexPriceListener, uniswapv2_price_listener::UniswapV2Listener},
+    helpers::{detect_token_ordering, get_amount_out_v2, reserves_to_price},
+    types::{DexPairConfig, PoolContext, PriceTracker, SwapParams, SwapSimulation},
+};
+
+/// Swap fee charged by QuickSwap/SushiSwap V2 pools
+const V2_FEE_BPS: u64 = 30;
+
+/// Constant-product (Uniswap V2 style) pools
+pub struct UniswapV2Adapter;
+
+#[async_trait::async_trait]
+impl<M> DexAdapter<M> for UniswapV2Adapter
+where
+    M: Middleware + 'static,
+{
+    fn name(&self) -> &str {
+        "uniswapv2"
+    }
+
+    async fn resolve_pool(
+        &self,
+        factory: Address,
+        base: Address,
+        quote: Address,
+        provider: Arc<M>,
+    ) -> anyhow::Result<Address> {
+        UniswapV2Resolver::resolve_pool(factory, base, quote, provider).await
+    }
+
+    fn event_topics(&self) -> Vec<H256> {
+        UniswapV2Listener::event_topics()
+    }
+
+    fn decode_price(&self, log: &Log, pool: &PoolContext) -> anyhow::Result<Option<f64>> {
+        UniswapV2Listener::decode_price(log, pool)
+    }
+
+    async fn load_initial_state(
+        &self,
+        provider: Arc<M>,
+        tracker: Arc<PriceTracker>,
+        dex_factory: Address,
+        pair: &DexPairConfig,
+    ) -> anyhow::Result<()> {
+        let pair_contract = UniswapV2Pair::new(pair.pair, provider);
+        let token0: Address = pair_contract.token_0().call().await?;
+        let token1: Address = pair_contract.token_1().call().await?;
+        let (token0_is_base, _) =
+            detect_token_ordering(token0, token1, pair.base.id, pair.quote.id, pair.pair)?;
+
+        let (reserve0, reserve1, _) = pair_contract.get_reserves().call().await?;
+
+        let price = reserves_to_price(
+            U256::from(reserve0),
+            U256::from(reserve1),
+            token0_is_base,
+            pair.base.decimals,
+            pair.quote.decimals,
+        )?;
+
+        tracker
+            .update(dex_factory, pair.pair, pair.base.id, pair.quote.id, price)
+            .await
+    }
+
+    async fn quote(
+        &self,
+        provider: Arc<M>,
+        pair: &DexPairConfig,
+        token_in: Address,
+        amount_in: U256,
+    ) -> anyhow::Result<U256> {
+        let pair_contract = UniswapV2Pair::new(pair.pair, provider);
+        let token0: Address = pair_contract.token_0().call().await?;
+        let (reserve0, reserve1, _) = pair_contract.get_reserves().call().await?;
+
+        let (reserve_in, reserve_out) = if token_in == token0 {
+            (U256::from(reserve0), U256::from(reserve1))
+        } else {
+            (U256::from(reserve1), U256::from(reserve0))
+        };
+
+        get_amount_out_v2(amount_in, reserve_in, reserve_out, V2_FEE_BPS)
+    }
+
+    async fn simulate_swap(
+        &self,
+        provider: Arc<M>,
+        pair: &DexPairConfig,
+        token_in: Address,
+        amount_in: U256,
+    ) -> anyhow::Result<SwapSimulation> {
+        let pair_contract = UniswapV2Pair::new(pair.pair, provider);
+        let token0: Address = pair_contract.token_0().call().await?;
+        let token1: Address = pair_contract.token_1().call().await?;
+        let (token0_is_base, _) =
+            detect_token_ordering(token0, token1, pair.base.id, pair.quote.id, pair.pair)?;
+        let (reserve0, reserve1, _) = pair_contract.get_reserves().call().await?;
+        let (reserve0, reserve1) = (U256::from(reserve0), U256::from(reserve1));
+
+        let zero_for_one = token_in == token0;
+        let (reserve_in, reserve_out) = if zero_for_one { (reserve0, reserve1) } else { (reserve1, reserve0) };
+        let amount_out = get_amount_out_v2(amount_in, reserve_in, reserve_out, V2_FEE_BPS)?;
+
+        let (after0, after1) = if zero_for_one {
+            (reserve0 + amount_in, reserve1 - amount_out)
+        } else {
+            (reserve0 - amount_out, reserve1 + amount_in)
+        };
+        let price = |r0, r1| {
+            reserves_to_price(r0, r1, token0_is_base, pair.base.decimals, pair.quote.decimals)
+        };
+
+        Ok(SwapSimulation {
+            amount_out,
+            price_before: price(reserve0, reserve1)?,
+            price_after: price(after0, after1)?,
+        })
+    }
+
+    fn state_call(&self) -> Bytes {
+        Bytes::from(GetReservesCall.encode())
+    }
+
+    fn encode_swap(&self, _pair: &DexPairConfig, params: &SwapParams) -> anyhow::Result<Bytes> {
+        Ok(encoders::v2_router_swap(params))
+    }
+}
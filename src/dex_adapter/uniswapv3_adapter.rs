@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use ethers::{
+    abi::AbiEncode,
+    providers::Middleware,
+    types::{Address, Bytes, H256, Log, U256},
+};
+
+use crate::{
+    bindings::uniswapv3::{Slot0Call, UniswapV3Pool},
+    dex_adapter::{
+        DexAdapter,
+        encoders::{self, V3Router},
+    },
+    dex_pool_resolver::{DexPoolResolver, uniswapv3_resolver::UniswapV3Resolver},
+    dex_price_listener::{DexPriceListener, uniswapv3_price_listener::UniswapV3Listener},
+    helpers::{
+        detect_token_ordering, spot_amount_out_sqrt_x96, sqrt_price_x96_to_price_f64,
+        swap_within_tick_sqrt_x96,
+    },
+    types::{DexPairConfig, PoolContext, PriceTracker, SwapParams, SwapSimulation},
+};
+
+/// Concentrated-liquidity (Uniswap V3 style) pools. Each pool's fee tier
+/// is read from the pool itself, once, and quoted and swapped with.
+///
+/// Uniswap and SushiSwap V3 share the pool and factory interfaces but not
+/// the router's: Uniswap is traded through SwapRouter02, Sushi through the
+/// original SwapRouter.
+pub struct UniswapV3Adapter {
+    name: &'static str,
+    router: V3Router,
+    /// pool -> fee in hundredths of a bip, as in the factory's `getPool`
+    fees: DashMap<Address, u32>,
+}
+
+impl UniswapV3Adapter {
+    pub fn uniswap() -> Self {
+        Self { name: "uniswapv3", router: V3Router::SwapRouter02, fees: DashMap::new() }
+    }
+
+    pub fn sushiswap() -> Self {
+        Self { name: "sushiswapv3", router: V3Router::SwapRouter, fees: DashMap::new() }
+    }
+
+    async fn fee<M: Middleware + 'static>(&self, pool: &UniswapV3Pool<M>) -> anyhow::Result<u32> {
+        if let Some(fee) = self.fees.get(&pool.address()) {
+            return Ok(*fee);
+        }
+        let fee = pool.fee().call().await?;
+        self.fees.insert(pool.address(), fee);
+        Ok(fee)
+    }
+}
+
+#[async_trait::async_trait]
+impl<M> DexAdapter<M> for UniswapV3Adapter
+where
+    M: Middleware + 'static,
+{
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    async fn resolve_pool(
+        &self,
+        factory: Address,
+        base: Address,
+        quote: Address,
+        provider: Arc<M>,
+    ) -> anyhow::Result<Address> {
+        UniswapV3Resolver::resolve_pool(factory, base, quote, provider).await
+    }
+
+    fn event_topics(&self) -> Vec<H256> {
+        UniswapV3Listener::event_topics()
+    }
+
+    fn decode_price(&self, log: &Log, pool: &PoolContext) -> anyhow::Result<Option<f64>> {
+        UniswapV3Listener::decode_price(log, pool)
+    }
+
+    async fn load_initial_state(
+        &self,
+        provider: Arc<M>,
+        tracker: Arc<PriceTracker>,
+        dex_factory: Address,
+        pair: &DexPairConfig,
+    ) -> anyhow::Result<()> {
+        let pool = UniswapV3Pool::new(pair.pair, provider);
+        let token0: Address = pool.token_0().call().await?;
+        let token1: Address = pool.token_1().call().await?;
+        let (token0_is_base, _) =
+            detect_token_ordering(token0, token1, pair.base.id, pair.quote.id, pair.pair)?;
+
+        let (token0_decimals, token1_decimals) = if token0_is_base {
+            (pair.base.decimals, pair.quote.decimals)
+        } else {
+            (pair.quote.decimals, pair.base.decimals)
+        };
+
+        let (sqrt_price_x96, ..) = pool.slot_0().call().await?;
+        self.fee(&pool).await?;
+
+        let price = sqrt_price_x96_to_price_f64(
+            sqrt_price_x96,
+            token0_is_base,
+            token0_decimals as i32,
+            token1_decimals as i32,
+        )?;
+
+        tracker
+            .update(dex_factory, pair.pair, pair.base.id, pair.quote.id, price)
+            .await
+    }
+
+    /// Spot quote from `slot0`; ignores price impact across ticks
+    async fn quote(
+        &self,
+        provider: Arc<M>,
+        pair: &DexPairConfig,
+        token_in: Address,
+        amount_in: U256,
+    ) -> anyhow::Result<U256> {
+        let pool = UniswapV3Pool::new(pair.pair, provider);
+        let token0: Address = pool.token_0().call().await?;
+        let (sqrt_price_x96, ..) = pool.slot_0().call().await?;
+        let fee = self.fee(&pool).await?;
+
+        spot_amount_out_sqrt_x96(amount_in, sqrt_price_x96, token_in == token0, fee)
+    }
+
+    /// Within-tick approximation: exact unless the swap crosses a tick
+    async fn simulate_swap(
+        &self,
+        provider: Arc<M>,
+        pair: &DexPairConfig,
+        token_in: Address,
+        amount_in: U256,
+    ) -> anyhow::Result<SwapSimulation> {
+        let pool = UniswapV3Pool::new(pair.pair, provider);
+        let token0: Address = pool.token_0().call().await?;
+        let token1: Address = pool.token_1().call().await?;
+        let (token0_is_base, _) =
+            detect_token_ordering(token0, token1, pair.base.id, pair.quote.id, pair.pair)?;
+        let (token0_decimals, token1_decimals) = if token0_is_base {
+            (pair.base.decimals, pair.quote.decimals)
+        } else {
+            (pair.quote.decimals, pair.base.decimals)
+        };
+
+        let (sqrt_price_x96, ..) = pool.slot_0().call().await?;
+        let liquidity: u128 = pool.liquidity().call().await?;
+        let fee = self.fee(&pool).await?;
+        let (amount_out, sqrt_after) = swap_within_tick_sqrt_x96(
+            amount_in,
+            sqrt_price_x96,
+            liquidity,
+            token_in == token0,
+            fee,
+        )?;
+        let price = |sqrt_price| {
+            sqrt_price_x96_to_price_f64(sqrt_price, token0_is_base, token0_decimals as i32, token1_decimals as i32)
+        };
+
+        Ok(SwapSimulation {
+            amount_out,
+            price_before: price(sqrt_price_x96)?,
+            price_after: price(sqrt_after)?,
+        })
+    }
+
+    fn state_call(&self) -> Bytes {
+        Bytes::from(Slot0Call.encode())
+    }
+
+    /// Needs the pool's fee tier, read by any quote or simulation before
+    fn encode_swap(&self, pair: &DexPairConfig, params: &SwapParams) -> anyhow::Result<Bytes> {
+        let fee = *self
+            .fees
+            .get(&pair.pair)
+            .ok_or_else(|| anyhow::anyhow!("Fee tier of pool {:?} not read yet", pair.pair))?;
+        Ok(encoders::v3_router_swap(self.router, fee, params))
+    }
+}
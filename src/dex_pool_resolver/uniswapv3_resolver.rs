@@ -1,22 +1,37 @@
-use crate::bindings::uniswapv3::IUniswapV3Factory;
+use crate::{
+    bindings::uniswapv3::{IUniswapV3Factory, UniswapV3Pool},
+    constants::UNISWAPV3_FEE_TIERS,
+};
 
 use super::{DexPoolResolver, Address, Middleware, Arc};
 
-pub struct UniswapV3Resolver<const FEE: u32>;
+/// Picks the pair's pool in whichever fee tier holds the most liquidity
+pub struct UniswapV3Resolver;
 
 #[async_trait::async_trait]
-impl<M: Middleware + 'static, const FEE: u32> DexPoolResolver<M> for UniswapV3Resolver<FEE> {
+impl<M: Middleware + 'static> DexPoolResolver<M> for UniswapV3Resolver {
     async fn resolve_pool(
         factory: Address,
         base: Address,
         quote: Address,
         provider: Arc<M>,
     ) -> anyhow::Result<Address> {
-        let factory_contract = IUniswapV3Factory::new(factory, provider);
-        let pool = factory_contract.get_pool(base, quote, FEE).call().await?;
-        if pool == Address::zero() {
-            anyhow::bail!("pool does not exist in UniswapV3 factory (fee {})", FEE);
+        let factory_contract = IUniswapV3Factory::new(factory, provider.clone());
+
+        let mut deepest: Option<(u128, Address)> = None;
+        for fee in UNISWAPV3_FEE_TIERS {
+            let pool = factory_contract.get_pool(base, quote, fee).call().await?;
+            if pool == Address::zero() {
+                continue;
+            }
+            let liquidity = UniswapV3Pool::new(pool, provider.clone()).liquidity().call().await?;
+            if deepest.is_none_or(|(most, _)| liquidity > most) {
+                deepest = Some((liquidity, pool));
+            }
         }
-        Ok(pool)
+
+        deepest
+            .map(|(_, pool)| pool)
+            .ok_or_else(|| anyhow::anyhow!("pool does not exist in UniswapV3 factory at any fee tier"))
     }
 }
@@ -1,28 +1,23 @@
-use std::sync::Arc;
+use ethers::types::{H256, Log};
 
-use ethers::{providers::{Middleware, PubsubClient}, types::Address};
-
-use crate::types::{DexPairConfig, PriceTracker};
+use crate::types::PoolContext;
 
 
 pub mod uniswapv2_price_listener;
 pub mod uniswapv3_price_listener;
 pub mod quickswapv3_price_listener;
+pub mod multiplexer;
 
 
-#[async_trait::async_trait]
- pub trait DexPriceListener<M>: Send + Sync 
-where
-    M: Middleware,
-    <M as Middleware>::Provider: PubsubClient
-{
-    async fn start(
-        dex_name: String,
-        provider: Arc<M>,
-        tracker: Arc<PriceTracker>,
-        dex_factory: Address,
-        pair: DexPairConfig
+/// Decodes a venue's swap logs into canonical prices.
+///
+/// Listeners no longer own a subscription: the `LogMultiplexer` receives
+/// every watched pool's logs on one stream and hands each log to the
+/// listener of the pool that emitted it.
+pub trait DexPriceListener: Send + Sync {
+    /// topic0 of every event this listener understands
+    fn event_topics() -> Vec<H256>;
 
-    ) -> anyhow::Result<()>;
-    
-}
\ No newline at end of file
+    /// Quote-per-base price carried by `log`, if any
+    fn decode_price(log: &Log, pool: &PoolContext) -> anyhow::Result<Option<f64>>;
+}
@@ -0,0 +1,374 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use dashmap::DashMap;
+use ethers::{
+    providers::{Middleware, PubsubClient, SubscriptionStream},
+    types::{Address, Filter, H256, Log, U256},
+};
+use futures::StreamExt;
+use tokio::sync::watch;
+
+use crate::{
+    block_watcher::BlockWatcher,
+    constants::MAX_POLL_BLOCK_RANGE,
+    dex_adapter::DexAdapter,
+    helpers::get_token_symbol,
+    metrics::METRICS,
+    recorder::{self, RecordedEvent},
+    rpc_pool::RpcPool,
+    store::{self, StoreEvent},
+    types::{BlockInfo, DexPairKey, LogPosition, PoolContext, PriceTracker},
+};
+
+/// How many recent log ids are remembered to drop duplicates
+const SEEN_LOGS_CAPACITY: usize = 4096;
+
+/// Bounded set of recently routed logs, keyed by (block hash, log index)
+#[derive(Default)]
+struct SeenLogs {
+    set: HashSet<(H256, U256)>,
+    order: VecDeque<(H256, U256)>,
+}
+
+impl SeenLogs {
+    /// Returns false if the log was already seen
+    fn insert(&mut self, log: &Log) -> bool {
+        let (Some(block_hash), Some(log_index)) = (log.block_hash, log.log_index) else {
+            return true;
+        };
+        let id = (block_hash, log_index);
+
+        if !self.set.insert(id) {
+            return false;
+        }
+        self.order.push_back(id);
+        if self.order.len() > SEEN_LOGS_CAPACITY
+            && let Some(old) = self.order.pop_front()
+        {
+            self.set.remove(&old);
+        }
+        true
+    }
+
+    /// Forget a log so it is accepted again (its block was orphaned)
+    fn forget(&mut self, log: &Log) {
+        if let (Some(block_hash), Some(log_index)) = (log.block_hash, log.log_index) {
+            self.set.remove(&(block_hash, log_index));
+        }
+    }
+}
+
+/// A watched pool: its decoding context and the adapter that understands its logs
+pub struct PoolHandler<M> {
+    pub context: PoolContext,
+    pub adapter: Arc<dyn DexAdapter<M>>,
+}
+
+impl<M> Clone for PoolHandler<M> {
+    fn clone(&self) -> Self {
+        Self {
+            context: self.context.clone(),
+            adapter: self.adapter.clone(),
+        }
+    }
+}
+
+/// Single `logs` subscription covering every watched pool.
+///
+/// Logs are filtered by pool address and the adapters' event topics, then
+/// routed by emitting address to the pool's handler. Watching or unwatching
+/// a pool resubscribes with the resized filter.
+pub struct LogMultiplexer<M> {
+    tracker: Arc<PriceTracker>,
+    rpc_pool: Arc<RpcPool>,
+    /// Drops logs delivered twice (backfill overlap, raced reads)
+    seen: Mutex<SeenLogs>,
+    pools: DashMap<Address, PoolHandler<M>>,
+    /// Bumped on every watch/unwatch so `run` knows to resubscribe
+    generation: watch::Sender<u64>,
+    /// Fed from a recording: chain state is never read to fill in prices
+    replaying: bool,
+}
+
+impl<M: Middleware + 'static> LogMultiplexer<M> {
+    pub fn new(tracker: Arc<PriceTracker>, rpc_pool: Arc<RpcPool>) -> Self {
+        Self {
+            tracker,
+            rpc_pool,
+            seen: Mutex::new(SeenLogs::default()),
+            pools: DashMap::new(),
+            generation: watch::Sender::new(0),
+            replaying: false,
+        }
+    }
+
+    /// Take prices only from replayed logs: no initial state is loaded and
+    /// reorged branches aren't re-fetched, as the recording holds both
+    pub fn replaying(mut self) -> Self {
+        self.replaying = true;
+        self
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+
+    /// Start routing a pool's logs; takes effect on the next resubscription
+    pub fn watch(&self, context: PoolContext, adapter: Arc<dyn DexAdapter<M>>) {
+        store::record(StoreEvent::Pool(context.clone()));
+        self.pools.insert(context.pair.pair, PoolHandler { context, adapter });
+        self.generation.send_modify(|g| *g += 1);
+    }
+
+    pub fn unwatch(&self, pool: Address) {
+        if self.pools.remove(&pool).is_some() {
+            self.generation.send_modify(|g| *g += 1);
+        }
+    }
+
+    pub fn watched_count(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_watched(&self, pool: Address) -> bool {
+        self.pools.contains_key(&pool)
+    }
+
+    /// Contexts of every watched pool
+    pub fn contexts(&self) -> Vec<PoolContext> {
+        self.pools.iter().map(|h| h.context.clone()).collect()
+    }
+
+    /// Log filter over all watched addresses and their adapters' topics
+    pub fn filter(&self) -> Filter {
+        let mut addresses: Vec<Address> = Vec::with_capacity(self.pools.len());
+        let mut topics: Vec<H256> = Vec::new();
+
+        for entry in self.pools.iter() {
+            addresses.push(*entry.key());
+            for topic in entry.value().adapter.event_topics() {
+                if !topics.contains(&topic) {
+                    topics.push(topic);
+                }
+            }
+        }
+
+        Filter::new().address(addresses).topic0(topics)
+    }
+
+    /// Replay logs from `from_block` to head through `eth_getLogs`, in
+    /// `MAX_POLL_BLOCK_RANGE` chunks raced across HTTP endpoints when enabled
+    pub async fn backfill(&self, provider: Arc<M>, from_block: u64) -> anyhow::Result<()> {
+        let head = provider
+            .get_block_number()
+            .await
+            .map_err(|e| anyhow::anyhow!("eth_blockNumber failed: {:?}", e))?
+            .as_u64();
+
+        let mut total = 0;
+        let mut start = from_block;
+        while start <= head {
+            let end = (start + MAX_POLL_BLOCK_RANGE - 1).min(head);
+            let filter = self.filter().from_block(start).to_block(end);
+            let logs = if self.rpc_pool.race_reads {
+                self.rpc_pool.race_get_logs(&filter).await?
+            } else {
+                provider
+                    .get_logs(&filter)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Backfill of {}..={} failed: {:?}", start, end, e))?
+            };
+            total += logs.len();
+
+            for log in &logs {
+                if let Err(e) = self.route(log, provider.clone()).await {
+                    tracing::warn!("Failed to handle backfilled log from {:?}: {:?}", log.address, e);
+                }
+            }
+            start = end + 1;
+        }
+
+        tracing::info!("⏪ Backfilled {} logs from blocks {}..={}", total, from_block, head);
+        Ok(())
+    }
+
+    /// Route every watched pool's logs in `[from_block, to_block]`
+    pub async fn poll_range(&self, provider: Arc<M>, from_block: u64, to_block: u64) -> anyhow::Result<()> {
+        let filter = self.filter().from_block(from_block).to_block(to_block);
+        let logs = provider
+            .get_logs(&filter)
+            .await
+            .map_err(|e| anyhow::anyhow!("eth_getLogs {}..={} failed: {:?}", from_block, to_block, e))?;
+
+        for log in &logs {
+            if let Err(e) = self.route(log, provider.clone()).await {
+                tracing::warn!("Failed to handle log from {:?}: {:?}", log.address, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Roll prices back past the orphaned blocks, then replay the pool
+    /// logs of the new branch so the tracker reflects the canonical chain
+    pub async fn handle_reorg(
+        &self,
+        provider: Arc<M>,
+        removed: &[BlockInfo],
+        added: &[BlockInfo],
+    ) -> anyhow::Result<()> {
+        let orphaned: HashSet<H256> = removed.iter().map(|b| b.hash).collect();
+        let rolled_back = self.tracker.rollback(&orphaned).await;
+
+        tracing::info!(
+            "⏪ Rolled back {} price updates from {} orphaned blocks; replaying {} blocks",
+            rolled_back,
+            removed.len(),
+            added.len()
+        );
+
+        // The branch's logs were recorded right after its head; they follow
+        if self.replaying {
+            return Ok(());
+        }
+
+        for block in added {
+            let filter = self.filter().at_block_hash(block.hash);
+            let logs = provider
+                .get_logs(&filter)
+                .await
+                .map_err(|e| anyhow::anyhow!("eth_getLogs at {:?} failed: {:?}", block.hash, e))?;
+
+            for log in &logs {
+                if let Err(e) = self.route(log, provider.clone()).await {
+                    tracing::warn!("Failed to replay log from {:?}: {:?}", log.address, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decode a log and push the resulting price into the tracker. Logs
+    /// flagged `removed` undo their block's updates for that pool instead.
+    pub async fn route(&self, log: &Log, provider: Arc<M>) -> anyhow::Result<()> {
+        recorder::record(|| RecordedEvent::Log(log.clone()));
+        if log.removed == Some(true) {
+            self.seen.lock().expect("seen logs lock poisoned").forget(log);
+
+            let dex_factory = self.pools.get(&log.address).map(|h| h.context.dex_factory);
+            if let (Some(dex_factory), Some(block_hash)) = (dex_factory, log.block_hash) {
+                let key = DexPairKey { dex_factory, pair_address: log.address };
+                self.tracker.remove_block(key, block_hash).await;
+            }
+            return Ok(());
+        }
+
+        if !self.seen.lock().expect("seen logs lock poisoned").insert(log) {
+            return Ok(());
+        }
+
+        let Some(handler) = self.pools.get(&log.address).map(|h| h.value().clone()) else {
+            return Ok(());
+        };
+        let ctx = &handler.context;
+
+        let Some(price) = handler.adapter.decode_price(log, ctx)? else {
+            return Ok(());
+        };
+
+        let timer = METRICS.tracker_update_seconds.start_timer();
+        self.tracker
+            .update_at(
+                ctx.dex_factory,
+                ctx.pair.pair,
+                ctx.pair.base.id,
+                ctx.pair.quote.id,
+                price,
+                LogPosition::of(log),
+            )
+            .await?;
+        timer.observe_duration();
+        store::record_price(ctx, price, LogPosition::of(log));
+        METRICS
+            .swap_events
+            .with_label_values(&[&ctx.dex_name, &format!("{:?}", ctx.pair.pair)])
+            .inc();
+
+        tracing::info!(
+            "[{}] {:.6} for {}/{}",
+            ctx.dex_name,
+            price,
+            get_token_symbol(ctx.pair.base.id, provider.clone())
+                .await
+                .unwrap_or_else(|_| format!("{:?}", ctx.pair.base.id)),
+            get_token_symbol(ctx.pair.quote.id, provider)
+                .await
+                .unwrap_or_else(|_| format!("{:?}", ctx.pair.quote.id)),
+        );
+
+        Ok(())
+    }
+}
+
+impl<M> LogMultiplexer<M>
+where
+    M: Middleware + 'static,
+    <M as Middleware>::Provider: PubsubClient,
+{
+    /// Run the subscription until it ends, resubscribing whenever the
+    /// watched universe changes. Each new subscription is live before the
+    /// old one is dropped, and logs since the block watcher's last head are
+    /// then backfilled, so nothing falls in the gap between the two;
+    /// `SeenLogs` drops the overlap.
+    pub async fn run(&self, provider: Arc<M>, block_watcher: &BlockWatcher) -> anyhow::Result<()> {
+        let mut changes = self.generation.subscribe();
+        let mut previous: Option<SubscriptionStream<'_, M::Provider, Log>> = None;
+
+        loop {
+            changes.borrow_and_update();
+            let filter = self.filter();
+            let mut stream = provider
+                .subscribe_logs(&filter)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to subscribe to logs: {:?}", e))?;
+
+            tracing::info!("📡 Subscribed to swap logs for {} pools", self.watched_count());
+
+            if let Some(stale) = previous.take() {
+                let id = stale.id;
+                drop(stale);
+                if let Err(e) = provider.unsubscribe(id).await {
+                    tracing::debug!("Failed to unsubscribe stale log stream: {:?}", e);
+                }
+            }
+
+            if let Some(from_block) = block_watcher.last_head() {
+                self.backfill(provider.clone(), from_block).await?;
+            }
+
+            loop {
+                tokio::select! {
+                    maybe_log = stream.next() => {
+                        let Some(log) = maybe_log else {
+                            anyhow::bail!("Log subscription ended");
+                        };
+
+                        if let Err(e) = self.route(&log, provider.clone()).await {
+                            tracing::warn!("Failed to handle log from {:?}: {:?}", log.address, e);
+                        }
+                    }
+                    _ = changes.changed() => {
+                        tracing::info!("Pool universe changed; resubscribing");
+                        break;
+                    }
+                }
+            }
+
+            previous = Some(stream);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use ethers::providers::Middleware;
+
+use crate::{
+    allowance::AllowanceManager, config::ExecutionMode, connection_manager::ConnectionManager, controls::BotControls,
+    flash_loan::FlashLoanRouter, fork_sim::ForkCache, gas_strategy::GasStrategy, inventory::Inventory, nonce_manager::NonceManager, paper_trading::PaperLedger, pnl::PnlLedger, dex_adapter::DexRegistry, rpc_pool::RpcPool,
+    shutdown::Shutdown, types::PriceTracker,
+};
+
+/// Shared services every worker needs to evaluate and execute opportunities
+pub struct ExecutionContext<M> {
+    pub connection: Arc<ConnectionManager<M>>,
+    pub registry: Arc<DexRegistry<M>>,
+    pub tracker: Arc<PriceTracker>,
+    pub rpc_pool: Arc<RpcPool>,
+    pub shutdown: Arc<Shutdown>,
+    pub controls: Arc<BotControls>,
+    pub mode: ExecutionMode,
+    /// Picks the cheapest lender for each trade
+    pub flash: FlashLoanRouter<M>,
+    /// How arb transactions bid for inclusion
+    pub gas: GasStrategy,
+    /// Nonces for the shared wallet
+    pub nonces: Arc<NonceManager>,
+    /// ERC20 approvals the wallet's swaps rely on
+    pub approvals: Arc<AllowanceManager>,
+    /// Would-be trades recorded in dry-run mode
+    pub paper: Arc<PaperLedger>,
+    /// Settled trades and their realized PnL
+    pub pnl: Arc<PnlLedger>,
+    /// Wallet balances traded in inventory funding mode; `None` borrows
+    /// through flash loans
+    pub inventory: Option<Arc<Inventory>>,
+    /// Local EVM state per block; `None` simulates over RPC instead
+    pub fork: Option<Arc<ForkCache<M>>>,
+}
+
+impl<M: Middleware + 'static> ExecutionContext<M> {
+    pub fn new(
+        connection: Arc<ConnectionManager<M>>,
+        registry: Arc<DexRegistry<M>>,
+        tracker: Arc<PriceTracker>,
+        rpc_pool: Arc<RpcPool>,
+        shutdown: Arc<Shutdown>,
+        controls: Arc<BotControls>,
+        mode: ExecutionMode,
+    ) -> Self {
+        Self {
+            connection,
+            registry,
+            tracker,
+            rpc_pool,
+            shutdown,
+            controls,
+            mode,
+            flash: FlashLoanRouter::with_defaults(),
+            gas: GasStrategy::default(),
+            nonces: Arc::new(NonceManager::new()),
+            approvals: Arc::new(AllowanceManager::default()),
+            paper: Arc::new(PaperLedger::new()),
+            pnl: Arc::new(PnlLedger::new()),
+            inventory: None,
+            fork: None,
+        }
+    }
+
+    pub fn with_gas_strategy(mut self, gas: GasStrategy) -> Self {
+        self.gas = gas;
+        self
+    }
+
+    pub fn with_approvals(mut self, approvals: AllowanceManager) -> Self {
+        self.approvals = Arc::new(approvals);
+        self
+    }
+
+    pub fn with_pnl_ledger(mut self, pnl: PnlLedger) -> Self {
+        self.pnl = Arc::new(pnl);
+        self
+    }
+
+    /// Trade from the wallet's own balances instead of flash loans
+    pub fn with_inventory(mut self, inventory: Arc<Inventory>) -> Self {
+        self.inventory = Some(inventory);
+        self
+    }
+
+    /// Simulate trades in a local EVM forked from the provider
+    pub fn with_fork_simulation(mut self) -> Self {
+        let connection = self.connection.clone();
+        self.fork = Some(Arc::new(ForkCache::new(move || connection.provider())));
+        self
+    }
+
+    /// Provider of the live connection
+    pub fn provider(&self) -> Arc<M> {
+        self.connection.provider()
+    }
+}
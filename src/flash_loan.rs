@@ -0,0 +1,325 @@
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+};
+use futures::future::join_all;
+use serde::Serialize;
+
+use crate::{
+    bindings::{
+        erc20::IERC20,
+        flash_lenders::{AaveV3Pool, BalancerProtocolFeesCollector, BalancerVault},
+        uniswapv2::UniswapV2Pair,
+        uniswapv3::IUniswapV3Factory,
+    },
+    constants::{AAVE_V3_POOL, BALANCER_VAULT, UNISWAPV3_FACTORY, UNISWAPV3_FEE_TIERS},
+    types::{DexPairConfig, DexPairKey},
+};
+
+/// Where the arb contract borrows its working capital. The discriminant is
+/// what the payload carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FlashSource {
+    /// Aave V3 `flashLoanSimple`, charged the pool's premium
+    AaveV3 = 0,
+    /// Balancer Vault `flashLoan`, free unless governance sets a fee
+    Balancer = 1,
+    /// A Uniswap V3 pool's `flash`, charged the pool's fee tier
+    UniswapV3Flash = 2,
+    /// Borrow the base straight out of the V2 buy pool and repay it in quote
+    V2FlashSwap = 3,
+}
+
+/// One lender's terms for funding an opportunity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FlashLoan {
+    pub source: FlashSource,
+    /// Contract the arb calls to borrow: pool, vault or pair
+    pub lender: Address,
+    pub token: Address,
+    pub amount: U256,
+    /// Owed on top of `amount`, in `token`
+    pub fee: U256,
+}
+
+impl FlashLoan {
+    /// The fee in quote, the unit profit is counted in. A fee owed in base
+    /// is priced at the buy leg's rate: `trade_size` quote for `base_out`.
+    pub fn fee_in_quote(&self, pair: &DexPairConfig, base_out: U256) -> anyhow::Result<U256> {
+        if self.token == pair.quote.id {
+            Ok(self.fee)
+        } else if self.token == pair.base.id && !base_out.is_zero() {
+            Ok(fee_on(self.fee, pair.trade_size, base_out))
+        } else {
+            anyhow::bail!("Can't price a flash fee in {:?} for {:?}", self.token, pair.quote.id)
+        }
+    }
+}
+
+/// What needs funding
+#[derive(Debug, Clone)]
+pub struct FlashRequest {
+    pub pair: DexPairConfig,
+    pub buy: DexPairKey,
+    pub sell: DexPairKey,
+    /// Adapter driving the buy venue
+    pub buy_adapter: String,
+    /// Base the buy leg is expected to return
+    pub base_out: U256,
+}
+
+/// A flash-loan source: its fee and whether it holds enough to lend
+#[async_trait::async_trait]
+pub trait FlashLender<M>: Send + Sync
+where
+    M: Middleware + 'static,
+{
+    fn source(&self) -> FlashSource;
+
+    /// Terms for funding `req`, or `None` when this lender can't cover it
+    async fn quote(&self, provider: Arc<M>, req: &FlashRequest) -> anyhow::Result<Option<FlashLoan>>;
+}
+
+async fn balance_of<M: Middleware + 'static>(provider: Arc<M>, token: Address, holder: Address) -> anyhow::Result<U256> {
+    Ok(IERC20::new(token, provider).balance_of(holder).call().await?)
+}
+
+/// `amount * numerator / denominator`, rounded up like the lenders do
+fn fee_on(amount: U256, numerator: U256, denominator: U256) -> U256 {
+    let (quotient, remainder) = (amount * numerator).div_mod(denominator);
+    if remainder.is_zero() { quotient } else { quotient + 1 }
+}
+
+pub struct AaveV3Lender {
+    pub pool: Address,
+}
+
+#[async_trait::async_trait]
+impl<M: Middleware + 'static> FlashLender<M> for AaveV3Lender {
+    fn source(&self) -> FlashSource {
+        FlashSource::AaveV3
+    }
+
+    async fn quote(&self, provider: Arc<M>, req: &FlashRequest) -> anyhow::Result<Option<FlashLoan>> {
+        let pool = AaveV3Pool::new(self.pool, provider.clone());
+        // Field 8 of `ReserveData` is the aToken; zero when the asset isn't listed
+        let a_token = pool.get_reserve_data(req.pair.quote.id).call().await?.8;
+        if a_token.is_zero() {
+            return Ok(None);
+        }
+
+        // Loans are paid out of the aToken's underlying balance
+        let available = balance_of(provider, req.pair.quote.id, a_token).await?;
+        if available < req.pair.trade_size {
+            return Ok(None);
+        }
+
+        let premium_bps = pool.flashloan_premium_total().call().await?;
+        Ok(Some(FlashLoan {
+            source: FlashSource::AaveV3,
+            lender: self.pool,
+            token: req.pair.quote.id,
+            amount: req.pair.trade_size,
+            fee: fee_on(req.pair.trade_size, U256::from(premium_bps), U256::from(10_000)),
+        }))
+    }
+}
+
+pub struct BalancerLender {
+    pub vault: Address,
+}
+
+#[async_trait::async_trait]
+impl<M: Middleware + 'static> FlashLender<M> for BalancerLender {
+    fn source(&self) -> FlashSource {
+        FlashSource::Balancer
+    }
+
+    async fn quote(&self, provider: Arc<M>, req: &FlashRequest) -> anyhow::Result<Option<FlashLoan>> {
+        let available = balance_of(provider.clone(), req.pair.quote.id, self.vault).await?;
+        if available < req.pair.trade_size {
+            return Ok(None);
+        }
+
+        // 1e18 = 100%
+        let collector = BalancerVault::new(self.vault, provider.clone())
+            .get_protocol_fees_collector()
+            .call()
+            .await?;
+        let fee_pct = BalancerProtocolFeesCollector::new(collector, provider)
+            .get_flash_loan_fee_percentage()
+            .call()
+            .await?;
+
+        Ok(Some(FlashLoan {
+            source: FlashSource::Balancer,
+            lender: self.vault,
+            token: req.pair.quote.id,
+            amount: req.pair.trade_size,
+            fee: fee_on(req.pair.trade_size, fee_pct, U256::exp10(18)),
+        }))
+    }
+}
+
+/// Flash from the cheapest Uniswap V3 pool of the pair the arb doesn't
+/// itself trade through (the pool is locked for the duration of `flash`)
+pub struct UniswapV3FlashLender {
+    pub factory: Address,
+    /// (base, quote, fee tier) -> pool, zero when not deployed
+    pools: DashMap<(Address, Address, u32), Address>,
+}
+
+impl UniswapV3FlashLender {
+    pub fn new(factory: Address) -> Self {
+        Self { factory, pools: DashMap::new() }
+    }
+
+    async fn pool<M: Middleware + 'static>(
+        &self,
+        provider: Arc<M>,
+        base: Address,
+        quote: Address,
+        fee: u32,
+    ) -> anyhow::Result<Address> {
+        if let Some(pool) = self.pools.get(&(base, quote, fee)) {
+            return Ok(*pool);
+        }
+        let pool = IUniswapV3Factory::new(self.factory, provider)
+            .get_pool(base, quote, fee)
+            .call()
+            .await?;
+        self.pools.insert((base, quote, fee), pool);
+        Ok(pool)
+    }
+}
+
+#[async_trait::async_trait]
+impl<M: Middleware + 'static> FlashLender<M> for UniswapV3FlashLender {
+    fn source(&self) -> FlashSource {
+        FlashSource::UniswapV3Flash
+    }
+
+    async fn quote(&self, provider: Arc<M>, req: &FlashRequest) -> anyhow::Result<Option<FlashLoan>> {
+        let (base, quote) = (req.pair.base.id, req.pair.quote.id);
+
+        // Fee tiers ascend, so the first pool that can lend is the cheapest
+        for fee in UNISWAPV3_FEE_TIERS {
+            let pool = self.pool(provider.clone(), base, quote, fee).await?;
+            if pool.is_zero() || pool == req.buy.pair_address || pool == req.sell.pair_address {
+                continue;
+            }
+            if balance_of(provider.clone(), quote, pool).await? < req.pair.trade_size {
+                continue;
+            }
+
+            return Ok(Some(FlashLoan {
+                source: FlashSource::UniswapV3Flash,
+                lender: pool,
+                token: quote,
+                amount: req.pair.trade_size,
+                fee: fee_on(req.pair.trade_size, U256::from(fee), U256::from(1_000_000)),
+            }));
+        }
+
+        Ok(None)
+    }
+}
+
+/// Take the buy leg's base out of the V2 pair first and repay it in quote
+/// from the sell leg. The swap fee is already in the buy quote, so nothing
+/// extra is owed.
+pub struct V2FlashSwapLender;
+
+#[async_trait::async_trait]
+impl<M: Middleware + 'static> FlashLender<M> for V2FlashSwapLender {
+    fn source(&self) -> FlashSource {
+        FlashSource::V2FlashSwap
+    }
+
+    async fn quote(&self, provider: Arc<M>, req: &FlashRequest) -> anyhow::Result<Option<FlashLoan>> {
+        if req.buy_adapter != "uniswapv2" {
+            return Ok(None);
+        }
+
+        let (reserve0, reserve1, _) = UniswapV2Pair::new(req.buy.pair_address, provider)
+            .get_reserves()
+            .call()
+            .await?;
+        // V2 pairs sort their tokens by address
+        let base_reserve = if req.pair.base.id < req.pair.quote.id { reserve0 } else { reserve1 };
+        if U256::from(base_reserve) <= req.base_out {
+            return Ok(None);
+        }
+
+        Ok(Some(FlashLoan {
+            source: FlashSource::V2FlashSwap,
+            lender: req.buy.pair_address,
+            token: req.pair.base.id,
+            amount: req.base_out,
+            fee: U256::zero(),
+        }))
+    }
+}
+
+/// Every known lender, asked in parallel for each opportunity
+pub struct FlashLoanRouter<M> {
+    lenders: Vec<Arc<dyn FlashLender<M>>>,
+}
+
+impl<M: Middleware + 'static> Default for FlashLoanRouter<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M: Middleware + 'static> FlashLoanRouter<M> {
+    pub fn new() -> Self {
+        Self { lenders: Vec::new() }
+    }
+
+    /// Balancer, V2 flash swap, Uniswap V3 flash and Aave V3, in the order
+    /// ties on fee are broken
+    pub fn with_defaults() -> Self {
+        let mut router = Self::new();
+        router.register(Arc::new(BalancerLender { vault: BALANCER_VAULT.parse().expect("valid vault address") }));
+        router.register(Arc::new(V2FlashSwapLender));
+        router.register(Arc::new(UniswapV3FlashLender::new(
+            UNISWAPV3_FACTORY.parse().expect("valid factory address"),
+        )));
+        router.register(Arc::new(AaveV3Lender { pool: AAVE_V3_POOL.parse().expect("valid pool address") }));
+        router
+    }
+
+    pub fn register(&mut self, lender: Arc<dyn FlashLender<M>>) {
+        self.lenders.push(lender);
+    }
+
+    /// Cheapest lender able to fund `req`, comparing fees in quote. Lenders
+    /// that fail to quote are skipped; none being able to lend is an error.
+    pub async fn cheapest(&self, provider: Arc<M>, req: &FlashRequest) -> anyhow::Result<FlashLoan> {
+        let quotes = join_all(self.lenders.iter().map(|lender| {
+            let provider = provider.clone();
+            async move { (lender.source(), lender.quote(provider, req).await) }
+        }))
+        .await;
+
+        let mut best: Option<(FlashLoan, U256)> = None;
+        for (source, quote) in quotes {
+            let priced = match quote {
+                Ok(Some(loan)) => loan.fee_in_quote(&req.pair, req.base_out).map(|fee| Some((loan, fee))),
+                Ok(None) => Ok(None),
+                Err(e) => Err(e),
+            };
+            match priced {
+                Ok(Some((loan, fee))) if best.is_none_or(|(_, b)| fee < b) => best = Some((loan, fee)),
+                Ok(_) => {}
+                Err(e) => tracing::debug!("Flash lender {:?} failed to quote: {:?}", source, e),
+            }
+        }
+
+        best.map(|(loan, _)| loan).ok_or_else(|| anyhow::anyhow!("No flash lender can fund {} of {:?}", req.pair.trade_size, req.pair.quote.id))
+    }
+}
@@ -0,0 +1,595 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use ethers::{
+    providers::Middleware,
+    types::{BlockId, Transaction, transaction::eip2718::TypedTransaction},
+};
+use revm::{
+    DatabaseCommit, DatabaseRef, Evm,
+    db::CacheDB,
+    primitives::{
+        AccountInfo, Address, B256, Bytecode, Bytes, ExecutionResult, KECCAK_EMPTY, Log, Output,
+        TxKind, U256,
+    },
+};
+use serde::{Deserialize, Serialize};
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+use crate::{constants::CHAIN_ID, simulation::RevertReason, types::BlockInfo};
+
+pub fn to_revm_address(address: ethers::types::Address) -> Address {
+    Address::from(address.0)
+}
+
+pub fn to_revm_u256(value: ethers::types::U256) -> U256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    U256::from_be_bytes(bytes)
+}
+
+pub fn to_ethers_u256(value: U256) -> ethers::types::U256 {
+    ethers::types::U256::from_big_endian(&value.to_be_bytes::<32>())
+}
+
+/// Where `ForkState` loads accounts and slots it hasn't seen yet
+trait StateSource: Send + Sync {
+    fn account(&self, address: Address) -> anyhow::Result<AccountInfo>;
+    fn storage(&self, address: Address, slot: U256) -> anyhow::Result<U256>;
+    fn block_hash(&self, number: u64) -> anyhow::Result<B256>;
+}
+
+/// Reads chain state at a fixed block through the bot's provider. revm's
+/// database traits are synchronous, so each read blocks the current worker
+/// thread on the async call; on any other runtime the read fails instead.
+struct ProviderSource<M> {
+    provider: Arc<M>,
+    block: BlockId,
+}
+
+impl<M: Middleware + 'static> ProviderSource<M> {
+    fn block_on<F: std::future::Future>(fut: F) -> anyhow::Result<F::Output> {
+        let handle = Handle::try_current().map_err(|_| anyhow::anyhow!("Fork state reads need a Tokio runtime"))?;
+        // block_in_place panics on a current-thread runtime
+        if handle.runtime_flavor() != RuntimeFlavor::MultiThread {
+            anyhow::bail!("Fork state reads need a multi-threaded Tokio runtime");
+        }
+        Ok(tokio::task::block_in_place(|| handle.block_on(fut)))
+    }
+}
+
+impl<M: Middleware + 'static> StateSource for ProviderSource<M> {
+    fn account(&self, address: Address) -> anyhow::Result<AccountInfo> {
+        let addr = ethers::types::Address::from(address.into_array());
+        let (balance, nonce, code) = Self::block_on(async {
+            tokio::try_join!(
+                self.provider.get_balance(addr, Some(self.block)),
+                self.provider.get_transaction_count(addr, Some(self.block)),
+                self.provider.get_code(addr, Some(self.block)),
+            )
+        })?
+        .map_err(|e| anyhow::anyhow!("Failed to load account {}: {:?}", address, e))?;
+
+        let code = Bytecode::new_raw(code.0.into());
+        Ok(AccountInfo::new(to_revm_u256(balance), nonce.as_u64(), code.hash_slow(), code))
+    }
+
+    fn storage(&self, address: Address, slot: U256) -> anyhow::Result<U256> {
+        let addr = ethers::types::Address::from(address.into_array());
+        let slot = ethers::types::H256::from(slot.to_be_bytes::<32>());
+        let value = Self::block_on(self.provider.get_storage_at(addr, slot, Some(self.block)))?
+            .map_err(|e| anyhow::anyhow!("Failed to load slot {} of {}: {:?}", slot, address, e))?;
+        Ok(U256::from_be_bytes(value.0))
+    }
+
+    fn block_hash(&self, number: u64) -> anyhow::Result<B256> {
+        let block = Self::block_on(self.provider.get_block(number))?
+            .map_err(|e| anyhow::anyhow!("Failed to load block {}: {:?}", number, e))?;
+        Ok(block.and_then(|b| b.hash).map_or(B256::ZERO, |h| B256::from(h.0)))
+    }
+}
+
+/// Chain state pinned to one block, loaded lazily from the provider and
+/// cached for every simulation against that block. Simulations run on a
+/// throwaway overlay, so the cache only ever holds on-chain values.
+pub struct ForkState {
+    block: BlockInfo,
+    source: Option<Box<dyn StateSource>>,
+    accounts: DashMap<Address, AccountInfo>,
+    storage: DashMap<(Address, U256), U256>,
+    contracts: DashMap<B256, Bytecode>,
+    block_hashes: DashMap<u64, B256>,
+}
+
+impl ForkState {
+    pub fn new<M: Middleware + 'static>(provider: Arc<M>, block: BlockInfo) -> Self {
+        let source = ProviderSource { provider, block: BlockId::Hash(block.hash) };
+        Self::with_source(block, Some(Box::new(source)))
+    }
+
+    fn with_source(block: BlockInfo, source: Option<Box<dyn StateSource>>) -> Self {
+        Self {
+            block,
+            source,
+            accounts: DashMap::new(),
+            storage: DashMap::new(),
+            contracts: DashMap::new(),
+            block_hashes: DashMap::new(),
+        }
+    }
+
+    pub fn block(&self) -> &BlockInfo {
+        &self.block
+    }
+
+    /// Everything loaded so far, for replaying offline
+    pub fn snapshot(&self) -> StateSnapshot {
+        let mut accounts: BTreeMap<Address, AccountSnapshot> = self
+            .accounts
+            .iter()
+            .map(|e| {
+                let info = e.value();
+                let code = info.code.as_ref().map(|c| c.original_bytes()).unwrap_or_default();
+                (*e.key(), AccountSnapshot { balance: info.balance, nonce: info.nonce, code, storage: BTreeMap::new() })
+            })
+            .collect();
+        for e in self.storage.iter() {
+            let (address, slot) = *e.key();
+            accounts.entry(address).or_default().storage.insert(slot, *e.value());
+        }
+
+        StateSnapshot {
+            block: BlockSnapshot::from(&self.block),
+            accounts,
+            block_hashes: self.block_hashes.iter().map(|e| (*e.key(), *e.value())).collect(),
+        }
+    }
+
+    /// Offline state from a snapshot; anything it lacks reads as empty
+    pub fn from_snapshot(snapshot: StateSnapshot) -> Self {
+        let state = Self::with_source(snapshot.block.into(), None);
+        for (address, account) in snapshot.accounts {
+            let code = Bytecode::new_raw(account.code);
+            let hash = if code.is_empty() { KECCAK_EMPTY } else { code.hash_slow() };
+            state.contracts.insert(hash, code.clone());
+            state.accounts.insert(address, AccountInfo::new(account.balance, account.nonce, hash, code));
+            for (slot, value) in account.storage {
+                state.storage.insert((address, slot), value);
+            }
+        }
+        for (number, hash) in snapshot.block_hashes {
+            state.block_hashes.insert(number, hash);
+        }
+        state
+    }
+}
+
+impl DatabaseRef for ForkState {
+    type Error = anyhow::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        if let Some(info) = self.accounts.get(&address) {
+            return Ok(Some(info.clone()));
+        }
+        let info = match &self.source {
+            Some(source) => source.account(address)?,
+            None => AccountInfo::default(),
+        };
+        if let Some(code) = &info.code {
+            self.contracts.insert(info.code_hash, code.clone());
+        }
+        self.accounts.insert(address, info.clone());
+        Ok(Some(info))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // Code is always loaded alongside its account
+        Ok(self.contracts.get(&code_hash).map(|c| c.clone()).unwrap_or_default())
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        if let Some(value) = self.storage.get(&(address, index)) {
+            return Ok(*value);
+        }
+        let value = match &self.source {
+            Some(source) => source.storage(address, index)?,
+            None => U256::ZERO,
+        };
+        self.storage.insert((address, index), value);
+        Ok(value)
+    }
+
+    fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+        let number = number.saturating_to::<u64>();
+        if let Some(hash) = self.block_hashes.get(&number) {
+            return Ok(*hash);
+        }
+        let hash = match &self.source {
+            Some(source) => source.block_hash(number)?,
+            None => B256::ZERO,
+        };
+        self.block_hashes.insert(number, hash);
+        Ok(hash)
+    }
+}
+
+/// A transaction as the local EVM executes it
+#[derive(Debug, Clone)]
+pub struct SimTx {
+    pub from: Address,
+    pub to: Address,
+    pub data: Bytes,
+    pub value: U256,
+    pub gas_limit: u64,
+    pub gas_price: U256,
+    pub priority_fee: Option<U256>,
+}
+
+impl SimTx {
+    /// A filled transaction (ours, before signing)
+    pub fn from_typed(tx: &TypedTransaction) -> anyhow::Result<Self> {
+        let to = tx
+            .to_addr()
+            .ok_or_else(|| anyhow::anyhow!("Contract creation can't be simulated as a swap"))?;
+        let (gas_price, priority_fee) = match tx {
+            TypedTransaction::Eip1559(tx) => (tx.max_fee_per_gas, tx.max_priority_fee_per_gas),
+            _ => (tx.gas_price(), None),
+        };
+
+        Ok(Self {
+            from: to_revm_address(tx.from().copied().unwrap_or_default()),
+            to: to_revm_address(*to),
+            data: tx.data().cloned().unwrap_or_default().0.into(),
+            value: to_revm_u256(tx.value().copied().unwrap_or_default()),
+            gas_limit: tx.gas().map_or(30_000_000, |g| g.as_u64()),
+            gas_price: to_revm_u256(gas_price.unwrap_or_default()),
+            priority_fee: priority_fee.map(to_revm_u256),
+        })
+    }
+
+    /// A pending transaction seen in the mempool (e.g. a backrun victim)
+    pub fn from_pending(tx: &Transaction) -> anyhow::Result<Self> {
+        let to = tx.to.ok_or_else(|| anyhow::anyhow!("Victim {:?} is a contract creation", tx.hash))?;
+        let gas_price = tx.max_fee_per_gas.or(tx.gas_price).unwrap_or_default();
+
+        Ok(Self {
+            from: to_revm_address(tx.from),
+            to: to_revm_address(to),
+            data: tx.input.0.clone().into(),
+            value: to_revm_u256(tx.value),
+            gas_limit: tx.gas.as_u64(),
+            gas_price: to_revm_u256(gas_price),
+            priority_fee: tx.max_priority_fee_per_gas.map(to_revm_u256),
+        })
+    }
+}
+
+/// One storage slot or balance an executed transaction changed
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountDiff {
+    pub address: Address,
+    pub balance: Option<(U256, U256)>,
+    /// (slot, before, after)
+    pub storage: Vec<(U256, U256, U256)>,
+}
+
+/// Result of one transaction in a simulated bundle
+#[derive(Debug, Clone)]
+pub struct SimOutcome {
+    pub success: bool,
+    pub gas_used: u64,
+    pub output: Bytes,
+    pub revert: Option<RevertReason>,
+    pub logs: Vec<Log>,
+    pub state_diff: Vec<AccountDiff>,
+    pub elapsed: Duration,
+}
+
+/// Execute `txs` in order on top of `state`, as the first transactions of
+/// the block after it. Each sees the previous one's writes, so a victim
+/// swap can precede our backrun. `relaxed` skips the sender balance check
+/// (dry runs from a throwaway key).
+pub fn simulate_bundle(state: &ForkState, txs: &[SimTx], relaxed: bool) -> anyhow::Result<Vec<SimOutcome>> {
+    let header = state.block();
+    let mut db = CacheDB::new(state);
+    let mut outcomes = Vec::with_capacity(txs.len());
+
+    for tx in txs {
+        let started = Instant::now();
+        let mut evm = Evm::builder()
+            .with_db(&mut db)
+            .modify_cfg_env(|cfg| {
+                cfg.chain_id = CHAIN_ID;
+                cfg.disable_balance_check = relaxed;
+            })
+            .modify_block_env(|block| {
+                block.number = U256::from(header.number + 1);
+                block.timestamp = U256::from(header.timestamp + 1);
+                block.gas_limit = to_revm_u256(header.gas_limit);
+                block.basefee = header.base_fee.map(to_revm_u256).unwrap_or_default();
+            })
+            .modify_tx_env(|env| {
+                env.caller = tx.from;
+                env.transact_to = TxKind::Call(tx.to);
+                env.data = tx.data.clone();
+                env.value = tx.value;
+                env.gas_limit = tx.gas_limit;
+                env.gas_price = tx.gas_price;
+                env.gas_priority_fee = tx.priority_fee;
+                env.nonce = None;
+            })
+            .build();
+
+        let result = evm
+            .transact()
+            .map_err(|e| anyhow::anyhow!("Local EVM failed: {:?}", e))?;
+        drop(evm);
+
+        let state_diff = result
+            .state
+            .iter()
+            .filter(|(_, account)| account.is_touched())
+            .filter_map(|(address, account)| {
+                let before = db.basic_ref(*address).ok().flatten().map(|i| i.balance).unwrap_or_default();
+                let balance = (before != account.info.balance).then_some((before, account.info.balance));
+                let storage: Vec<_> = account
+                    .changed_storage_slots()
+                    .map(|(slot, s)| (*slot, s.original_value, s.present_value))
+                    .collect();
+                (balance.is_some() || !storage.is_empty()).then_some(AccountDiff {
+                    address: *address,
+                    balance,
+                    storage,
+                })
+            })
+            .collect();
+
+        let outcome = match result.result {
+            ExecutionResult::Success { gas_used, logs, output, .. } => SimOutcome {
+                success: true,
+                gas_used,
+                output: match output {
+                    Output::Call(bytes) | Output::Create(bytes, _) => bytes,
+                },
+                revert: None,
+                logs,
+                state_diff,
+                elapsed: Duration::ZERO,
+            },
+            ExecutionResult::Revert { gas_used, output } => SimOutcome {
+                success: false,
+                gas_used,
+                revert: Some(RevertReason::decode(&output)),
+                output,
+                logs: Vec::new(),
+                state_diff: Vec::new(),
+                elapsed: Duration::ZERO,
+            },
+            ExecutionResult::Halt { reason, gas_used } => SimOutcome {
+                success: false,
+                gas_used,
+                output: Bytes::new(),
+                revert: Some(RevertReason::Error(format!("halted: {:?}", reason))),
+                logs: Vec::new(),
+                state_diff: Vec::new(),
+                elapsed: Duration::ZERO,
+            },
+        };
+
+        db.commit(result.state);
+        outcomes.push(SimOutcome { elapsed: started.elapsed(), ..outcome });
+    }
+
+    Ok(outcomes)
+}
+
+/// `ForkState` for the current head, replaced whenever the head moves
+pub struct ForkCache<M> {
+    provider_of: Box<dyn Fn() -> Arc<M> + Send + Sync>,
+    current: Mutex<Option<Arc<ForkState>>>,
+}
+
+impl<M: Middleware + 'static> ForkCache<M> {
+    pub fn new(provider_of: impl Fn() -> Arc<M> + Send + Sync + 'static) -> Self {
+        Self {
+            provider_of: Box::new(provider_of),
+            current: Mutex::new(None),
+        }
+    }
+
+    /// State pinned at `block`, shared by every simulation on that block
+    pub fn at(&self, block: &BlockInfo) -> Arc<ForkState> {
+        let mut current = self.current.lock().expect("fork cache lock poisoned");
+        match current.as_ref() {
+            Some(state) if state.block().hash == block.hash => state.clone(),
+            _ => {
+                let state = Arc::new(ForkState::new((self.provider_of)(), block.clone()));
+                *current = Some(state.clone());
+                state
+            }
+        }
+    }
+}
+
+/// Serializable copy of a `ForkState`, used as an offline fixture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub block: BlockSnapshot,
+    pub accounts: BTreeMap<Address, AccountSnapshot>,
+    pub block_hashes: BTreeMap<u64, B256>,
+}
+
+impl StateSnapshot {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path.as_ref())?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = std::fs::File::create(path.as_ref())?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountSnapshot {
+    pub balance: U256,
+    pub nonce: u64,
+    pub code: Bytes,
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// The `BlockInfo` fields a simulation needs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockSnapshot {
+    pub number: u64,
+    pub hash: B256,
+    pub parent_hash: B256,
+    pub timestamp: u64,
+    pub base_fee: Option<U256>,
+    pub gas_limit: U256,
+}
+
+impl From<&BlockInfo> for BlockSnapshot {
+    fn from(block: &BlockInfo) -> Self {
+        Self {
+            number: block.number,
+            hash: B256::from(block.hash.0),
+            parent_hash: B256::from(block.parent_hash.0),
+            timestamp: block.timestamp,
+            base_fee: block.base_fee.map(to_revm_u256),
+            gas_limit: to_revm_u256(block.gas_limit),
+        }
+    }
+}
+
+impl From<BlockSnapshot> for BlockInfo {
+    fn from(block: BlockSnapshot) -> Self {
+        Self {
+            number: block.number,
+            hash: ethers::types::H256(block.hash.0),
+            parent_hash: ethers::types::H256(block.parent_hash.0),
+            timestamp: block.timestamp,
+            base_fee: block.base_fee.map(to_ethers_u256),
+            gas_limit: to_ethers_u256(block.gas_limit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        abi::AbiEncode,
+        providers::{Http, Provider},
+    };
+
+    use super::*;
+    use crate::{
+        bindings::erc20::{BalanceOfCall, TransferCall},
+        dex_adapter::encoders,
+        types::SwapParams,
+    };
+
+    /// A V2 pair holding 1,000 token0 (18 decimals) against 500 token1 (6
+    /// decimals), and a trader holding 10 token0. The contracts are small
+    /// hand-assembled stand-ins for the ERC20 and UniswapV2Pair ABIs.
+    /// Balances live in a slot-0 mapping. The pair keeps token0, token1,
+    /// reserve0 and reserve1 in slots 0 to 3 and enforces V2's 0.3% `k`
+    /// check.
+    const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/fork_sim/v2_pair.json");
+
+    const TOKEN0: Address = Address::repeat_byte(0x10);
+    const TOKEN1: Address = Address::repeat_byte(0x20);
+    const PAIR: Address = Address::repeat_byte(0x30);
+    const TRADER: Address = Address::repeat_byte(0x40);
+    /// getAmountOut(1e18, 1_000e18, 500e6)
+    const QUOTED_OUT: u64 = 498_003;
+
+    fn ethers_address(address: Address) -> ethers::types::Address {
+        ethers::types::Address::from(address.into_array())
+    }
+
+    fn fixture() -> ForkState {
+        ForkState::from_snapshot(StateSnapshot::load(FIXTURE).unwrap())
+    }
+
+    fn tx(to: Address, data: Vec<u8>) -> SimTx {
+        SimTx {
+            from: TRADER,
+            to,
+            data: data.into(),
+            value: U256::ZERO,
+            gas_limit: 200_000,
+            gas_price: U256::from(30_000_000_000u64),
+            priority_fee: None,
+        }
+    }
+
+    /// Pay 1 token0 into the pair, swap it for `amount_out` token1, then
+    /// read the trader's token1 balance
+    fn swap_bundle(amount_out: u64) -> Vec<SimTx> {
+        let amount_in = ethers::types::U256::exp10(18);
+        let swap = encoders::v2_pool_swap(
+            &SwapParams {
+                token_in: ethers_address(TOKEN0),
+                token_out: ethers_address(TOKEN1),
+                amount_in,
+                min_amount_out: ethers::types::U256::zero(),
+                recipient: ethers_address(TRADER),
+                deadline: ethers::types::U256::MAX,
+            },
+            amount_out.into(),
+        )
+        .unwrap();
+
+        vec![
+            tx(TOKEN0, TransferCall { to: ethers_address(PAIR), amount: amount_in }.encode()),
+            tx(PAIR, swap.to_vec()),
+            tx(TOKEN1, BalanceOfCall { account: ethers_address(TRADER) }.encode()),
+        ]
+    }
+
+    #[test]
+    fn simulates_a_v2_swap_from_the_snapshot() {
+        let outcomes = simulate_bundle(&fixture(), &swap_bundle(QUOTED_OUT), false).unwrap();
+
+        assert!(outcomes.iter().all(|o| o.success), "{:?}", outcomes);
+        assert_eq!(U256::from_be_slice(&outcomes[2].output), U256::from(QUOTED_OUT));
+
+        // Reserves move by exactly the amounts in and out
+        let pair = outcomes[1].state_diff.iter().find(|d| d.address == PAIR).unwrap();
+        let e18 = U256::from(10u64).pow(U256::from(18));
+        assert!(pair.storage.contains(&(U256::from(2), U256::from(1_000u64) * e18, U256::from(1_001u64) * e18)));
+        assert!(pair.storage.contains(&(U256::from(3), U256::from(500_000_000u64), U256::from(500_000_000 - QUOTED_OUT))));
+        assert!(outcomes[1].logs.iter().any(|log| log.address == PAIR));
+    }
+
+    #[test]
+    fn rejects_a_v2_swap_past_the_quote() {
+        let outcomes = simulate_bundle(&fixture(), &swap_bundle(QUOTED_OUT + 1), false).unwrap();
+
+        assert!(outcomes[0].success);
+        assert!(!outcomes[1].success);
+        assert_eq!(outcomes[1].revert, Some(RevertReason::Error("UniswapV2: K".to_string())));
+        assert_eq!(U256::from_be_slice(&outcomes[2].output), U256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn provider_reads_fail_on_a_current_thread_runtime() {
+        let provider = Provider::<Http>::try_from("http://127.0.0.1:1").unwrap();
+        let block = StateSnapshot::load(FIXTURE).unwrap().block.into();
+        let state = ForkState::new(Arc::new(provider), block);
+
+        let error = state.basic_ref(TRADER).unwrap_err();
+        assert!(error.to_string().contains("multi-threaded"), "{}", error);
+    }
+}
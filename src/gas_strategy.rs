@@ -0,0 +1,292 @@
+use std::sync::Mutex;
+
+use ethers::{
+    providers::Middleware,
+    types::{
+        Address, BlockNumber, Eip1559TransactionRequest, TransactionRequest, U256,
+        transaction::eip2718::TypedTransaction,
+    },
+    utils::parse_units,
+};
+
+use crate::{
+    config::GasMode,
+    constants::{
+        BASE_FEE_MULTIPLIER, DEFAULT_FEE_HISTORY_BLOCKS, DEFAULT_PRIORITY_FEE_CEILING_GWEI,
+        DEFAULT_PRIORITY_FEE_FLOOR_GWEI, DEFAULT_PRIORITY_FEE_PROFIT_SHARE_BPS, FEE_HISTORY_PERCENTILE, USDC, USDT,
+        WPOL,
+    },
+    helpers::{env_u64, to_f64_normalized},
+    types::{BlockInfo, PriceTracker, Token},
+};
+
+/// Fees to bid on one transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasFees {
+    /// `maxFeePerGas`, or the gas price in legacy mode
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+}
+
+/// Prices arb transactions from the head's base fee plus a tip.
+///
+/// The tip is `PRIORITY_FEE_PROFIT_SHARE_BPS` of the expected profit spread
+/// over the gas limit, clamped to the floor and ceiling. When the profit
+/// can't be valued in the native token, the recent `eth_feeHistory` tip
+/// percentile is bid instead.
+pub struct GasStrategy {
+    pub mode: GasMode,
+    pub profit_share_bps: u64,
+    pub floor: U256,
+    pub ceiling: U256,
+    pub history_blocks: u64,
+    /// Historical tip, fetched at most once per block
+    history: Mutex<Option<(u64, U256)>>,
+}
+
+impl Default for GasStrategy {
+    fn default() -> Self {
+        Self::new(
+            GasMode::Eip1559,
+            DEFAULT_PRIORITY_FEE_PROFIT_SHARE_BPS,
+            gwei(DEFAULT_PRIORITY_FEE_FLOOR_GWEI),
+            gwei(DEFAULT_PRIORITY_FEE_CEILING_GWEI),
+            DEFAULT_FEE_HISTORY_BLOCKS,
+        )
+    }
+}
+
+impl GasStrategy {
+    pub fn new(mode: GasMode, profit_share_bps: u64, floor: U256, ceiling: U256, history_blocks: u64) -> Self {
+        Self {
+            mode,
+            profit_share_bps,
+            floor,
+            ceiling,
+            history_blocks,
+            history: Mutex::new(None),
+        }
+    }
+
+    /// `GAS_MODE`, `PRIORITY_FEE_PROFIT_SHARE_BPS`, `PRIORITY_FEE_FLOOR_GWEI`,
+    /// `PRIORITY_FEE_CEILING_GWEI` and `FEE_HISTORY_BLOCKS`
+    pub fn from_env() -> anyhow::Result<Self> {
+        let floor = gwei(env_u64("PRIORITY_FEE_FLOOR_GWEI", DEFAULT_PRIORITY_FEE_FLOOR_GWEI));
+        let ceiling = gwei(env_u64("PRIORITY_FEE_CEILING_GWEI", DEFAULT_PRIORITY_FEE_CEILING_GWEI));
+        if floor > ceiling {
+            anyhow::bail!("PRIORITY_FEE_FLOOR_GWEI must not exceed PRIORITY_FEE_CEILING_GWEI");
+        }
+
+        Ok(Self::new(
+            GasMode::from_env()?,
+            env_u64("PRIORITY_FEE_PROFIT_SHARE_BPS", DEFAULT_PRIORITY_FEE_PROFIT_SHARE_BPS),
+            floor,
+            ceiling,
+            env_u64("FEE_HISTORY_BLOCKS", DEFAULT_FEE_HISTORY_BLOCKS).max(1),
+        ))
+    }
+
+    /// Empty transaction of the type this strategy bids with
+    pub fn new_tx(&self) -> TypedTransaction {
+        match self.mode {
+            GasMode::Eip1559 => Eip1559TransactionRequest::new().into(),
+            GasMode::Legacy => TransactionRequest::new().into(),
+        }
+    }
+
+    /// Fees for a transaction in the block after `block`. `profit` is the
+    /// expected profit in native wei, if known.
+    pub async fn quote<M: Middleware + 'static>(
+        &self,
+        provider: &M,
+        block: &BlockInfo,
+        gas_limit: U256,
+        profit: Option<U256>,
+    ) -> anyhow::Result<GasFees> {
+        let tip = match profit {
+            Some(profit) if !gas_limit.is_zero() => {
+                profit * U256::from(self.profit_share_bps) / U256::from(10_000) / gas_limit
+            }
+            _ => self.historical_tip(provider, block.number).await?,
+        };
+        let tip = tip.clamp(self.floor, self.ceiling);
+
+        let base_fee = match block.base_fee {
+            Some(base_fee) => base_fee,
+            // No base fee in the header: price off the node's gas price
+            None => provider
+                .get_gas_price()
+                .await
+                .map_err(|e| anyhow::anyhow!("eth_gasPrice failed: {:?}", e))?,
+        };
+
+        // Room for the base fee to rise before the tx is priced out. A legacy
+        // gas price needs it as much, though it pays all of it when mined.
+        let max_fee_per_gas = base_fee * BASE_FEE_MULTIPLIER + tip;
+        Ok(GasFees { max_fee_per_gas, max_priority_fee_per_gas: tip })
+    }
+
+    /// Set `fees` on `tx`, as EIP-1559 fields or a legacy gas price
+    pub fn apply(&self, tx: &mut TypedTransaction, fees: GasFees) {
+        match tx {
+            TypedTransaction::Eip1559(inner) => {
+                inner.max_fee_per_gas = Some(fees.max_fee_per_gas);
+                inner.max_priority_fee_per_gas = Some(fees.max_priority_fee_per_gas);
+            }
+            _ => {
+                tx.set_gas_price(fees.max_fee_per_gas);
+            }
+        }
+    }
+
+    /// Median of the recent blocks' `FEE_HISTORY_PERCENTILE` tips
+    async fn historical_tip<M: Middleware + 'static>(&self, provider: &M, block: u64) -> anyhow::Result<U256> {
+        if let Some((at, tip)) = *self.history.lock().expect("fee history lock poisoned")
+            && at == block
+        {
+            return Ok(tip);
+        }
+
+        let history = provider
+            .fee_history(self.history_blocks, BlockNumber::Latest, &[FEE_HISTORY_PERCENTILE])
+            .await
+            .map_err(|e| anyhow::anyhow!("eth_feeHistory failed: {:?}", e))?;
+
+        let mut tips: Vec<U256> = history.reward.iter().filter_map(|r| r.first().copied()).collect();
+        tips.sort();
+        let tip = tips.get(tips.len() / 2).copied().unwrap_or(self.floor);
+
+        *self.history.lock().expect("fee history lock poisoned") = Some((block, tip));
+        Ok(tip)
+    }
+}
+
+/// Value `amount` of `token` in native wei, through a tracked WPOL pool
+/// of that token, or else through USDC or USDT: the token's stable price
+/// times the stable's WPOL rate. `None` when no route prices it.
+pub async fn native_value(tracker: &PriceTracker, token: &Token, amount: U256) -> Option<U256> {
+    let wpol: Address = WPOL.parse().ok()?;
+
+    let mut to_native = rate(tracker, token.id, wpol).await;
+    for stable in [USDC, USDT] {
+        if to_native.is_some() {
+            break;
+        }
+        let stable: Address = stable.parse().ok()?;
+        if let (Some(to_usd), Some(usd_to_native)) =
+            (rate(tracker, token.id, stable).await, rate(tracker, stable, wpol).await)
+        {
+            to_native = Some(to_usd * usd_to_native);
+        }
+    }
+
+    let native = to_f64_normalized(amount, token.decimals).ok()? * to_native?;
+    parse_units(format!("{:.18}", native), 18).ok().map(Into::into)
+}
+
+/// Units of `to` per unit of `from`, averaged over every tracked pool of
+/// the two, whichever way round the pool quotes them
+async fn rate(tracker: &PriceTracker, from: Address, to: Address) -> Option<f64> {
+    if from == to {
+        return Some(1.0);
+    }
+
+    // Tracked prices are quote per base
+    let mut rates: Vec<f64> = Vec::new();
+    if let Ok(prices) = tracker.get_all_for_pair(from, to).await {
+        rates.extend(prices.iter().map(|(_, info)| info.price));
+    }
+    if let Ok(prices) = tracker.get_all_for_pair(to, from).await {
+        rates.extend(prices.iter().map(|(_, info)| 1.0 / info.price));
+    }
+    rates.retain(|r| r.is_finite() && *r > 0.0);
+    if rates.is_empty() {
+        return None;
+    }
+
+    Some(rates.iter().sum::<f64>() / rates.len() as f64)
+}
+
+fn gwei(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(9)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::H256;
+
+    use super::*;
+    use crate::provider_factory::ProviderFactory;
+
+    fn block(base_fee: u64) -> BlockInfo {
+        BlockInfo {
+            number: 65_000_000,
+            hash: H256::repeat_byte(0x11),
+            parent_hash: H256::repeat_byte(0x10),
+            timestamp: 1_760_000_000,
+            base_fee: Some(gwei(base_fee)),
+            gas_limit: U256::from(30_000_000u64),
+        }
+    }
+
+    fn native(amount: &str) -> U256 {
+        parse_units(amount, 18).unwrap().into()
+    }
+
+    async fn track(tracker: &PriceTracker, pool: u8, base: Address, quote: Address, price: f64) {
+        tracker
+            .update(Address::repeat_byte(0xf0), Address::repeat_byte(pool), base, quote, price)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn quote_tips_a_share_of_profit_per_unit_of_gas() {
+        // No RPC call is expected: the head carries a base fee and the profit is known
+        let (provider, _mock) = ProviderFactory::default().mock();
+        let strategy = GasStrategy::new(GasMode::Eip1559, 2_000, gwei(30), gwei(500), 1);
+        let gas_limit = U256::from(1_000_000u64);
+
+        // 20% of 1 POL over 1M gas: 200 gwei
+        let fees = strategy.quote(&provider, &block(50), gas_limit, Some(native("1"))).await.unwrap();
+        assert_eq!(fees.max_priority_fee_per_gas, gwei(200));
+        assert_eq!(fees.max_fee_per_gas, gwei(50) * BASE_FEE_MULTIPLIER + gwei(200));
+
+        let fees = strategy.quote(&provider, &block(50), gas_limit, Some(native("0.001"))).await.unwrap();
+        assert_eq!(fees.max_priority_fee_per_gas, gwei(30));
+
+        let fees = strategy.quote(&provider, &block(50), gas_limit, Some(native("100"))).await.unwrap();
+        assert_eq!(fees.max_priority_fee_per_gas, gwei(500));
+        assert_eq!(fees.max_fee_per_gas, gwei(50) * BASE_FEE_MULTIPLIER + gwei(500));
+    }
+
+    #[tokio::test]
+    async fn rate_averages_pools_quoted_either_way_round() {
+        let tracker = PriceTracker::new();
+        let (weth, usdc) = (Address::repeat_byte(0xe1), USDC.parse::<Address>().unwrap());
+        track(&tracker, 0x01, weth, usdc, 2_000.0).await;
+        assert_eq!(rate(&tracker, weth, usdc).await, Some(2_000.0));
+        assert_eq!(rate(&tracker, usdc, weth).await, Some(1.0 / 2_000.0));
+
+        // The same pair quoted the other way round
+        track(&tracker, 0x02, usdc, weth, 1.0 / 2_200.0).await;
+        let averaged = rate(&tracker, weth, usdc).await.unwrap();
+        assert!((averaged - 2_100.0).abs() < 1e-9, "{}", averaged);
+
+        assert_eq!(rate(&tracker, weth, Address::repeat_byte(0x99)).await, None);
+    }
+
+    #[tokio::test]
+    async fn native_value_falls_back_to_a_usd_stable_route() {
+        let tracker = PriceTracker::new();
+        let (usdc, wpol) = (USDC.parse::<Address>().unwrap(), WPOL.parse::<Address>().unwrap());
+        let weth = Token { id: Address::repeat_byte(0xe1), decimals: 18 };
+
+        assert_eq!(native_value(&tracker, &weth, native("1")).await, None);
+
+        // No WETH/WPOL pool: 2000 USDC per WETH, 0.5 USDC per WPOL
+        track(&tracker, 0x01, weth.id, usdc, 2_000.0).await;
+        track(&tracker, 0x02, wpol, usdc, 0.5).await;
+        assert_eq!(native_value(&tracker, &weth, native("1")).await, Some(native("4000")));
+    }
+}
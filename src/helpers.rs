@@ -1,50 +1,66 @@
 use ethers::{
-    providers::{Middleware, PubsubClient}, 
-    types::{Address, Bytes, H256 as TxHash, U256}
+    providers::{Middleware, Provider}, 
+    middleware::SignerMiddleware,
+    signers::{LocalWallet, Signer},
+    types::{
+        Address, Bytes, H256 as TxHash, U256, U512,
+        transaction::eip2718::TypedTransaction,
+    },
 };
 use crate::{
     bindings::erc20::IERC20, 
-    constants::{FACTORY_ROUTER_MAP, TOKEN_SYMBOL_CACHE}, 
-    dex_pool_resolver::DexPoolResolver,
-    dex_price_listener::{
-        DexPriceListener,
-        uniswapv2_price_listener::UniswapV2Listener, 
-        uniswapv3_price_listener::UniswapV3Listener,
-        quickswapv3_price_listener::QuickSwapV3Listener
-     },
+    config::ExecutionMode,
+    constants::{
+        CHAIN_ID, DEFAULT_ARB_GAS_LIMIT, DEFAULT_MIN_PROFIT_BPS,
+        FLASH_ARBITRAGEUR, SLIPPAGE_BPS, SWAP_DEADLINE_SECS, TOKEN_SYMBOL_CACHE,
+    },
+    dex_adapter::{DexAdapter, DexRegistry},
+    dex_price_listener::multiplexer::LogMultiplexer,
+    execution_context::ExecutionContext,
+    metrics::METRICS,
+    paper_trading::paper_trade,
+    flash_loan::FlashRequest,
+    fork_sim::ForkState,
+    gas_strategy::native_value,
+    inventory::execute_inventory_arb,
+    pnl::{OpenTrade, track_trade},
+    simulation::{simulate_local, simulate_tx},
+    provider_factory::{MeteredHttp, TimedWs},
+    rpc_pool::{RpcEndpoint, RpcPool},
     types::{
+        ArbBounds,
         ArbOpportunity,
+        BlockInfo,
         DexConfig, 
         DexPairConfig, 
         DexPairKey, 
         PriceInfo, 
         PriceTracker, 
-        Token,
-        DexType
+        SwapSimulation,
+        Token
     }
 };
 
 use std::sync::Arc;
-use tokio::task::JoinHandle;
 
 use std::fs;
 use std::env;
 
 use num_traits::ToPrimitive;
 
-pub async fn make_pair<M, R>(
+pub async fn make_pair<M>(
     factory: Address,
     base: Token,
     quote: Token,
     trade_size: U256,
+    adapter: Arc<dyn DexAdapter<M>>,
     provider: Arc<M>,
 ) -> anyhow::Result<DexPairConfig>
 where 
     M: Middleware + 'static,
-    R: DexPoolResolver<M>
 
 {
-    let pair = R::resolve_pool(factory, base.id, quote.id, provider.clone()).await?;
+    let pair = adapter.resolve_pool(factory, base.id, quote.id, provider.clone()).await?;
 
     Ok(DexPairConfig {
         pair,
@@ -61,7 +77,16 @@ pub fn create_arb_calldata(arb_opp: &ArbOpportunity) -> anyhow::Result<Bytes> {
         ethers::abi::Token::Address(arb_opp.quote_asset),
         ethers::abi::Token::Address(arb_opp.base_asset),
         ethers::abi::Token::Address(arb_opp.buy_dex),
-        ethers::abi::Token::Address(arb_opp.sell_dex)
+        ethers::abi::Token::Address(arb_opp.sell_dex),
+        ethers::abi::Token::Uint(arb_opp.bounds.min_base_out),
+        ethers::abi::Token::Uint(arb_opp.bounds.min_quote_out),
+        ethers::abi::Token::Uint(arb_opp.bounds.min_profit),
+        ethers::abi::Token::Uint(arb_opp.bounds.deadline),
+        ethers::abi::Token::Uint(U256::from(arb_opp.flash.source as u8)),
+        ethers::abi::Token::Address(arb_opp.flash.lender),
+        ethers::abi::Token::Address(arb_opp.flash.token),
+        ethers::abi::Token::Uint(arb_opp.flash.amount),
+        ethers::abi::Token::Uint(arb_opp.flash.fee),
     ];
     let encoded = ethers::abi::encode(&tokens);
     Ok(Bytes::from(encoded))
@@ -164,10 +189,10 @@ pub fn compute_spread(
             continue;
         }
 
-        if min.as_ref().map_or(true, |(_, p)| v.price < p.price) {
+        if min.as_ref().is_none_or(|(_, p)| v.price < p.price) {
             min = Some((*k, v.clone()));
         }
-        if max.as_ref().map_or(true, |(_, p)| v.price > p.price) {
+        if max.as_ref().is_none_or(|(_, p)| v.price > p.price) {
             max = Some((*k, v.clone()));
         }
     }
@@ -192,33 +217,193 @@ pub async fn handle_arb_opportunity<M: Middleware + 'static>(
     sell_k: DexPairKey, 
     spread: f64,
     pair: &DexPairConfig,
-    provider: Arc<M>,
-) -> anyhow::Result<()> {
+    block: &BlockInfo,
+    ctx: &ExecutionContext<M>,
+) -> anyhow::Result<Option<TxHash>> {
     tracing::info!(
         "Detected arb between {:?} and {:?}, spread {:.3}%",
         buy_k.dex_factory, sell_k.dex_factory, spread * 100.0
     );
 
+    if ctx.rpc_pool.quorum_reads {
+        verify_pool_states(&[buy_k, sell_k], ctx).await?;
+    }
+
+    let (buy, sell) = quote_legs(buy_k, sell_k, pair, ctx).await?;
+    if ctx.inventory.is_some() {
+        return execute_inventory_arb(buy_k, sell_k, pair, (buy, sell), block, ctx).await;
+    }
+
+    let flash = ctx
+        .flash
+        .cheapest(
+            ctx.provider(),
+            &FlashRequest {
+                pair: pair.clone(),
+                buy: buy_k,
+                sell: sell_k,
+                buy_adapter: ctx.registry.for_factory(buy_k.dex_factory)?.name().to_string(),
+                base_out: buy.amount_out,
+            },
+        )
+        .await?;
+    let flash_fee = flash.fee_in_quote(pair, buy.amount_out)?;
+    if sell.amount_out <= pair.trade_size + flash_fee {
+        tracing::info!(
+            "Quoted legs return {} for {} plus {:?} flash fee {}; skipping",
+            sell.amount_out, pair.trade_size, flash.source, flash_fee
+        );
+        return Ok(None);
+    }
+    tracing::debug!("💸 Flash loan from {:?} at {:?}, fee {}", flash.source, flash.lender, flash_fee);
+
+    let bounds = ArbBounds::from_quotes(
+        pair.trade_size,
+        buy.amount_out,
+        sell.amount_out,
+        flash_fee,
+        env_u64("SLIPPAGE_BPS", SLIPPAGE_BPS),
+        min_profit_for(pair.trade_size),
+        U256::from(block.timestamp + SWAP_DEADLINE_SECS),
+    );
+
     let arb_opp = ArbOpportunity {
         trade_amt: pair.trade_size,
         base_asset: pair.base.id,
         quote_asset: pair.quote.id,
 
-        buy_dex: *FACTORY_ROUTER_MAP
-            .get(&buy_k.dex_factory)
-            .ok_or(anyhow::anyhow!("Buy DEX router not found"))?,
+        buy_dex: ctx.registry.router(buy_k.dex_factory)?,
+        sell_dex: ctx.registry.router(sell_k.dex_factory)?,
 
-        sell_dex: *FACTORY_ROUTER_MAP
-            .get(&sell_k.dex_factory)
-            .ok_or(anyhow::anyhow!("Sell DEX router not found"))?,
+        bounds,
+        flash,
     };
 
     let arb_data = create_arb_calldata(&arb_opp)?;
-    tracing::debug!("Prepared calldata for arb execution ({} bytes)", arb_data.len());
+    tracing::debug!(
+        "Prepared calldata for arb execution ({} bytes), min out {} / {}, min profit {}",
+        arb_data.len(),
+        bounds.min_base_out,
+        bounds.min_quote_out,
+        bounds.min_profit
+    );
 
-    match execute_arb_tx(arb_data, provider.clone()).await {
-        Ok(tx_hash) => tracing::info!("✅ Executed arb tx: {:?}", tx_hash),
-        Err(e) => tracing::warn!("❌ Failed to execute arb tx: {:?}", e),
+    if ctx.mode == ExecutionMode::DryRun {
+        let trade = paper_trade(buy_k, sell_k, pair, &arb_opp, arb_data, (buy, sell), ctx).await?;
+        tracing::info!(
+            "📝 Paper trade: {:.6} profit in {:?} (eth_call: {})",
+            trade.profit,
+            trade.quote,
+            trade.call_result.as_deref().unwrap_or("no arb contract"),
+        );
+        ctx.paper.record(trade);
+        return Ok(None);
+    }
+
+    // Never start new trades once shutdown has begun
+    if ctx.shutdown.is_shutting_down() {
+        tracing::info!("Shutdown in progress; skipping arb execution");
+        return Ok(None);
+    }
+
+    let contract = arb_contract().ok_or_else(|| anyhow::anyhow!("No arb contract configured (ARB_CONTRACT)"))?;
+    let provider = ctx.provider();
+
+    // Fix every field up front so the simulation sees exactly what gets sent
+    let gas_limit = U256::from(env_u64("ARB_GAS_LIMIT", DEFAULT_ARB_GAS_LIMIT));
+    let mut tx = ctx.gas.new_tx();
+    tx.set_to(contract);
+    tx.set_data(arb_data);
+    tx.set_gas(gas_limit);
+    if let Some(from) = provider.default_sender() {
+        tx.set_from(from);
+    }
+    // Provisional bid so filling doesn't estimate fees; repriced once profit is known
+    ctx.gas.apply(&mut tx, ctx.gas.quote(&*provider, block, gas_limit, None).await?);
+    provider
+        .fill_transaction(&mut tx, None)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to fill arb tx: {:?}", e))?;
+
+    let min_profit = bounds.min_profit;
+    let profit = match &ctx.fork {
+        Some(fork) => {
+            let state = fork.at(block);
+            let profit = simulate_local(&state, &tx, None, min_profit, false)?;
+            save_fork_snapshot(&state, pair);
+            profit
+        }
+        None => simulate_tx(&*provider, &tx, min_profit, None).await?,
+    };
+    tracing::info!("🧪 Simulated profit {} for {:?}/{:?}", profit, pair.base.id, pair.quote.id);
+
+    let fees = ctx
+        .gas
+        .quote(&*provider, block, gas_limit, native_value(&ctx.tracker, &pair.quote, profit).await)
+        .await?;
+    ctx.gas.apply(&mut tx, fees);
+    tracing::debug!(
+        "⛽ Bidding {} wei tip, {} wei max fee",
+        fees.max_priority_fee_per_gas,
+        fees.max_fee_per_gas
+    );
+
+    METRICS.opportunity("attempted");
+    let tx_hash = execute_arb_tx(tx, min_profit, ctx)
+        .await
+        .map_err(|e| anyhow::anyhow!("❌ Failed to execute arb tx: {:?}", e))?;
+
+    tracing::info!("✅ Executed arb tx: {:?}", tx_hash);
+    track_trade(
+        ctx,
+        vec![tx_hash],
+        OpenTrade {
+            pair: pair.clone(),
+            buy_factory: buy_k.dex_factory,
+            sell_factory: sell_k.dex_factory,
+            expected_profit: profit,
+        },
+    );
+
+    Ok(Some(tx_hash))
+}
+
+/// Simulate both legs against the pools' current state: quote in on the
+/// cheap venue, then the base received out on the dear one
+pub async fn quote_legs<M: Middleware + 'static>(
+    buy_k: DexPairKey,
+    sell_k: DexPairKey,
+    pair: &DexPairConfig,
+    ctx: &ExecutionContext<M>,
+) -> anyhow::Result<(SwapSimulation, SwapSimulation)> {
+    let provider = ctx.provider();
+    let buy_pair = DexPairConfig { pair: buy_k.pair_address, ..pair.clone() };
+    let sell_pair = DexPairConfig { pair: sell_k.pair_address, ..pair.clone() };
+
+    let buy = ctx
+        .registry
+        .for_factory(buy_k.dex_factory)?
+        .simulate_swap(provider.clone(), &buy_pair, pair.quote.id, pair.trade_size)
+        .await?;
+    let sell = ctx
+        .registry
+        .for_factory(sell_k.dex_factory)?
+        .simulate_swap(provider, &sell_pair, pair.base.id, buy.amount_out)
+        .await?;
+
+    Ok((buy, sell))
+}
+
+/// Cross-check each pool's on-chain state on two providers before trading
+pub async fn verify_pool_states<M: Middleware + 'static>(
+    pools: &[DexPairKey],
+    ctx: &ExecutionContext<M>,
+) -> anyhow::Result<()> {
+    for key in pools {
+        let adapter = ctx.registry.for_factory(key.dex_factory)?;
+        ctx.rpc_pool
+            .cross_checked_call(key.pair_address, adapter.state_call())
+            .await?;
     }
 
     Ok(())
@@ -245,116 +430,254 @@ pub async fn get_token_symbol<M: Middleware + 'static>(
     Ok(symbol)
 }
 
- async fn spawn_listener<M, L>(
-    dex: DexConfig,
-    pair: DexPairConfig,
+/// Seed every configured pool's price and register it with a single log
+/// multiplexer. The subscription itself is driven by the `ConnectionManager`.
+pub async fn register_all_pools<M>(
+    dex_configs: Vec<DexConfig>,
+    registry: Arc<DexRegistry<M>>,
+    rpc_pool: Arc<RpcPool>,
     provider: Arc<M>,
     tracker: Arc<PriceTracker>,
-) -> anyhow::Result<()>
+    replaying: bool,
+) -> anyhow::Result<Arc<LogMultiplexer<M>>>
 where
     M: Middleware + 'static,
-    L: DexPriceListener<M>,
-    <M as Middleware>::Provider: PubsubClient
 {
-   L::start(dex.name, provider.clone(), tracker.clone(), dex.factory, pair).await?;
+    let mut multiplexer = LogMultiplexer::new(tracker.clone(), rpc_pool);
+    if replaying {
+        multiplexer = multiplexer.replaying();
+    }
+    let multiplexer = Arc::new(multiplexer);
+    sync_pools(dex_configs, registry, &multiplexer, provider, tracker).await?;
 
-    Ok(())
+    tracing::info!("✅ Watching {} pools on a single log subscription", multiplexer.watched_count());
 
+    Ok(multiplexer)
 }
 
-pub async fn start_all_listeners<M>(
+/// Bring the multiplexer in line with `dex_configs`: watch pools it doesn't
+/// know yet (loading their initial state, unless replaying, when prices come
+/// from the recording alone) and drop pools no longer listed. Returns how
+/// many pools were added and removed.
+pub async fn sync_pools<M>(
     dex_configs: Vec<DexConfig>,
+    registry: Arc<DexRegistry<M>>,
+    multiplexer: &LogMultiplexer<M>,
     provider: Arc<M>,
     tracker: Arc<PriceTracker>,
-) -> anyhow::Result<()>
+) -> anyhow::Result<(usize, usize)>
 where
     M: Middleware + 'static,
-    <M as Middleware>::Provider: PubsubClient,
 {
-    let mut handles: Vec<JoinHandle<()>> = Vec::new();
+    let wanted: std::collections::HashSet<Address> = dex_configs
+        .iter()
+        .flat_map(|dex| dex.pairs.iter().map(|p| p.pair))
+        .collect();
+    let mut added = 0;
 
     for dex in dex_configs {
-        for pair in dex.pairs.clone() {
-            let provider = provider.clone();
-            let tracker = tracker.clone();
-            let dex_clone = dex.clone();
-
-            let handle = match dex.dex_type {
-                DexType::V2 => {
-                    tokio::spawn(async move {
-                        let _ = spawn_listener::<_, UniswapV2Listener>(
-                            dex_clone, pair, provider, tracker
-                        ).await;
-                    })
+        let adapter = registry.get(&dex.adapter)?;
+        registry.bind_venue(dex.factory, &dex.adapter, dex.router);
+
+        let contexts = futures::future::join_all(
+            dex.pairs.iter().filter(|pair| !multiplexer.is_watched(pair.pair)).map(|pair| {
+                let adapter = adapter.clone();
+                let provider = provider.clone();
+                let tracker = tracker.clone();
+                let dex_name = dex.name.clone();
+                let dex_factory = dex.factory;
+
+                async move {
+                    if !multiplexer.is_replaying()
+                        && let Err(e) = adapter
+                            .load_initial_state(provider.clone(), tracker, dex_factory, pair)
+                            .await
+                    {
+                        tracing::warn!("[{}] Failed to load initial state for {:?}: {:?}", dex_name, pair.pair, e);
+                    }
+
+                    adapter.pool_context(dex_name, dex_factory, pair.clone(), provider).await
                 }
-                DexType::V3 => {
-                    tokio::spawn(async move {
-                        let _ = spawn_listener::<_, UniswapV3Listener>(
-                            dex_clone, pair, provider, tracker
-                        ).await;
-                    })
-                }
-                DexType::QuickSwap => {
-                    tokio::spawn(async move {
-                        let _ = spawn_listener::<_, QuickSwapV3Listener>(
-                            dex_clone, pair, provider, tracker
-                        ).await;
-                    })
-                }
-            };
+            }),
+        )
+        .await;
 
-            handles.push(handle);
+        for context in contexts {
+            match context {
+                Ok(ctx) => {
+                    multiplexer.watch(ctx, adapter.clone());
+                    added += 1;
+                }
+                Err(e) => tracing::warn!("[{}] Skipping pool: {:?}", dex.name, e),
+            }
         }
     }
 
-    tracing::info!("✅ Spawned {} DEX listeners", handles.len());
-
-    // Optional: keep them alive forever
-    futures::future::join_all(handles).await;
+    let stale: Vec<Address> = multiplexer
+        .contexts()
+        .into_iter()
+        .map(|ctx| ctx.pair.pair)
+        .filter(|pool| !wanted.contains(pool))
+        .collect();
+    for pool in &stale {
+        multiplexer.unwatch(*pool);
+    }
 
-    Ok(())
+    Ok((added, stale.len()))
 }
 
 
+
+/// Sign and broadcast an already filled and simulated arb transaction
+/// under the next managed nonce, and follow it until mined
 pub async fn execute_arb_tx<M: Middleware + 'static>(
-    arb_data: Bytes, 
-    provider: Arc<M>
+    tx: TypedTransaction,
+    min_profit: U256,
+    ctx: &ExecutionContext<M>,
 ) -> anyhow::Result<TxHash>{
-    todo!()
-    
+    let provider = ctx.provider();
+    let tx_hash = ctx.nonces.send(&*provider, tx, min_profit).await?;
+    ctx.shutdown.track_tx(tx_hash, ctx.connection.clone());
+
+    Ok(tx_hash)
+}
+
+/// With FORK_SNAPSHOT_DIR set, keep the state a local simulation touched
+/// as a JSON fixture for offline replays
+fn save_fork_snapshot(state: &ForkState, pair: &DexPairConfig) {
+    let Ok(dir) = env::var("FORK_SNAPSHOT_DIR") else {
+        return;
+    };
+    let path = std::path::Path::new(&dir).join(format!(
+        "{}-{:?}-{:?}.json",
+        state.block().number,
+        pair.base.id,
+        pair.quote.id
+    ));
+    if let Err(e) = state.snapshot().save(&path) {
+        tracing::warn!("Failed to save fork snapshot {:?}: {:?}", path, e);
+    }
+}
+
+/// Minimum simulated profit for a trade: MIN_PROFIT_BPS of its size
+pub fn min_profit_for(trade_size: U256) -> U256 {
+    trade_size * U256::from(env_u64("MIN_PROFIT_BPS", DEFAULT_MIN_PROFIT_BPS)) / U256::from(10_000)
+}
+
+
+/// Connect to the healthiest WS endpoint and wrap it with the bot's signer
+pub async fn connect_ws_signer(
+    rpc_pool: &RpcPool,
+    wallet: LocalWallet,
+) -> anyhow::Result<Arc<SignerMiddleware<Provider<TimedWs>, LocalWallet>>> {
+    let provider = rpc_pool.connect_ws().await?;
+    Ok(Arc::new(SignerMiddleware::new(provider, wallet)))
+}
+
+
+/// Connect to the healthiest HTTP endpoint and wrap it with the bot's signer
+pub async fn connect_http_signer(
+    rpc_pool: &RpcPool,
+    wallet: LocalWallet,
+) -> anyhow::Result<Arc<SignerMiddleware<Provider<MeteredHttp>, LocalWallet>>> {
+    let provider = rpc_pool.connect_http().await?;
+    Ok(Arc::new(SignerMiddleware::new(provider, wallet)))
 }
 
 
 /// Load the bot's private key from Docker secret or environment variable
-pub fn load_private_key() -> String {
+pub fn load_private_key() -> anyhow::Result<String> {
+    try_load_private_key().ok_or_else(|| anyhow::anyhow!(
+        "❌ No private key found. Please set PRIVATE_KEY env var or provide /run/secrets/private_key."
+    ))
+}
+
+/// Like `load_private_key`, but `None` instead of an error when unset
+pub fn try_load_private_key() -> Option<String> {
     // Path where Docker secrets are mounted
     let secret_path = "/run/secrets/private_key";
 
     // Try to read from Docker secret file first
     if let Ok(key) = fs::read_to_string(secret_path) {
         println!("✅ Loaded PRIVATE_KEY from Docker secret.");
-        return key.trim().to_string();
+        return Some(key.trim().to_string());
     }
 
     // Fall back to environment variable
-    match env::var("PRIVATE_KEY") {
-        Ok(key) => {
-            println!("⚠️ Loaded PRIVATE_KEY from environment variable.");
-            key
+    let key = env::var("PRIVATE_KEY").ok().filter(|k| !k.trim().is_empty())?;
+    println!("⚠️ Loaded PRIVATE_KEY from environment variable.");
+    Some(key.trim().to_string())
+}
+
+/// Signer for the configured execution mode. Live trading requires the
+/// bot's key; dry runs use it when present (so simulations run from the
+/// real sender) and otherwise a throwaway key that never signs anything.
+pub fn load_wallet(mode: ExecutionMode) -> anyhow::Result<LocalWallet> {
+    match (try_load_private_key(), mode) {
+        (Some(key), _) => Ok(key
+            .parse::<LocalWallet>()
+            .map_err(|e| anyhow::anyhow!("Invalid private key: {:?}", e))?
+            .with_chain_id(CHAIN_ID)),
+        (None, ExecutionMode::DryRun) => {
+            tracing::warn!("No private key set; dry run uses a throwaway signer");
+            Ok(LocalWallet::new(&mut ethers::core::rand::thread_rng()).with_chain_id(CHAIN_ID))
         }
-        Err(_) => panic!(
+        (None, ExecutionMode::Live) => anyhow::bail!(
             "❌ No private key found. Please set PRIVATE_KEY env var or provide /run/secrets/private_key."
         ),
     }
 }
 
-pub fn load_rpc_url() -> String {
-    match env::var("RPC_URL") {
-        Ok(key) => key,
-        Err(_) => panic!(
-            "No RPC URL found. Please set RPC_URL env var."
-        )
-    }
+/// Deployed arbitrage contract: ARB_CONTRACT, else `FLASH_ARBITRAGEUR`
+pub fn arb_contract() -> Option<Address> {
+    env::var("ARB_CONTRACT")
+        .ok()
+        .filter(|a| !a.trim().is_empty())
+        .or_else(|| (!FLASH_ARBITRAGEUR.is_empty()).then(|| FLASH_ARBITRAGEUR.to_string()))
+        .and_then(|a| a.trim().parse().ok())
+}
+
+/// Bearer token for the admin API, from the `admin_token` Docker secret or
+/// the ADMIN_TOKEN env var. `None` leaves the API disabled.
+pub fn load_admin_token() -> Option<String> {
+    let token = fs::read_to_string("/run/secrets/admin_token")
+        .ok()
+        .or_else(|| env::var("ADMIN_TOKEN").ok())?;
+
+    let token = token.trim().to_string();
+    (!token.is_empty()).then_some(token)
+}
+
+pub fn load_rpc_url() -> anyhow::Result<String> {
+    env::var("RPC_URL").map_err(|_| anyhow::anyhow!("No RPC URL found. Please set RPC_URL env var."))
+}
+
+/// Comma-separated `RPC_URLS` (WS and HTTP mixed), falling back to `RPC_URL`
+pub fn load_rpc_endpoints() -> anyhow::Result<Vec<RpcEndpoint>> {
+    let urls = match env::var("RPC_URLS").ok().filter(|v| !v.trim().is_empty()) {
+        Some(urls) => urls,
+        None => load_rpc_url()?,
+    };
+
+    urls.split(',')
+        .filter(|u| !u.trim().is_empty())
+        .map(RpcEndpoint::parse)
+        .collect()
+}
+
+/// Numeric env var, or `default` when unset or unparsable
+pub fn env_u64(name: &str, default: u64) -> u64 {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// Boolean env var: "1", "true" or "yes" (any case) enable it
+pub fn env_flag(name: &str) -> bool {
+    env::var(name)
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
 }
 
 
@@ -396,8 +719,215 @@ pub fn sqrt_price_x96_to_price_f64(
     Ok(price)
 }
 
+/// Canonical quote-per-base price from V2 reserves
+pub fn reserves_to_price(
+    reserve0: U256,
+    reserve1: U256,
+    token0_is_base: bool,
+    base_decimals: usize,
+    quote_decimals: usize,
+) -> anyhow::Result<f64> {
+    let (reserve_base, reserve_quote) = if token0_is_base {
+        (reserve0, reserve1)
+    } else {
+        (reserve1, reserve0)
+    };
+
+    let base = to_f64_normalized(reserve_base, base_decimals)?;
+    let quote = to_f64_normalized(reserve_quote, quote_decimals)?;
 
+    if base <= 0.0 {
+        anyhow::bail!("Empty base reserve");
+    }
 
+    Ok(quote / base)
+}
 
+/// Constant-product output amount (UniswapV2Library::getAmountOut)
+pub fn get_amount_out_v2(
+    amount_in: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    fee_bps: u64,
+) -> anyhow::Result<U256> {
+    if reserve_in.is_zero() || reserve_out.is_zero() {
+        anyhow::bail!("Insufficient liquidity");
+    }
 
+    let amount_in_with_fee = amount_in * U256::from(10_000 - fee_bps);
+    let numerator = amount_in_with_fee * reserve_out;
+    let denominator = reserve_in * U256::from(10_000) + amount_in_with_fee;
 
+    Ok(numerator / denominator)
+}
+
+/// Output amount at the pool's current sqrtPriceX96, net of a fee in
+/// hundredths of a bip. Ignores price impact.
+pub fn spot_amount_out_sqrt_x96(
+    amount_in: U256,
+    sqrt_price_x96: U256,
+    zero_for_one: bool,
+    fee_pips: u32,
+) -> anyhow::Result<U256> {
+    if sqrt_price_x96.is_zero() {
+        anyhow::bail!("Pool not initialized");
+    }
+
+    let amount_in = U512::from(amount_in) * U512::from(1_000_000 - fee_pips) / U512::from(1_000_000);
+    let price_x192 = U512::from(sqrt_price_x96) * U512::from(sqrt_price_x96);
+    let q192 = U512::one() << 192;
+
+    // token1 per token0 = sqrtP^2 / 2^192
+    let out = if zero_for_one {
+        amount_in * price_x192 / q192
+    } else {
+        amount_in * q192 / price_x192
+    };
+
+    U256::try_from(out).map_err(|_| anyhow::anyhow!("Quote overflows U256"))
+}
+
+/// Exact-input swap through concentrated liquidity `liquidity` at
+/// `sqrt_price_x96`, net of a fee in hundredths of a bip. Assumes the swap
+/// stays inside the current tick. Returns (amount_out, sqrt_price_x96_after).
+pub fn swap_within_tick_sqrt_x96(
+    amount_in: U256,
+    sqrt_price_x96: U256,
+    liquidity: u128,
+    zero_for_one: bool,
+    fee_pips: u32,
+) -> anyhow::Result<(U256, U256)> {
+    if sqrt_price_x96.is_zero() {
+        anyhow::bail!("Pool not initialized");
+    }
+    if liquidity == 0 {
+        anyhow::bail!("No in-range liquidity");
+    }
+
+    let amount_in = U512::from(amount_in) * U512::from(1_000_000 - fee_pips) / U512::from(1_000_000);
+    let sqrt_p = U512::from(sqrt_price_x96);
+    let liquidity = U512::from(liquidity);
+    let q96 = U512::one() << 96;
+
+    let (out, sqrt_after) = if zero_for_one {
+        // token0 in: sqrtP' = L * sqrtP / (L + amount * sqrtP / Q96); out = L * (sqrtP - sqrtP') / Q96
+        let sqrt_after = liquidity * sqrt_p * q96 / (liquidity * q96 + amount_in * sqrt_p);
+        (liquidity * (sqrt_p - sqrt_after) / q96, sqrt_after)
+    } else {
+        // token1 in: sqrtP' = sqrtP + amount * Q96 / L; out = L * Q96 * (sqrtP' - sqrtP) / (sqrtP' * sqrtP)
+        let sqrt_after = sqrt_p + amount_in * q96 / liquidity;
+        (liquidity * q96 * (sqrt_after - sqrt_p) / (sqrt_after * sqrt_p), sqrt_after)
+    };
+
+    Ok((
+        U256::try_from(out).map_err(|_| anyhow::anyhow!("Swap output overflows U256"))?,
+        U256::try_from(sqrt_after).map_err(|_| anyhow::anyhow!("Price overflows U256"))?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::{ParamType, Token};
+
+    use super::*;
+    use crate::flash_loan::{FlashLoan, FlashSource};
+
+    // A 1,000 USDC trade quoted at 0.5 base out and 1,010 USDC back, with a
+    // 0.5 USDC flash fee and the default 30 bps slippage
+    const TRADE_AMT: u64 = 1_000_000_000;
+    const BASE_OUT: u64 = 500_000_000_000_000_000;
+    const QUOTE_OUT: u64 = 1_010_000_000;
+    const FLASH_FEE: u64 = 500_000;
+    const DEADLINE: u64 = 1_700_000_120;
+
+    fn opportunity(bounds: ArbBounds) -> ArbOpportunity {
+        ArbOpportunity {
+            trade_amt: U256::from(TRADE_AMT),
+            quote_asset: Address::repeat_byte(0x01),
+            base_asset: Address::repeat_byte(0x02),
+            buy_dex: Address::repeat_byte(0x03),
+            sell_dex: Address::repeat_byte(0x04),
+            bounds,
+            flash: FlashLoan {
+                source: FlashSource::AaveV3,
+                lender: Address::repeat_byte(0x05),
+                token: Address::repeat_byte(0x01),
+                amount: U256::from(TRADE_AMT),
+                fee: U256::from(FLASH_FEE),
+            },
+        }
+    }
+
+    fn decode_bounds(calldata: &Bytes) -> ArbBounds {
+        let tokens = ethers::abi::decode(
+            &[
+                ParamType::Uint(256),
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Address,
+                ParamType::Address,
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+            ],
+            calldata,
+        )
+        .unwrap();
+        let uint = |i: usize| match &tokens[i] {
+            Token::Uint(value) => *value,
+            other => panic!("expected a uint at {}, got {:?}", i, other),
+        };
+        ArbBounds {
+            min_base_out: uint(5),
+            min_quote_out: uint(6),
+            min_profit: uint(7),
+            deadline: uint(8),
+        }
+    }
+
+    #[test]
+    fn encoded_bounds_match_simulated_quotes_less_slippage() {
+        let bounds = ArbBounds::from_quotes(
+            U256::from(TRADE_AMT),
+            U256::from(BASE_OUT),
+            U256::from(QUOTE_OUT),
+            U256::from(FLASH_FEE),
+            SLIPPAGE_BPS,
+            U256::from(1_000_000u64),
+            U256::from(DEADLINE),
+        );
+        let decoded = decode_bounds(&create_arb_calldata(&opportunity(bounds)).unwrap());
+
+        assert_eq!(decoded, bounds);
+        // 30 bps under each simulated leg
+        assert_eq!(decoded.min_base_out, U256::from(498_500_000_000_000_000u64));
+        assert_eq!(decoded.min_quote_out, U256::from(1_006_970_000u64));
+        // What the sell floor leaves after repaying the loan and its fee
+        assert_eq!(decoded.min_profit, U256::from(6_470_000u64));
+        assert_eq!(decoded.deadline, U256::from(DEADLINE));
+    }
+
+    #[test]
+    fn encoded_profit_never_drops_below_the_floor() {
+        let floor = U256::from(10_000_000u64);
+        let bounds = ArbBounds::from_quotes(
+            U256::from(TRADE_AMT),
+            U256::from(BASE_OUT),
+            U256::from(QUOTE_OUT),
+            U256::from(FLASH_FEE),
+            SLIPPAGE_BPS,
+            floor,
+            U256::from(DEADLINE),
+        );
+        let decoded = decode_bounds(&create_arb_calldata(&opportunity(bounds)).unwrap());
+
+        assert_eq!(decoded.min_profit, floor);
+        assert_eq!(decoded.min_quote_out, U256::from(1_006_970_000u64));
+    }
+}
@@ -0,0 +1,609 @@
+use std::{sync::Arc, time::Duration};
+
+use dashmap::DashMap;
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockNumber, H256 as TxHash, U256, transaction::eip2718::TypedTransaction},
+};
+
+use crate::{
+    bindings::erc20::IERC20,
+    config::ExecutionMode,
+    constants::{
+        DEFAULT_INVENTORY_REFRESH_SECS, DEFAULT_REBALANCE_DRIFT_BPS, DEFAULT_SWAP_GAS_LIMIT,
+        SLIPPAGE_BPS, SWAP_DEADLINE_SECS,
+    },
+    execution_context::ExecutionContext,
+    fork_sim::{SimTx, simulate_bundle},
+    helpers::{env_u64, min_profit_for, to_f64_normalized},
+    metrics::METRICS,
+    paper_trading::inventory_paper_trade,
+    pnl::{OpenTrade, track_trade},
+    simulation::simulate_tx,
+    types::{
+        ArbBounds, BlockInfo, DexPairConfig, DexPairKey, PriceInfo, PriceTracker, SwapParams, SwapSimulation,
+        Token,
+    },
+};
+
+/// A token the wallet holds and the share of total value it should be
+#[derive(Debug, Clone)]
+pub struct InventoryTarget {
+    pub token: Token,
+    pub weight: f64,
+}
+
+/// One swap bringing inventory back toward its targets
+#[derive(Debug, Clone)]
+pub struct Rebalance {
+    pub from: Token,
+    pub to: Token,
+    pub amount_in: U256,
+}
+
+/// The wallet's own balances, for trading without flash loans.
+///
+/// Balances are read from chain periodically and debited locally as trades
+/// go out, so concurrent workers can't spend the same tokens twice. The
+/// first target is the numeraire values are measured in and rebalances
+/// route through.
+pub struct Inventory {
+    owner: Address,
+    targets: Vec<InventoryTarget>,
+    balances: DashMap<Address, U256>,
+    drift_bps: u64,
+}
+
+impl Inventory {
+    pub fn new(owner: Address, targets: Vec<InventoryTarget>, drift_bps: u64) -> Self {
+        Self {
+            owner,
+            targets,
+            balances: DashMap::new(),
+            drift_bps,
+        }
+    }
+
+    /// `INVENTORY_TARGETS` as `token=weight` pairs, comma-separated, with the
+    /// numeraire first; weights are normalized. Decimals are read on chain.
+    pub async fn from_env<M: Middleware + 'static>(provider: Arc<M>, owner: Address) -> anyhow::Result<Self> {
+        let raw = std::env::var("INVENTORY_TARGETS")
+            .map_err(|_| anyhow::anyhow!("FUNDING_MODE=inventory needs INVENTORY_TARGETS"))?;
+
+        let mut targets = Vec::new();
+        for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (token, weight) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid INVENTORY_TARGETS entry {:?} (expected token=weight)", entry))?;
+            let id: Address = token.trim().parse()?;
+            let weight: f64 = weight.trim().parse()?;
+            if !weight.is_finite() || weight < 0.0 {
+                anyhow::bail!("Invalid inventory weight {} for {:?}", weight, id);
+            }
+            let decimals = IERC20::new(id, provider.clone()).decimals().call().await?;
+            targets.push(InventoryTarget { token: Token { id, decimals: decimals as usize }, weight });
+        }
+
+        let total: f64 = targets.iter().map(|t| t.weight).sum();
+        if targets.is_empty() || total <= 0.0 {
+            anyhow::bail!("INVENTORY_TARGETS has no positive weights");
+        }
+        for target in &mut targets {
+            target.weight /= total;
+        }
+
+        Ok(Self::new(owner, targets, env_u64("REBALANCE_DRIFT_BPS", DEFAULT_REBALANCE_DRIFT_BPS)))
+    }
+
+    pub fn targets(&self) -> &[InventoryTarget] {
+        &self.targets
+    }
+
+    /// Locally known balance, net of trades sent since the last refresh
+    pub fn balance(&self, token: Address) -> U256 {
+        self.balances.get(&token).map_or(U256::zero(), |b| *b)
+    }
+
+    /// Re-read every target balance from chain
+    pub async fn refresh<M: Middleware + 'static>(&self, provider: Arc<M>) -> anyhow::Result<()> {
+        for target in &self.targets {
+            let balance = IERC20::new(target.token.id, provider.clone())
+                .balance_of(self.owner)
+                .call()
+                .await?;
+            self.balances.insert(target.token.id, balance);
+            METRICS
+                .inventory_balance
+                .with_label_values(&[&format!("{:?}", target.token.id)])
+                .set(to_f64_normalized(balance, target.token.decimals).unwrap_or_default());
+        }
+        Ok(())
+    }
+
+    /// Set aside `amount` of `token` for a trade, or refuse if the wallet
+    /// doesn't have it
+    pub fn debit(&self, token: Address, amount: U256) -> anyhow::Result<()> {
+        let mut balance = self
+            .balances
+            .get_mut(&token)
+            .ok_or_else(|| anyhow::anyhow!("No inventory held in {:?}", token))?;
+        if *balance < amount {
+            anyhow::bail!("Trade needs {} of {:?}, inventory has {}", amount, token, *balance);
+        }
+        *balance -= amount;
+        Ok(())
+    }
+
+    /// Undo a debit for a trade that never went out
+    pub fn credit(&self, token: Address, amount: U256) {
+        *self.balances.entry(token).or_insert_with(U256::zero) += amount;
+    }
+
+    /// Value of one whole unit of `token` in the numeraire, through the
+    /// average tracked price of their pools
+    async fn unit_value(&self, tracker: &PriceTracker, token: Address) -> Option<f64> {
+        let numeraire = self.targets.first()?.token.id;
+        if token == numeraire {
+            return Some(1.0);
+        }
+
+        let average = |prices: Vec<(DexPairKey, PriceInfo)>| {
+            (!prices.is_empty()).then(|| prices.iter().map(|(_, p)| p.price).sum::<f64>() / prices.len() as f64)
+        };
+        // Prices are quote per base
+        if let Some(price) = average(tracker.get_all_for_pair(token, numeraire).await.ok()?) {
+            return Some(price);
+        }
+        average(tracker.get_all_for_pair(numeraire, token).await.ok()?)
+            .filter(|p| *p > 0.0)
+            .map(|p| 1.0 / p)
+    }
+
+    /// Swaps restoring target weights once any token drifts more than
+    /// `REBALANCE_DRIFT_BPS` of total value: overweight tokens are sold to
+    /// the numeraire first, then the numeraire buys what's underweight
+    pub async fn rebalance_plan(&self, tracker: &PriceTracker) -> anyhow::Result<Vec<Rebalance>> {
+        let Some(numeraire) = self.targets.first().map(|t| t.token.clone()) else {
+            return Ok(Vec::new());
+        };
+
+        let mut values = Vec::with_capacity(self.targets.len());
+        for target in &self.targets {
+            let unit = self
+                .unit_value(tracker, target.token.id)
+                .await
+                .ok_or_else(|| anyhow::anyhow!("No tracked price for {:?} in the numeraire", target.token.id))?;
+            let held = to_f64_normalized(self.balance(target.token.id), target.token.decimals)?;
+            values.push((target, unit, held * unit));
+        }
+
+        let total: f64 = values.iter().map(|(_, _, v)| v).sum();
+        if total <= 0.0 {
+            return Ok(Vec::new());
+        }
+        let drifted = values
+            .iter()
+            .any(|(t, _, v)| (v - t.weight * total).abs() / total * 10_000.0 > self.drift_bps as f64);
+        if !drifted {
+            return Ok(Vec::new());
+        }
+
+        let to_units = |amount: f64, token: &Token| -> anyhow::Result<U256> {
+            Ok(ethers::utils::parse_units(format!("{:.*}", token.decimals, amount), token.decimals)?.into())
+        };
+
+        let mut sells = Vec::new();
+        let mut buys = Vec::new();
+        for (target, unit, value) in values.iter().skip(1) {
+            let excess = value - target.weight * total;
+            if excess > 0.0 {
+                sells.push(Rebalance {
+                    from: target.token.clone(),
+                    to: numeraire.clone(),
+                    amount_in: to_units(excess / unit, &target.token)?,
+                });
+            } else if excess < 0.0 {
+                buys.push(Rebalance {
+                    from: numeraire.clone(),
+                    to: target.token.clone(),
+                    amount_in: to_units(-excess, &numeraire)?,
+                });
+            }
+        }
+
+        sells.extend(buys);
+        sells.retain(|r| !r.amount_in.is_zero());
+        Ok(sells)
+    }
+
+    /// Refresh balances and rebalance every `INVENTORY_REFRESH_SECS` until
+    /// shutdown. Both wait while our transactions are pending.
+    pub async fn run<M: Middleware + 'static>(self: Arc<Self>, ctx: Arc<ExecutionContext<M>>) -> anyhow::Result<()> {
+        let token = ctx.shutdown.token();
+        let mut ticker = tokio::time::interval(Duration::from_secs(
+            env_u64("INVENTORY_REFRESH_SECS", DEFAULT_INVENTORY_REFRESH_SECS),
+        ));
+
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => return Ok(()),
+                _ = ticker.tick() => {}
+            }
+
+            // Chain balances lag our pending trades; keep the local debits until they land
+            if !ctx.nonces.pending().is_empty() {
+                continue;
+            }
+            if let Err(e) = self.refresh(ctx.provider()).await {
+                tracing::warn!("Inventory refresh failed: {:?}", e);
+                continue;
+            }
+            if ctx.mode != ExecutionMode::Live || ctx.controls.is_paused() {
+                continue;
+            }
+
+            let plan = match self.rebalance_plan(&ctx.tracker).await {
+                Ok(plan) => plan,
+                Err(e) => {
+                    tracing::warn!("Can't plan inventory rebalance: {:?}", e);
+                    continue;
+                }
+            };
+            if plan.is_empty() {
+                continue;
+            }
+
+            let block = latest_block(&*ctx.provider()).await?;
+            for step in plan {
+                match self.rebalance(&step, &block, &ctx).await {
+                    Ok(hash) => tracing::info!(
+                        "⚖️ Rebalancing {} of {:?} into {:?}: {:?}",
+                        step.amount_in, step.from.id, step.to.id, hash
+                    ),
+                    Err(e) => tracing::warn!("Rebalance {:?} -> {:?} failed: {:?}", step.from.id, step.to.id, e),
+                }
+            }
+        }
+    }
+
+    async fn rebalance<M: Middleware + 'static>(
+        &self,
+        step: &Rebalance,
+        block: &BlockInfo,
+        ctx: &ExecutionContext<M>,
+    ) -> anyhow::Result<TxHash> {
+        let (key, pair) = tracked_pool(ctx, &step.from, &step.to, step.amount_in)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("No tracked pool between {:?} and {:?}", step.from.id, step.to.id))?;
+        let quote = ctx
+            .registry
+            .for_factory(key.dex_factory)?
+            .simulate_swap(ctx.provider(), &pair, step.from.id, step.amount_in)
+            .await?;
+
+        self.debit(step.from.id, step.amount_in)?;
+        let tx = router_swap_tx(ctx, key, &pair, step.from.id, step.amount_in, slippage_floor(quote.amount_out), block)
+            .await;
+        let sent = match tx {
+            Ok(tx) => ctx.nonces.send(&*ctx.provider(), tx, U256::zero()).await,
+            Err(e) => Err(e),
+        };
+        match sent {
+            Ok(hash) => {
+                ctx.shutdown.track_tx(hash, ctx.connection.clone());
+                Ok(hash)
+            }
+            Err(e) => {
+                self.credit(step.from.id, step.amount_in);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// `amount` less `SLIPPAGE_BPS`
+fn slippage_floor(amount: U256) -> U256 {
+    let slippage = env_u64("SLIPPAGE_BPS", SLIPPAGE_BPS).min(10_000);
+    amount * U256::from(10_000 - slippage) / U256::from(10_000)
+}
+
+async fn latest_block<M: Middleware + 'static>(provider: &M) -> anyhow::Result<BlockInfo> {
+    let block = provider
+        .get_block(BlockNumber::Latest)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to fetch latest block: {:?}", e))?
+        .ok_or_else(|| anyhow::anyhow!("Node returned no latest block"))?;
+    BlockInfo::try_from(&block)
+}
+
+/// A priced, unpaused pool between two tokens, as a pair config oriented
+/// the way the tracker prices it
+async fn tracked_pool<M: Middleware + 'static>(
+    ctx: &ExecutionContext<M>,
+    a: &Token,
+    b: &Token,
+    trade_size: U256,
+) -> Option<(DexPairKey, DexPairConfig)> {
+    ctx.tracker
+        .snapshot()
+        .await
+        .into_iter()
+        .filter(|(key, _)| !ctx.controls.is_dex_paused(key.dex_factory))
+        .find_map(|(key, info)| {
+            let (base, quote) = if (info.base, info.quote) == (a.id, b.id) {
+                (a.clone(), b.clone())
+            } else if (info.base, info.quote) == (b.id, a.id) {
+                (b.clone(), a.clone())
+            } else {
+                return None;
+            };
+            Some((key, DexPairConfig { pair: key.pair_address, base, quote, trade_size }))
+        })
+}
+
+/// Exact-input swap through the router of `key`'s venue, from and to the
+/// wallet, priced by the gas strategy
+async fn router_swap_tx<M: Middleware + 'static>(
+    ctx: &ExecutionContext<M>,
+    key: DexPairKey,
+    pair: &DexPairConfig,
+    token_in: Address,
+    amount_in: U256,
+    min_amount_out: U256,
+    block: &BlockInfo,
+) -> anyhow::Result<TypedTransaction> {
+    let provider = ctx.provider();
+    let owner = provider
+        .default_sender()
+        .ok_or_else(|| anyhow::anyhow!("Provider has no signer to trade from"))?;
+    let router = ctx.registry.router(key.dex_factory)?;
+    let token_out = if token_in == pair.base.id { pair.quote.id } else { pair.base.id };
+
+    // The router can't be estimated, let alone simulated, without the allowance
+    if let Some(approval) = ctx.approvals.ensure(ctx, token_in, router, amount_in).await? {
+        anyhow::bail!("Waiting on approval {:?} of {:?} to router {:?}", approval, token_in, router);
+    }
+
+    let data = ctx.registry.for_factory(key.dex_factory)?.encode_swap(
+        pair,
+        &SwapParams {
+            token_in,
+            token_out,
+            amount_in,
+            min_amount_out,
+            recipient: owner,
+            deadline: U256::from(block.timestamp + SWAP_DEADLINE_SECS),
+        },
+    )?;
+
+    // A fixed limit: the second leg can't be estimated before the first lands
+    let gas_limit = U256::from(env_u64("SWAP_GAS_LIMIT", DEFAULT_SWAP_GAS_LIMIT));
+    let mut tx = ctx.gas.new_tx();
+    tx.set_from(owner);
+    tx.set_to(router);
+    tx.set_data(data);
+    tx.set_gas(gas_limit);
+    ctx.gas.apply(&mut tx, ctx.gas.quote(&*provider, block, gas_limit, None).await?);
+    provider
+        .fill_transaction(&mut tx, None)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to fill swap tx: {:?}", e))?;
+
+    Ok(tx)
+}
+
+/// Run an opportunity from the wallet's balances: buy base with quote on
+/// the cheap venue, then sell that base on the dear one, as two router
+/// swaps. The first leg must return the buy bound; the second must bring
+/// back the quote spent plus the minimum profit, or revert and leave the
+/// base in inventory for the rebalancer.
+pub async fn execute_inventory_arb<M: Middleware + 'static>(
+    buy_k: DexPairKey,
+    sell_k: DexPairKey,
+    pair: &DexPairConfig,
+    (buy, sell): (SwapSimulation, SwapSimulation),
+    block: &BlockInfo,
+    ctx: &ExecutionContext<M>,
+) -> anyhow::Result<Option<TxHash>> {
+    let inventory = ctx
+        .inventory
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("Inventory mode without an inventory"))?;
+
+    let bounds = ArbBounds::from_quotes(
+        pair.trade_size,
+        buy.amount_out,
+        sell.amount_out,
+        U256::zero(),
+        env_u64("SLIPPAGE_BPS", SLIPPAGE_BPS),
+        min_profit_for(pair.trade_size),
+        U256::from(block.timestamp + SWAP_DEADLINE_SECS),
+    );
+    if sell.amount_out <= pair.trade_size + bounds.min_profit {
+        tracing::info!("Quoted legs return {} for {}; skipping", sell.amount_out, pair.trade_size);
+        return Ok(None);
+    }
+
+    if ctx.mode == ExecutionMode::DryRun {
+        let trade = inventory_paper_trade(buy_k, sell_k, pair, &bounds, (buy, sell))?;
+        tracing::info!(
+            "📝 Paper inventory trade: {:.6} profit in {:?} ({} held)",
+            trade.profit,
+            trade.quote,
+            inventory.balance(pair.quote.id),
+        );
+        ctx.paper.record(trade);
+        return Ok(None);
+    }
+    if ctx.shutdown.is_shutting_down() {
+        tracing::info!("Shutdown in progress; skipping arb execution");
+        return Ok(None);
+    }
+
+    inventory.debit(pair.quote.id, pair.trade_size)?;
+    let expected_profit = sell.amount_out - pair.trade_size;
+    let result = send_inventory_legs(buy_k, sell_k, pair, &bounds, expected_profit, block, ctx).await;
+    if result.is_err() {
+        inventory.credit(pair.quote.id, pair.trade_size);
+    }
+    result.map(Some)
+}
+
+async fn send_inventory_legs<M: Middleware + 'static>(
+    buy_k: DexPairKey,
+    sell_k: DexPairKey,
+    pair: &DexPairConfig,
+    bounds: &ArbBounds,
+    expected_profit: U256,
+    block: &BlockInfo,
+    ctx: &ExecutionContext<M>,
+) -> anyhow::Result<TxHash> {
+    let provider = ctx.provider();
+    let buy_pair = DexPairConfig { pair: buy_k.pair_address, ..pair.clone() };
+    let sell_pair = DexPairConfig { pair: sell_k.pair_address, ..pair.clone() };
+
+    let buy_tx = router_swap_tx(ctx, buy_k, &buy_pair, pair.quote.id, pair.trade_size, bounds.min_base_out, block).await?;
+    let sell_tx = router_swap_tx(
+        ctx,
+        sell_k,
+        &sell_pair,
+        pair.base.id,
+        bounds.min_base_out,
+        pair.trade_size + bounds.min_profit,
+        block,
+    )
+    .await?;
+
+    // Both legs must pass before either is sent: the second is a separate
+    // transaction, so a failure after the first lands can't undo it. The
+    // local fork runs the sell on top of the buy; over RPC each leg is
+    // called on its own, which holds since they trade different pools, but
+    // the sell then spends base the wallet must already hold.
+    match &ctx.fork {
+        Some(fork) => {
+            let state = fork.at(block);
+            let legs = [SimTx::from_typed(&buy_tx)?, SimTx::from_typed(&sell_tx)?];
+            for outcome in simulate_bundle(&state, &legs, false)? {
+                if let Some(reason) = outcome.revert {
+                    METRICS.simulation_rejections.with_label_values(&[&reason.label()]).inc();
+                    anyhow::bail!("Inventory leg reverts in simulation: {}", reason);
+                }
+            }
+        }
+        None => {
+            let inventory = ctx
+                .inventory
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Inventory mode without an inventory"))?;
+            let held = inventory.balance(pair.base.id);
+            if held < bounds.min_base_out {
+                anyhow::bail!(
+                    "Sell leg can't be simulated over RPC with {} base held (needs {}); enable the local fork",
+                    held, bounds.min_base_out
+                );
+            }
+            simulate_tx(&*provider, &buy_tx, U256::zero(), None).await?;
+            simulate_tx(&*provider, &sell_tx, U256::zero(), None).await?;
+        }
+    }
+
+    METRICS.opportunity("attempted");
+    let buy_hash = ctx.nonces.send(&*provider, buy_tx, U256::zero()).await?;
+    ctx.shutdown.track_tx(buy_hash, ctx.connection.clone());
+    let mut hashes = vec![buy_hash];
+    match ctx.nonces.send(&*provider, sell_tx, U256::zero()).await {
+        Ok(sell_hash) => {
+            ctx.shutdown.track_tx(sell_hash, ctx.connection.clone());
+            tracing::info!("✅ Inventory arb sent: buy {:?}, sell {:?}", buy_hash, sell_hash);
+            hashes.push(sell_hash);
+        }
+        // The quote is spent either way; the base waits for the rebalancer
+        Err(e) => tracing::warn!("Sell leg failed after buy {:?}; base stays in inventory: {:?}", buy_hash, e),
+    }
+    track_trade(
+        ctx,
+        hashes,
+        OpenTrade {
+            pair: pair.clone(),
+            buy_factory: buy_k.dex_factory,
+            sell_factory: sell_k.dex_factory,
+            expected_profit,
+        },
+    );
+    Ok(buy_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(byte: u8, decimals: usize) -> Token {
+        Token { id: Address::repeat_byte(byte), decimals }
+    }
+
+    fn units(amount: &str, decimals: usize) -> U256 {
+        ethers::utils::parse_units(amount, decimals).unwrap().into()
+    }
+
+    /// USDC numeraire and WETH, half each, with WETH tracked at 2000 USDC
+    async fn usdc_weth() -> (Inventory, PriceTracker, Token, Token) {
+        let (usdc, weth) = (token(0xc1, 6), token(0xe1, 18));
+        let inventory = Inventory::new(
+            Address::repeat_byte(0xaa),
+            vec![
+                InventoryTarget { token: usdc.clone(), weight: 0.5 },
+                InventoryTarget { token: weth.clone(), weight: 0.5 },
+            ],
+            500,
+        );
+        let tracker = PriceTracker::new();
+        tracker
+            .update(Address::repeat_byte(0xf0), Address::repeat_byte(0x01), weth.id, usdc.id, 2_000.0)
+            .await
+            .unwrap();
+        (inventory, tracker, usdc, weth)
+    }
+
+    #[test]
+    fn debit_refuses_more_than_the_balance() {
+        let inventory = Inventory::new(Address::repeat_byte(0xaa), Vec::new(), 500);
+        let token = Address::repeat_byte(0xc1);
+        assert!(inventory.debit(token, U256::one()).is_err());
+
+        inventory.credit(token, U256::from(100u64));
+        assert!(inventory.debit(token, U256::from(150u64)).is_err());
+        assert_eq!(inventory.balance(token), U256::from(100u64));
+
+        inventory.debit(token, U256::from(60u64)).unwrap();
+        assert_eq!(inventory.balance(token), U256::from(40u64));
+    }
+
+    #[tokio::test]
+    async fn rebalance_plan_ignores_drift_within_the_band() {
+        let (inventory, tracker, usdc, weth) = usdc_weth().await;
+        inventory.credit(usdc.id, units("1000", 6));
+        // 1020 against 1000: under 1% off target
+        inventory.credit(weth.id, units("0.51", 18));
+
+        assert!(inventory.rebalance_plan(&tracker).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rebalance_plan_sells_overweight_and_buys_underweight_through_the_numeraire() {
+        let (inventory, tracker, usdc, weth) = usdc_weth().await;
+        inventory.credit(usdc.id, units("1000", 6));
+        inventory.credit(weth.id, units("1", 18));
+
+        // 2000 of WETH against 1000 USDC: sell 500 worth back
+        let plan = inventory.rebalance_plan(&tracker).await.unwrap();
+        assert_eq!(plan.len(), 1);
+        assert_eq!((plan[0].from.id, plan[0].to.id), (weth.id, usdc.id));
+        assert_eq!(plan[0].amount_in, units("0.25", 18));
+
+        inventory.debit(weth.id, units("1", 18)).unwrap();
+        let plan = inventory.rebalance_plan(&tracker).await.unwrap();
+        assert_eq!(plan.len(), 1);
+        assert_eq!((plan[0].from.id, plan[0].to.id), (usdc.id, weth.id));
+        assert_eq!(plan[0].amount_in, units("500", 6));
+    }
+}
@@ -0,0 +1,32 @@
+pub mod admin_api;
+pub mod alert;
+pub mod allowance;
+pub mod config;
+pub mod constants;
+pub mod controls;
+pub mod types;
+pub mod block_watcher;
+pub mod block_poller;
+pub mod connection_manager;
+pub mod execution_context;
+pub mod flash_loan;
+pub mod fork_sim;
+pub mod gas_strategy;
+pub mod inventory;
+pub mod nonce_manager;
+pub mod rpc_pool;
+pub mod shutdown;
+pub mod store;
+pub mod supervisor;
+pub mod provider_factory;
+pub mod bindings;
+pub mod arb_worker;
+pub mod helpers;
+pub mod metrics;
+pub mod paper_trading;
+pub mod pnl;
+pub mod recorder;
+pub mod simulation;
+pub mod dex_adapter;
+pub mod dex_price_listener;
+pub mod dex_pool_resolver;
@@ -1,24 +1,50 @@
-
-mod config;
-mod constants;
-mod types;
-mod block_watcher;
-mod bindings;
-mod arb_worker;
-mod helpers;
-mod dex_price_listener;
-mod dex_pool_resolver;
-
-use crate::{
+use defi_arbitrageur::{
+    admin_api::{self, AdminState},
+    alert::{Alerter, LogAlerter, WebhookAlerter},
+    allowance::AllowanceManager,
     arb_worker::ArbWorker, 
+    block_poller::BlockPoller,
     block_watcher::BlockWatcher, 
-    constants::PROVIDER,
-    types::PriceTracker
+    config::{self, ExecutionMode, FundingMode, IngestionMode},
+    connection_manager::{Backoff, ConnectionManager, Connector},
+    controls::BotControls,
+    constants::{
+        DEFAULT_ADMIN_ADDR,
+        DEFAULT_METRICS_ADDR,
+        DEFAULT_POLL_INTERVAL_MS,
+        DEFAULT_RPC_CONNECT_TIMEOUT_MS, 
+        DEFAULT_RPC_REQUEST_TIMEOUT_MS, 
+        DEFAULT_SHUTDOWN_TIMEOUT_SECS,
+        DEFAULT_SUPERVISOR_MAX_RESTARTS,
+    },
+    dex_adapter::DexRegistry,
+    dex_price_listener::multiplexer::LogMultiplexer,
+    execution_context::ExecutionContext,
+    gas_strategy::GasStrategy,
+    inventory::Inventory,
+    pnl::PnlLedger,
+    recorder::{self, EventRecorder, RECORDER},
+    helpers,
+    metrics,
+    provider_factory::{MeteredHttp, ProviderConfig, ProviderFactory, TimedWs},
+    rpc_pool::RpcPool,
+    shutdown::Shutdown,
+    store::Store,
+    supervisor::Supervisor,
+    types::{BlockEvent, DexPairConfig, PriceTracker}
 };
 
+use ethers::{
+    middleware::SignerMiddleware,
+    providers::{Middleware, Provider},
+    signers::LocalWallet,
+    types::Address,
+};
+use dashmap::DashMap;
+use futures::{FutureExt, future::BoxFuture};
 use tokio::sync::broadcast;
-use std::sync::Arc;
-use ethers::types::H256;
+use tokio_util::sync::CancellationToken;
+use std::{collections::HashSet, sync::Arc, time::Duration};
 
 use dotenv::dotenv;
 
@@ -27,53 +53,374 @@ async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
     dotenv().ok();
 
-    // Shared state
-    let provider = PROVIDER.clone();
-    let tracker = Arc::new(PriceTracker::new());
+    let factory = ProviderFactory::new(ProviderConfig {
+        connect_timeout: Duration::from_millis(
+            helpers::env_u64("RPC_CONNECT_TIMEOUT_MS", DEFAULT_RPC_CONNECT_TIMEOUT_MS)
+        ),
+        request_timeout: Duration::from_millis(
+            helpers::env_u64("RPC_REQUEST_TIMEOUT_MS", DEFAULT_RPC_REQUEST_TIMEOUT_MS)
+        ),
+    });
+    let rpc_pool = Arc::new(RpcPool::new(
+        factory,
+        helpers::load_rpc_endpoints()?,
+        helpers::env_flag("RPC_RACE_READS"),
+        helpers::env_flag("RPC_QUORUM_READS"),
+    )?);
 
-    // 1 Create broadcast channel for block hashes
-    let (tx, _rx) = broadcast::channel::<H256>(16);
+    let mode = ExecutionMode::from_env()?;
+    let wallet = helpers::load_wallet(mode)?;
+    if mode == ExecutionMode::DryRun {
+        tracing::warn!("📝 Dry-run mode: opportunities are simulated, never signed or sent");
+    }
+
+    let ingestion_mode = IngestionMode::from_env()?;
+    // A replay is never re-recorded
+    if ingestion_mode != IngestionMode::Replay {
+        EventRecorder::init_from_env()?;
+    }
 
-    // 2 Start BlockWatcher
-    let block_watcher = BlockWatcher::new(provider.clone(), tx.clone());
-    tokio::spawn(async move {
-        if let Err(e) = block_watcher.start().await {
-            tracing::error!("Block watcher failed: {:?}", e);
+    match ingestion_mode {
+        IngestionMode::Ws => {
+            let pool = rpc_pool.clone();
+            let connector: Connector<SignerMiddleware<Provider<TimedWs>, LocalWallet>> = Arc::new(move || {
+                let pool = pool.clone();
+                let wallet = wallet.clone();
+                async move { helpers::connect_ws_signer(&pool, wallet).await }.boxed()
+            });
+
+            run_bot(rpc_pool, connector, mode, false, |connection, block_watcher, multiplexer| {
+                async move { connection.run(block_watcher, multiplexer).await }.boxed()
+            })
+            .await
         }
-    });
+        IngestionMode::Http => {
+            let pool = rpc_pool.clone();
+            let connector: Connector<SignerMiddleware<Provider<MeteredHttp>, LocalWallet>> = Arc::new(move || {
+                let pool = pool.clone();
+                let wallet = wallet.clone();
+                async move { helpers::connect_http_signer(&pool, wallet).await }.boxed()
+            });
+            let poller = BlockPoller::new(Duration::from_millis(
+                helpers::env_u64("POLL_INTERVAL_MS", DEFAULT_POLL_INTERVAL_MS)
+            ));
+
+            run_bot(rpc_pool, connector, mode, false, move |connection, block_watcher, multiplexer| {
+                let poller = poller.clone();
+                async move { connection.run_polling(poller, block_watcher, multiplexer).await }.boxed()
+            })
+            .await
+        }
+        IngestionMode::Replay => {
+            if mode == ExecutionMode::Live {
+                anyhow::bail!("INGESTION_MODE=replay needs EXECUTION_MODE=dry-run");
+            }
+            let dir = std::env::var("REPLAY_DIR")
+                .ok()
+                .filter(|d| !d.trim().is_empty())
+                .ok_or_else(|| anyhow::anyhow!("INGESTION_MODE=replay needs REPLAY_DIR"))?;
+            let dir = std::path::PathBuf::from(dir.trim());
+            let paced = helpers::env_flag("REPLAY_PACED");
+
+            let pool = rpc_pool.clone();
+            let connector: Connector<SignerMiddleware<Provider<MeteredHttp>, LocalWallet>> = Arc::new(move || {
+                let pool = pool.clone();
+                let wallet = wallet.clone();
+                async move { helpers::connect_http_signer(&pool, wallet).await }.boxed()
+            });
 
-    // 3 Load target configs
-    let dex_configs = config::build_target_configs(provider.clone()).await?;
+            run_bot(rpc_pool, connector, mode, true, move |connection, block_watcher, multiplexer| {
+                let dir = dir.clone();
+                async move {
+                    recorder::replay(&dir, connection.provider(), &block_watcher, &multiplexer, paced).await?;
+                    // Returning would have the supervisor replay it all again
+                    std::future::pending().await
+                }
+                .boxed()
+            })
+            .await
+        }
+    }
+}
+
+/// Wire up shared state, pools and workers for either ingestion mode.
+/// `ingestion` runs whatever keeps blocks and pool logs flowing; it is
+/// supervised and restarted like every other long-running task. When
+/// `replaying`, pool prices come only from the recording being fed in.
+async fn run_bot<M, F>(
+    rpc_pool: Arc<RpcPool>,
+    connector: Connector<M>,
+    mode: ExecutionMode,
+    replaying: bool,
+    ingestion: F,
+) -> anyhow::Result<()>
+where
+    M: Middleware + 'static,
+    F: Fn(Arc<ConnectionManager<M>>, Arc<BlockWatcher>, Arc<LogMultiplexer<M>>) -> BoxFuture<'static, anyhow::Result<()>>
+        + Send
+        + Sync
+        + 'static,
+{
+    let alerter: Arc<dyn Alerter> = match std::env::var("ALERT_WEBHOOK_URL") {
+        Ok(url) if !url.trim().is_empty() => Arc::new(WebhookAlerter::new(url)),
+        _ => Arc::new(LogAlerter),
+    };
+    let shutdown = Arc::new(Shutdown::new());
+    if let Some(store) = Store::init_from_env()? {
+        shutdown.on_shutdown("store", move || store.flush().boxed());
+    }
+    if let Some(recorder) = RECORDER.get() {
+        shutdown.on_shutdown("recorder", move || async move { recorder.flush() }.boxed());
+    }
+    let supervisor = Arc::new(Supervisor::new(
+        Backoff::default(),
+        helpers::env_u64("SUPERVISOR_MAX_RESTARTS", DEFAULT_SUPERVISOR_MAX_RESTARTS) as u32,
+        alerter,
+        shutdown.token(),
+    ));
+
+    // Shared state
+    let connection = Arc::new(ConnectionManager::connect(connector, Backoff::default()).await);
+    let provider = connection.provider();
+    let tracker = Arc::new(PriceTracker::new());
+    let registry = Arc::new(DexRegistry::with_defaults());
+    let controls = Arc::new(BotControls::new());
+    let mut ctx = ExecutionContext::new(
+        connection.clone(),
+        registry.clone(),
+        tracker.clone(),
+        rpc_pool.clone(),
+        shutdown.clone(),
+        controls.clone(),
+        mode,
+    )
+    .with_gas_strategy(GasStrategy::from_env()?)
+    .with_pnl_ledger(PnlLedger::from_env()?);
+    if FundingMode::from_env()? == FundingMode::Inventory {
+        let owner = provider
+            .default_sender()
+            .ok_or_else(|| anyhow::anyhow!("Inventory mode needs a wallet"))?;
+        let inventory = Arc::new(Inventory::from_env(provider.clone(), owner).await?);
+        inventory.refresh(provider.clone()).await?;
+        tracing::info!("🏦 Trading from wallet inventory across {} tokens", inventory.targets().len());
+        ctx = ctx.with_inventory(inventory);
+    }
+    let approvals = AllowanceManager::from_env(ctx.inventory.as_deref())?;
+    tracing::info!("🔓 Managing {} token approvals ({:?})", approvals.spenders().len(), approvals.mode());
+    ctx = ctx.with_approvals(approvals);
+    if helpers::env_flag("FORK_SIM") {
+        tracing::info!("🧪 Simulating trades in a local EVM fork");
+        ctx = ctx.with_fork_simulation();
+    }
+    let ctx = Arc::new(ctx);
+    ctx.approvals.sync(&ctx).await?;
+
+    // 1 Create broadcast channel for block headers and reorgs
+    let (tx, _rx) = broadcast::channel::<BlockEvent>(16);
+
+    // 2 Load target configs
+    let dex_configs = config::build_target_configs(provider.clone(), &registry).await?;
     let pair_configs = helpers::extract_pair_configs(&dex_configs)?;
 
-    helpers::start_all_listeners(dex_configs.clone(), provider.clone(), tracker.clone()).await?;
+    let multiplexer = helpers::register_all_pools(
+        dex_configs.clone(), 
+        registry.clone(), 
+        rpc_pool.clone(),
+        provider.clone(), 
+        tracker.clone(),
+        replaying,
+    ).await?;
 
-    // 5 Start Arbitrage Workers (block-triggered)
-    for (_factory, pairs) in pair_configs.clone() {
-        for pair in pairs {
+    // Prometheus scrape endpoint
+    {
+        let addr = std::env::var("METRICS_ADDR")
+            .unwrap_or_else(|_| DEFAULT_METRICS_ADDR.to_string())
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid METRICS_ADDR: {:?}", e))?;
+        let tracker = tracker.clone();
+        let token = shutdown.token();
+        supervisor.spawn("metrics", move || metrics::serve(addr, tracker.clone(), token.clone()));
+    }
+
+    // 3 Keep blocks and pool logs flowing across reconnects. The watcher
+    // outlives ingestion restarts so its chain buffer and head carry over.
+    {
+        let connection = connection.clone();
+        let block_watcher = Arc::new(BlockWatcher::new(tx.clone(), rpc_pool.clone()));
+        let multiplexer = multiplexer.clone();
+        let token = shutdown.token();
+        supervisor.spawn("ingestion", move || {
+            let run = ingestion(connection.clone(), block_watcher.clone(), multiplexer.clone());
+            let token = token.clone();
+            // Dropping the ingestion future tears down the block watcher and log subscriptions
+            async move {
+                tokio::select! {
+                    res = run => res,
+                    _ = token.cancelled() => Ok(()),
+                }
+            }
+        });
+    }
+
+    // Re-bump or cancel our stuck transactions
+    if mode == ExecutionMode::Live {
+        let nonces = ctx.nonces.clone();
+        let connection = connection.clone();
+        let shutdown = shutdown.clone();
+        supervisor.spawn("nonce-manager", move || {
+            nonces.clone().run(connection.clone(), shutdown.clone())
+        });
+    }
+
+    // Keep inventory balances fresh and near target weights
+    if let Some(inventory) = ctx.inventory.clone() {
+        let ctx = ctx.clone();
+        supervisor.spawn("inventory", move || inventory.clone().run(ctx.clone()));
+    }
+
+    // 4 Start Arbitrage Workers (block-triggered)
+    let workers = Arc::new(DashMap::new());
+    spawn_workers(&supervisor, &workers, pair_configs, &tx, &tracker, &ctx);
+
+    // 5 Rebuild target configs when an operator asks for a reload
+    {
+        let supervisor_handle = supervisor.clone();
+        let registry = registry.clone();
+        let connection = connection.clone();
+        let tracker = tracker.clone();
+        let multiplexer = multiplexer.clone();
+        let ctx = ctx.clone();
+        let tx = tx.clone();
+        supervisor.spawn("config-reload", move || {
+            let mut reloads = ctx.controls.subscribe_reload();
+            let supervisor = supervisor_handle.clone();
+            let registry = registry.clone();
+            let connection = connection.clone();
             let tracker = tracker.clone();
-            let rx = tx.subscribe(); // every worker gets its own receiver
-            let pair = pair.clone();
-            let provider = provider.clone();
+            let multiplexer = multiplexer.clone();
+            let workers = workers.clone();
+            let ctx = ctx.clone();
+            let tx = tx.clone();
 
-            tokio::spawn(async move {
-                let worker = ArbWorker::new(
-                    rx, 
-                    tracker, 
-                    pair,
-                    provider
-                );
-                if let Err(e) = worker.start().await {
-                    tracing::error!("Arbitrage worker failed for : {:?}", e);
+            async move {
+                while reloads.changed().await.is_ok() {
+                    let provider = connection.provider();
+                    let dex_configs = config::build_target_configs(provider.clone(), &registry).await?;
+                    let (added, removed) = helpers::sync_pools(
+                        dex_configs.clone(),
+                        registry.clone(),
+                        &multiplexer,
+                        provider,
+                        tracker.clone(),
+                    )
+                    .await?;
+                    spawn_workers(
+                        &supervisor,
+                        &workers,
+                        helpers::extract_pair_configs(&dex_configs)?,
+                        &tx,
+                        &tracker,
+                        &ctx,
+                    );
+                    tracing::info!("🔄 Config reloaded: {} pools added, {} removed", added, removed);
                 }
+                Ok(())
+            }
+        });
+    }
+
+    // Admin API, only with a token configured
+    match helpers::load_admin_token() {
+        Some(token) => {
+            let addr = std::env::var("ADMIN_ADDR")
+                .unwrap_or_else(|_| DEFAULT_ADMIN_ADDR.to_string())
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid ADMIN_ADDR: {:?}", e))?;
+            let state = Arc::new(AdminState {
+                controls: controls.clone(),
+                tracker: tracker.clone(),
+                multiplexer: multiplexer.clone(),
+                paper: ctx.paper.clone(),
+                ctx: ctx.clone(),
+                supervisor: supervisor.clone(),
+            });
+            let shutdown_token = shutdown.token();
+            supervisor.spawn("admin-api", move || {
+                admin_api::serve(addr, state.clone(), token.clone(), shutdown_token.clone())
             });
         }
+        None => tracing::warn!("No admin token configured; admin API disabled"),
     }
 
     tracing::info!("🚀 System initialized: block watcher, listeners, and workers running");
 
-    // Prevent main from exiting
-    tokio::signal::ctrl_c().await?;
+    // Run until SIGINT/SIGTERM, then wind down in order
+    Shutdown::wait_for_signal().await?;
     tracing::info!("Shutting down gracefully...");
+    shutdown.trigger();
+
+    // One budget for both: in-flight transactions settle first, with the
+    // nonce manager still bumping them, then the remaining tasks stop
+    let timeout = Duration::from_secs(
+        helpers::env_u64("SHUTDOWN_TIMEOUT_SECS", DEFAULT_SHUTDOWN_TIMEOUT_SECS)
+    );
+    let deadline = tokio::time::Instant::now() + timeout;
+    shutdown.drain_in_flight(deadline).await;
+
+    if !supervisor.wait_stopped(deadline).await {
+        tracing::warn!("Some tasks did not stop within {:?}", timeout);
+    }
+
+    shutdown.run_hooks().await;
+    tracing::info!("👋 Shutdown complete");
     Ok(())
 }
+
+/// Start a supervised worker for every (factory, pair) not already running,
+/// and stop those whose pair is no longer in `pair_configs`
+fn spawn_workers<M: Middleware + 'static>(
+    supervisor: &Arc<Supervisor>,
+    running: &DashMap<String, CancellationToken>,
+    pair_configs: Vec<(Address, Vec<DexPairConfig>)>,
+    tx: &broadcast::Sender<BlockEvent>,
+    tracker: &Arc<PriceTracker>,
+    ctx: &Arc<ExecutionContext<M>>,
+) {
+    let mut wanted = HashSet::new();
+
+    for (factory, pairs) in pair_configs {
+        for pair in pairs {
+            let name = format!("worker:{:?}/{:?}@{:?}", pair.base.id, pair.quote.id, factory);
+            wanted.insert(name.clone());
+            if running.contains_key(&name) {
+                continue;
+            }
+
+            let stop = ctx.shutdown.token().child_token();
+            running.insert(name.clone(), stop.clone());
+
+            let tracker = tracker.clone();
+            let tx = tx.clone();
+            let ctx = ctx.clone();
+            supervisor.spawn_until(name, stop.clone(), move || {
+                let worker = ArbWorker::new(
+                    tx.subscribe(), // every worker gets its own receiver
+                    tracker.clone(), 
+                    pair.clone(),
+                    ctx.clone(),
+                    stop.clone(),
+                );
+                worker.start()
+            });
+        }
+    }
+
+    running.retain(|name, stop| {
+        if wanted.contains(name) {
+            return true;
+        }
+        tracing::info!("🛑 Stopping {}; its pair left the config", name);
+        stop.cancel();
+        false
+    });
+}
@@ -0,0 +1,239 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{Router, extract::State, http::StatusCode, routing::get};
+use ethers::types::{TransactionReceipt, U64};
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, Gauge, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec,
+    Opts, Registry, TextEncoder,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::types::PriceTracker;
+
+/// Process-wide metrics, registered once and exported on `/metrics`
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+pub struct Metrics {
+    registry: Registry,
+    /// Swap/price logs decoded, by DEX and pool
+    pub swap_events: IntCounterVec,
+    /// Time spent writing one price into the tracker
+    pub tracker_update_seconds: Histogram,
+    /// Seconds since each pool's price last changed, by factory and pool;
+    /// refreshed on scrape
+    pub price_age_seconds: GaugeVec,
+    /// Best buy/sell spread seen per block, in basis points
+    pub spread_bps: Histogram,
+    /// Opportunities by stage: detected, attempted, succeeded, reverted
+    pub opportunities: IntCounterVec,
+    /// Pre-trade simulations that aborted a trade, by revert kind
+    pub simulation_rejections: IntCounterVec,
+    /// Cumulative realized profit per quote token, in whole units
+    pub realized_profit: GaugeVec,
+    /// Cumulative dry-run profit per quote token, in whole units
+    pub paper_profit: GaugeVec,
+    /// Gas paid by our transactions, in wei
+    pub gas_spent_wei: Gauge,
+    /// Wallet balance per inventory token, in whole units
+    pub inventory_balance: GaugeVec,
+    /// RPC calls per endpoint and method
+    pub rpc_calls: IntCounterVec,
+    /// Failed RPC calls per endpoint and method
+    pub rpc_errors: IntCounterVec,
+    /// Successful provider reconnects
+    pub ws_reconnects: IntCounter,
+    /// Block events workers missed because they fell behind the channel
+    pub broadcast_lag: IntCounter,
+    /// History rows dropped because the store's queue was full, by kind
+    pub store_dropped_events: IntCounterVec,
+    /// Wall-clock time of each RPC call, by method
+    pub rpc_latency_seconds: HistogramVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("arb".into()), None)
+            .expect("valid metrics prefix");
+
+        let swap_events = IntCounterVec::new(
+            Opts::new("swap_events_total", "Pool events decoded into prices"),
+            &["dex", "pool"],
+        )
+        .expect("valid metric");
+        let tracker_update_seconds = Histogram::with_opts(
+            HistogramOpts::new("tracker_update_seconds", "Price tracker write latency")
+                .buckets(prometheus::exponential_buckets(0.000_01, 4.0, 8).expect("valid buckets")),
+        )
+        .expect("valid metric");
+        let price_age_seconds = GaugeVec::new(
+            Opts::new("price_age_seconds", "Seconds since the pool's price last updated"),
+            &["factory", "pool"],
+        )
+        .expect("valid metric");
+        let spread_bps = Histogram::with_opts(
+            HistogramOpts::new("spread_bps", "Best cross-DEX spread per block, in bps")
+                .buckets(vec![1.0, 5.0, 10.0, 20.0, 30.0, 50.0, 100.0, 200.0, 500.0]),
+        )
+        .expect("valid metric");
+        let opportunities = IntCounterVec::new(
+            Opts::new("opportunities_total", "Arbitrage opportunities by stage"),
+            &["stage"],
+        )
+        .expect("valid metric");
+        let simulation_rejections = IntCounterVec::new(
+            Opts::new("simulation_rejections_total", "Trades aborted by pre-trade simulation"),
+            &["reason"],
+        )
+        .expect("valid metric");
+        let realized_profit = GaugeVec::new(
+            Opts::new("realized_profit", "Realized profit of settled trades in quote units"),
+            &["quote"],
+        )
+        .expect("valid metric");
+        let paper_profit = GaugeVec::new(
+            Opts::new("paper_profit", "Would-be profit of dry-run trades in quote units"),
+            &["quote"],
+        )
+        .expect("valid metric");
+        let gas_spent_wei = Gauge::new("gas_spent_wei", "Gas paid by submitted transactions, in wei")
+            .expect("valid metric");
+        let inventory_balance = GaugeVec::new(
+            Opts::new("inventory_balance", "Wallet balance of each inventory token in whole units"),
+            &["token"],
+        )
+        .expect("valid metric");
+        let rpc_calls = IntCounterVec::new(
+            Opts::new("rpc_calls_total", "RPC calls by endpoint and method"),
+            &["endpoint", "method"],
+        )
+        .expect("valid metric");
+        let rpc_errors = IntCounterVec::new(
+            Opts::new("rpc_errors_total", "Failed RPC calls by endpoint and method"),
+            &["endpoint", "method"],
+        )
+        .expect("valid metric");
+        let rpc_latency_seconds = HistogramVec::new(
+            HistogramOpts::new("rpc_latency_seconds", "RPC call latency"),
+            &["method"],
+        )
+        .expect("valid metric");
+        let ws_reconnects = IntCounter::new("ws_reconnects_total", "Provider reconnects")
+            .expect("valid metric");
+        let broadcast_lag = IntCounter::new(
+            "broadcast_lag_total",
+            "Block events skipped by lagging workers",
+        )
+        .expect("valid metric");
+        let store_dropped_events = IntCounterVec::new(
+            Opts::new("store_dropped_events_total", "History rows dropped on a full store queue"),
+            &["kind"],
+        )
+        .expect("valid metric");
+
+        for collector in [
+            Box::new(swap_events.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(tracker_update_seconds.clone()),
+            Box::new(price_age_seconds.clone()),
+            Box::new(spread_bps.clone()),
+            Box::new(opportunities.clone()),
+            Box::new(simulation_rejections.clone()),
+            Box::new(realized_profit.clone()),
+            Box::new(paper_profit.clone()),
+            Box::new(gas_spent_wei.clone()),
+            Box::new(inventory_balance.clone()),
+            Box::new(rpc_calls.clone()),
+            Box::new(rpc_errors.clone()),
+            Box::new(rpc_latency_seconds.clone()),
+            Box::new(ws_reconnects.clone()),
+            Box::new(broadcast_lag.clone()),
+            Box::new(store_dropped_events.clone()),
+        ] {
+            registry.register(collector).expect("metric registered once");
+        }
+
+        Self {
+            registry,
+            swap_events,
+            tracker_update_seconds,
+            price_age_seconds,
+            spread_bps,
+            opportunities,
+            simulation_rejections,
+            realized_profit,
+            paper_profit,
+            gas_spent_wei,
+            inventory_balance,
+            rpc_calls,
+            rpc_errors,
+            ws_reconnects,
+            broadcast_lag,
+            store_dropped_events,
+            rpc_latency_seconds,
+        }
+    }
+
+    /// Count one opportunity reaching `stage`
+    pub fn opportunity(&self, stage: &str) {
+        self.opportunities.with_label_values(&[stage]).inc();
+    }
+
+    /// Outcome and gas cost of one of our mined transactions
+    pub fn record_receipt(&self, receipt: &TransactionReceipt) {
+        let stage = if receipt.status == Some(U64::one()) { "succeeded" } else { "reverted" };
+        self.opportunity(stage);
+
+        if let (Some(gas_used), Some(price)) = (receipt.gas_used, receipt.effective_gas_price) {
+            self.gas_spent_wei.add((gas_used * price).as_u128() as f64);
+        }
+    }
+
+    /// Refresh per-pool price ages from the tracker
+    pub async fn observe_price_ages(&self, tracker: &PriceTracker) {
+        self.price_age_seconds.reset();
+        for (key, info) in tracker.snapshot().await {
+            self.price_age_seconds
+                .with_label_values(&[
+                    &format!("{:?}", key.dex_factory),
+                    &format!("{:?}", key.pair_address),
+                ])
+                .set(info.updated_at.elapsed().as_secs_f64());
+        }
+    }
+
+    /// Everything in Prometheus text format
+    pub fn render(&self) -> anyhow::Result<String> {
+        let mut buf = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buf)
+            .map_err(|e| anyhow::anyhow!("Failed to encode metrics: {:?}", e))?;
+        Ok(String::from_utf8(buf)?)
+    }
+}
+
+async fn metrics_handler(State(tracker): State<Arc<PriceTracker>>) -> (StatusCode, String) {
+    METRICS.observe_price_ages(&tracker).await;
+    match METRICS.render() {
+        Ok(body) => (StatusCode::OK, body),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{:?}", e)),
+    }
+}
+
+/// Serve `GET /metrics` on `addr` until `shutdown` is cancelled
+pub async fn serve(
+    addr: SocketAddr,
+    tracker: Arc<PriceTracker>,
+    shutdown: CancellationToken,
+) -> anyhow::Result<()> {
+    let app = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(tracker);
+
+    tracing::info!("📊 Metrics listening on http://{}/metrics", addr);
+    axum::Server::try_bind(&addr)
+        .map_err(|e| anyhow::anyhow!("Failed to bind metrics server on {}: {:?}", addr, e))?
+        .serve(app.into_make_service())
+        .with_graceful_shutdown(shutdown.cancelled_owned())
+        .await
+        .map_err(|e| anyhow::anyhow!("Metrics server failed: {:?}", e))
+}
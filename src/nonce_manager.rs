@@ -0,0 +1,436 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockNumber, Bytes, H256 as TxHash, U256, transaction::eip2718::TypedTransaction},
+};
+use tokio::sync::Mutex;
+
+use crate::{
+    connection_manager::ConnectionManager,
+    constants::{
+        DEFAULT_MAX_FEE_BUMPS, DEFAULT_STUCK_TX_SECS, FEE_BUMP_PERCENT, IN_FLIGHT_POLL_INTERVAL_MS,
+    },
+    helpers::env_u64,
+    shutdown::Shutdown,
+    simulation::{SimulationError, simulate_tx},
+    store::{self, StoreEvent},
+};
+
+/// Gas for a plain value transfer, which is all a cancellation is
+const TRANSFER_GAS: u64 = 21_000;
+/// Mined nonces whose attempts are still kept for receipt followers
+const CONFIRMED_NONCES_KEPT: u64 = 256;
+
+/// Every transaction broadcast at one nonce; at most one of them is mined
+#[derive(Debug, Clone, Default)]
+pub struct NonceAttempts {
+    pub nonce: U256,
+    /// The original first, then each replacement
+    pub hashes: Vec<TxHash>,
+    /// Those of `hashes` that are cancellations
+    pub cancellations: Vec<TxHash>,
+}
+
+/// A transaction we sent that hasn't been mined yet
+#[derive(Debug, Clone)]
+pub struct PendingTx {
+    /// Exactly what was last broadcast under this nonce
+    pub tx: TypedTransaction,
+    pub hash: TxHash,
+    pub sent_at: Instant,
+    /// Fee bumps so far
+    pub bumps: u64,
+    /// Profit the trade must still simulate to before it's worth re-bumping
+    pub min_profit: U256,
+    /// Replaced by a zero-value self-send
+    pub cancelling: bool,
+    pub attempts: NonceAttempts,
+}
+
+/// Hands out nonces to every worker sharing the wallet, and keeps what
+/// they sent moving: a transaction stuck for `STUCK_TX_SECS` is re-bumped
+/// if its trade still simulates profitably, otherwise cancelled.
+///
+/// The next nonce is read from chain on first use and again after any
+/// failed send or dropped transaction.
+pub struct NonceManager {
+    next: Mutex<Option<U256>>,
+    pending: DashMap<U256, PendingTx>,
+    /// Attempts at recently mined nonces, for followers yet to see which landed
+    confirmed: DashMap<U256, NonceAttempts>,
+}
+
+impl Default for NonceManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NonceManager {
+    pub fn new() -> Self {
+        Self {
+            next: Mutex::new(None),
+            pending: DashMap::new(),
+            confirmed: DashMap::new(),
+        }
+    }
+
+    /// Transactions sent and not yet mined, by nonce
+    pub fn pending(&self) -> Vec<(U256, PendingTx)> {
+        let mut pending: Vec<_> = self.pending.iter().map(|e| (*e.key(), e.value().clone())).collect();
+        pending.sort_by_key(|(nonce, _)| *nonce);
+        pending
+    }
+
+    /// Everything sent at the same nonce as `hash`: the original and its
+    /// fee bumps and cancellations. Kept until well after the nonce is mined.
+    pub fn attempts(&self, hash: TxHash) -> Option<NonceAttempts> {
+        if let Some(pending) = self.pending.iter().find(|p| p.attempts.hashes.contains(&hash)) {
+            return Some(pending.attempts.clone());
+        }
+        self.confirmed
+            .iter()
+            .find(|a| a.hashes.contains(&hash))
+            .map(|a| a.value().clone())
+    }
+
+    /// Forget the local nonce; the next send reads it from chain
+    pub async fn resync(&self) {
+        *self.next.lock().await = None;
+    }
+
+    /// Assign the next nonce to `tx` and broadcast it. Sends are serialized
+    /// so nonces reach the node in order; a failed send triggers a resync.
+    pub async fn send<M: Middleware + 'static>(
+        &self,
+        provider: &M,
+        mut tx: TypedTransaction,
+        min_profit: U256,
+    ) -> anyhow::Result<TxHash> {
+        let from = sender(provider)?;
+        let mut next = self.next.lock().await;
+
+        let nonce = match *next {
+            Some(nonce) => nonce,
+            None => {
+                let nonce = chain_nonce(provider, from, BlockNumber::Pending).await?;
+                tracing::info!("🔢 Nonce synced from chain: {}", nonce);
+                nonce
+            }
+        };
+        tx.set_nonce(nonce);
+
+        match provider.send_transaction(tx.clone(), None).await {
+            Ok(sent) => {
+                let hash = sent.tx_hash();
+                *next = Some(nonce + 1);
+                store::record(StoreEvent::Transaction {
+                    at: store::now(),
+                    hash,
+                    kind: "send",
+                    nonce,
+                    target: tx.to_addr().copied(),
+                    replaces: None,
+                });
+                let attempts = NonceAttempts { nonce, hashes: vec![hash], cancellations: Vec::new() };
+                self.pending.insert(
+                    nonce,
+                    PendingTx { tx, hash, sent_at: Instant::now(), bumps: 0, min_profit, cancelling: false, attempts },
+                );
+                Ok(hash)
+            }
+            Err(e) => {
+                *next = None;
+                anyhow::bail!("eth_sendRawTransaction failed at nonce {}: {:?}", nonce, e)
+            }
+        }
+    }
+
+    /// Re-send the transaction at `nonce` with fees raised by `FEE_BUMP_PERCENT`
+    pub async fn bump<M: Middleware + 'static>(&self, provider: &M, nonce: U256) -> anyhow::Result<TxHash> {
+        let mut tx = self.pending_tx(nonce)?.tx;
+        bump_fees(&mut tx);
+        self.replace(provider, nonce, tx, false).await
+    }
+
+    /// Replace the transaction at `nonce` with a zero-value self-send that
+    /// outbids it, freeing the nonce without running the trade
+    pub async fn cancel<M: Middleware + 'static>(&self, provider: &M, nonce: U256) -> anyhow::Result<TxHash> {
+        let from = sender(provider)?;
+        let mut tx = self.pending_tx(nonce)?.tx;
+        tx.set_to(from);
+        tx.set_value(U256::zero());
+        tx.set_data(Bytes::new());
+        tx.set_gas(TRANSFER_GAS);
+        tx.set_access_list(Default::default());
+        bump_fees(&mut tx);
+        self.replace(provider, nonce, tx, true).await
+    }
+
+    fn pending_tx(&self, nonce: U256) -> anyhow::Result<PendingTx> {
+        self.pending
+            .get(&nonce)
+            .map(|p| p.clone())
+            .ok_or_else(|| anyhow::anyhow!("No pending transaction at nonce {}", nonce))
+    }
+
+    async fn replace<M: Middleware + 'static>(
+        &self,
+        provider: &M,
+        nonce: U256,
+        tx: TypedTransaction,
+        cancelling: bool,
+    ) -> anyhow::Result<TxHash> {
+        let hash = provider
+            .send_transaction(tx.clone(), None)
+            .await
+            .map_err(|e| anyhow::anyhow!("Replacement at nonce {} failed: {:?}", nonce, e))?
+            .tx_hash();
+
+        if let Some(mut pending) = self.pending.get_mut(&nonce) {
+            pending.attempts.hashes.push(hash);
+            if cancelling {
+                pending.attempts.cancellations.push(hash);
+            }
+            store::record(StoreEvent::Transaction {
+                at: store::now(),
+                hash,
+                kind: if cancelling { "cancel" } else { "bump" },
+                nonce,
+                target: tx.to_addr().copied(),
+                replaces: Some(pending.hash),
+            });
+            pending.tx = tx;
+            pending.hash = hash;
+            pending.sent_at = Instant::now();
+            pending.bumps += 1;
+            pending.cancelling = cancelling;
+        }
+        Ok(hash)
+    }
+
+    /// One maintenance pass: forget mined nonces, resync after drops, and
+    /// re-bump or cancel anything stuck
+    pub async fn check_pending<M: Middleware + 'static>(
+        &self,
+        connection: &Arc<ConnectionManager<M>>,
+        shutdown: &Arc<Shutdown>,
+    ) -> anyhow::Result<()> {
+        let provider = connection.provider();
+        let from = sender(&*provider)?;
+        let mined = chain_nonce(&*provider, from, BlockNumber::Latest).await?;
+        let confirmed: Vec<U256> = self.pending.iter().map(|p| *p.key()).filter(|nonce| *nonce < mined).collect();
+        for nonce in confirmed {
+            if let Some((_, pending)) = self.pending.remove(&nonce) {
+                self.confirmed.insert(nonce, pending.attempts);
+            }
+        }
+        self.confirmed.retain(|nonce, _| *nonce + CONFIRMED_NONCES_KEPT >= mined);
+
+        {
+            // Our pending nonces are all gone yet the local counter is ahead
+            // of chain: something was dropped and left a gap
+            let mut next = self.next.lock().await;
+            if self.pending.is_empty() && next.is_some_and(|n| n != mined) {
+                tracing::warn!("🔢 Nonce gap detected (local {:?}, chain {}); resyncing", *next, mined);
+                *next = None;
+            }
+        }
+
+        let stuck_after = Duration::from_secs(env_u64("STUCK_TX_SECS", DEFAULT_STUCK_TX_SECS));
+        let max_bumps = env_u64("MAX_FEE_BUMPS", DEFAULT_MAX_FEE_BUMPS);
+
+        for (nonce, pending) in self.pending() {
+            if pending.sent_at.elapsed() < stuck_after {
+                continue;
+            }
+
+            let cancel = if pending.cancelling {
+                // A cancellation gets as many bumps again before we give up on it
+                if pending.bumps >= max_bumps * 2 {
+                    continue;
+                }
+                true
+            } else {
+                match simulate_tx(&*provider, &pending.tx, pending.min_profit, None).await {
+                    Ok(_) => pending.bumps >= max_bumps,
+                    Err(SimulationError::Reverted(_) | SimulationError::BelowMinProfit { .. }) => true,
+                    // Can't tell whether it's still worth it; try again next pass
+                    Err(SimulationError::Call(e)) => {
+                        tracing::debug!("Re-simulation of nonce {} failed: {}", nonce, e);
+                        continue;
+                    }
+                }
+            };
+
+            let replaced = if cancel {
+                self.cancel(&*provider, nonce).await
+            } else {
+                self.bump(&*provider, nonce).await
+            };
+            let action = if cancel { "cancelled" } else { "fee-bumped" };
+            match replaced {
+                Ok(hash) => {
+                    tracing::info!("⛽ Stuck tx {:?} at nonce {} {} as {:?}", pending.hash, nonce, action, hash);
+                    shutdown.track_tx(hash, connection.clone());
+                }
+                Err(e) => tracing::warn!("Stuck tx at nonce {} could not be {}: {:?}", nonce, action, e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Keep checking pending transactions until shutdown has drained them
+    pub async fn run<M: Middleware + 'static>(
+        self: Arc<Self>,
+        connection: Arc<ConnectionManager<M>>,
+        shutdown: Arc<Shutdown>,
+    ) -> anyhow::Result<()> {
+        let token = shutdown.drained_token();
+        let mut ticker = tokio::time::interval(Duration::from_millis(IN_FLIGHT_POLL_INTERVAL_MS));
+
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => return Ok(()),
+                _ = ticker.tick() => {}
+            }
+
+            if self.pending.is_empty() && self.next.lock().await.is_none() {
+                continue;
+            }
+            if let Err(e) = self.check_pending(&connection, &shutdown).await {
+                tracing::warn!("Pending tx check failed: {:?}", e);
+            }
+        }
+    }
+}
+
+fn sender<M: Middleware>(provider: &M) -> anyhow::Result<Address> {
+    provider
+        .default_sender()
+        .ok_or_else(|| anyhow::anyhow!("Provider has no signer to send from"))
+}
+
+async fn chain_nonce<M: Middleware>(provider: &M, from: Address, block: BlockNumber) -> anyhow::Result<U256> {
+    provider
+        .get_transaction_count(from, Some(block.into()))
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read nonce for {:?}: {:?}", from, e))
+}
+
+/// Raise every fee field by `FEE_BUMP_PERCENT`; nodes only accept a
+/// replacement that outbids the original by at least 10%
+fn bump_fees(tx: &mut TypedTransaction) {
+    let bump = |fee: U256| fee * (100 + FEE_BUMP_PERCENT) / 100 + 1;
+
+    match tx {
+        TypedTransaction::Eip1559(inner) => {
+            inner.max_fee_per_gas = inner.max_fee_per_gas.map(bump);
+            inner.max_priority_fee_per_gas = inner.max_priority_fee_per_gas.map(bump);
+        }
+        _ => {
+            if let Some(price) = tx.gas_price() {
+                tx.set_gas_price(bump(price));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        providers::{MockProvider, Provider},
+        types::{Eip1559TransactionRequest, TransactionRequest},
+    };
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::{connection_manager::Backoff, provider_factory::ProviderFactory};
+
+    async fn connection() -> (Arc<ConnectionManager<Provider<MockProvider>>>, MockProvider) {
+        let (provider, mock) = ProviderFactory::default().mock();
+        let provider = Arc::new(provider.with_sender(Address::repeat_byte(0xaa)));
+        let connection = ConnectionManager::connect(
+            Arc::new(move || {
+                let provider = provider.clone();
+                async move { Ok(provider) }.boxed()
+            }),
+            Backoff::default(),
+        )
+        .await;
+        (Arc::new(connection), mock)
+    }
+
+    fn pending(nonce: u64) -> PendingTx {
+        let hash = TxHash::from_low_u64_be(nonce);
+        PendingTx {
+            tx: TransactionRequest::new().nonce(nonce).into(),
+            hash,
+            sent_at: Instant::now(),
+            bumps: 0,
+            min_profit: U256::zero(),
+            cancelling: false,
+            attempts: NonceAttempts { nonce: U256::from(nonce), hashes: vec![hash], cancellations: Vec::new() },
+        }
+    }
+
+    #[test]
+    fn bump_raises_every_fee_by_the_bump_percent_plus_one() {
+        let mut tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .max_fee_per_gas(100_000_000_000u64)
+            .max_priority_fee_per_gas(30_000_000_000u64)
+            .into();
+        bump_fees(&mut tx);
+        let TypedTransaction::Eip1559(inner) = tx else { unreachable!() };
+        assert_eq!(inner.max_fee_per_gas, Some(U256::from(115_000_000_001u64)));
+        assert_eq!(inner.max_priority_fee_per_gas, Some(U256::from(34_500_000_001u64)));
+
+        // Rounds down before the +1, so the replacement still outbids
+        let mut tx: TypedTransaction = TransactionRequest::new().gas_price(1_001u64).into();
+        bump_fees(&mut tx);
+        assert_eq!(tx.gas_price(), Some(U256::from(1_152u64)));
+    }
+
+    #[tokio::test]
+    async fn check_pending_confirms_mined_nonces_and_keeps_their_attempts() {
+        let (connection, mock) = connection().await;
+        let manager = NonceManager::new();
+        *manager.next.lock().await = Some(U256::from(7u64));
+        manager.pending.insert(U256::from(5u64), pending(5));
+        manager.pending.insert(U256::from(6u64), pending(6));
+
+        mock.push(U256::from(6u64)).unwrap();
+        manager.check_pending(&connection, &Arc::new(Shutdown::new())).await.unwrap();
+
+        let still_pending: Vec<U256> = manager.pending().into_iter().map(|(nonce, _)| nonce).collect();
+        assert_eq!(still_pending, vec![U256::from(6u64)]);
+        let attempts = manager.attempts(TxHash::from_low_u64_be(5)).unwrap();
+        assert_eq!(attempts.nonce, U256::from(5u64));
+        assert_eq!(*manager.next.lock().await, Some(U256::from(7u64)));
+    }
+
+    #[tokio::test]
+    async fn check_pending_resyncs_only_on_a_gap() {
+        let (connection, mock) = connection().await;
+        let shutdown = Arc::new(Shutdown::new());
+        let manager = NonceManager::new();
+
+        // Nothing pending and the local counter matches chain
+        *manager.next.lock().await = Some(U256::from(7u64));
+        mock.push(U256::from(7u64)).unwrap();
+        manager.check_pending(&connection, &shutdown).await.unwrap();
+        assert_eq!(*manager.next.lock().await, Some(U256::from(7u64)));
+
+        // Ahead of chain with nothing pending: a dropped tx left a gap
+        *manager.next.lock().await = Some(U256::from(9u64));
+        mock.push(U256::from(7u64)).unwrap();
+        manager.check_pending(&connection, &shutdown).await.unwrap();
+        assert_eq!(*manager.next.lock().await, None);
+    }
+}
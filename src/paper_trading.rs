@@ -0,0 +1,238 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use dashmap::DashMap;
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockNumber, Bytes, TransactionRequest, U256, transaction::eip2718::TypedTransaction},
+};
+use serde::Serialize;
+
+use crate::{
+    constants::RECENT_OPPORTUNITIES_LEN,
+    execution_context::ExecutionContext,
+    flash_loan::FlashLoan,
+    helpers::{arb_contract, to_f64_normalized},
+    metrics::METRICS,
+    simulation::simulate_tx,
+    types::{ArbBounds, ArbOpportunity, DexPairConfig, DexPairKey, SwapSimulation},
+};
+
+/// What one opportunity would have done had it been sent
+#[derive(Debug, Clone, Serialize)]
+pub struct PaperTrade {
+    /// Unix seconds of the simulation
+    pub at: u64,
+    pub base: Address,
+    pub quote: Address,
+    pub buy_factory: Address,
+    pub sell_factory: Address,
+    /// Quote tokens spent on the buy leg
+    pub amount_in: U256,
+    /// Base tokens received on the buy leg and sold on the sell leg
+    pub base_out: U256,
+    /// Quote tokens received on the sell leg
+    pub quote_out: U256,
+    /// `quote_out - amount_in`, less any flash fee, in whole quote units
+    pub profit: f64,
+    pub buy_price_before: f64,
+    pub buy_price_after: f64,
+    pub sell_price_before: f64,
+    pub sell_price_after: f64,
+    /// Bounds encoded into the payload from the quotes above
+    pub min_base_out: U256,
+    pub min_quote_out: U256,
+    pub min_profit: U256,
+    /// Lender the payload borrows from; `None` for inventory trades
+    pub flash: Option<FlashLoan>,
+    /// `eth_call` of the arb payload against the pending block; `None`
+    /// without a deployed arb contract, or for inventory trades
+    pub call_result: Option<String>,
+}
+
+/// Would-be trades and their cumulative PnL per quote token
+#[derive(Default)]
+pub struct PaperLedger {
+    recent: Mutex<VecDeque<PaperTrade>>,
+    profit: DashMap<Address, f64>,
+}
+
+impl PaperLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, trade: PaperTrade) {
+        let total = {
+            let mut total = self.profit.entry(trade.quote).or_insert(0.0);
+            *total += trade.profit;
+            *total
+        };
+        METRICS
+            .paper_profit
+            .with_label_values(&[&format!("{:?}", trade.quote)])
+            .set(total);
+
+        let mut recent = self.recent.lock().expect("paper trades lock poisoned");
+        if recent.len() == RECENT_OPPORTUNITIES_LEN {
+            recent.pop_front();
+        }
+        recent.push_back(trade);
+    }
+
+    /// Recorded trades, newest first
+    pub fn recent(&self) -> Vec<PaperTrade> {
+        let recent = self.recent.lock().expect("paper trades lock poisoned");
+        recent.iter().rev().cloned().collect()
+    }
+
+    /// Cumulative would-be profit per quote token
+    pub fn totals(&self) -> Vec<(Address, f64)> {
+        self.profit.iter().map(|e| (*e.key(), *e.value())).collect()
+    }
+}
+
+/// Simulate an opportunity without signing: `eth_call` the arb payload at
+/// the pending block, and price the trade from both legs' quotes and the
+/// reserves they would leave behind.
+pub async fn paper_trade<M: Middleware + 'static>(
+    buy_k: DexPairKey,
+    sell_k: DexPairKey,
+    pair: &DexPairConfig,
+    arb_opp: &ArbOpportunity,
+    arb_data: Bytes,
+    (buy, sell): (SwapSimulation, SwapSimulation),
+    ctx: &ExecutionContext<M>,
+) -> anyhow::Result<PaperTrade> {
+    let provider = ctx.provider();
+
+    let call_result = match arb_contract() {
+        Some(contract) => {
+            let mut tx = TransactionRequest::new().to(contract).data(arb_data);
+            if let Some(from) = provider.default_sender() {
+                tx = tx.from(from);
+            }
+            let tx: TypedTransaction = tx.into();
+            Some(
+                match simulate_tx(&*provider, &tx, arb_opp.bounds.min_profit, Some(BlockNumber::Pending)).await {
+                    Ok(profit) => format!("ok (profit {})", profit),
+                    Err(e) => e.to_string(),
+                },
+            )
+        }
+        None => None,
+    };
+
+    quoted_trade(buy_k, sell_k, pair, &arb_opp.bounds, Some(arb_opp.flash), call_result, (buy, sell))
+}
+
+/// Price an inventory opportunity from its quotes alone; it runs as two
+/// router swaps from the wallet, so there's no payload to call
+pub fn inventory_paper_trade(
+    buy_k: DexPairKey,
+    sell_k: DexPairKey,
+    pair: &DexPairConfig,
+    bounds: &ArbBounds,
+    legs: (SwapSimulation, SwapSimulation),
+) -> anyhow::Result<PaperTrade> {
+    quoted_trade(buy_k, sell_k, pair, bounds, None, None, legs)
+}
+
+fn quoted_trade(
+    buy_k: DexPairKey,
+    sell_k: DexPairKey,
+    pair: &DexPairConfig,
+    bounds: &ArbBounds,
+    flash: Option<FlashLoan>,
+    call_result: Option<String>,
+    (buy, sell): (SwapSimulation, SwapSimulation),
+) -> anyhow::Result<PaperTrade> {
+    // Same cost the live path checks the quotes against
+    let flash_fee = match &flash {
+        Some(flash) => flash.fee_in_quote(pair, buy.amount_out)?,
+        None => U256::zero(),
+    };
+    let profit = to_f64_normalized(sell.amount_out, pair.quote.decimals)?
+        - to_f64_normalized(pair.trade_size + flash_fee, pair.quote.decimals)?;
+
+    Ok(PaperTrade {
+        at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        base: pair.base.id,
+        quote: pair.quote.id,
+        buy_factory: buy_k.dex_factory,
+        sell_factory: sell_k.dex_factory,
+        amount_in: pair.trade_size,
+        base_out: buy.amount_out,
+        quote_out: sell.amount_out,
+        profit,
+        buy_price_before: buy.price_before,
+        buy_price_after: buy.price_after,
+        sell_price_before: sell.price_before,
+        sell_price_after: sell.price_after,
+        min_base_out: bounds.min_base_out,
+        min_quote_out: bounds.min_quote_out,
+        min_profit: bounds.min_profit,
+        flash,
+        call_result,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{flash_loan::FlashSource, types::Token};
+
+    const USDC: Address = Address::repeat_byte(0x01);
+    const WETH: Address = Address::repeat_byte(0x02);
+
+    fn pair() -> DexPairConfig {
+        DexPairConfig {
+            pair: Address::repeat_byte(0x30),
+            base: Token { id: WETH, decimals: 18 },
+            quote: Token { id: USDC, decimals: 6 },
+            trade_size: U256::from(1_000_000_000u64), // 1000 USDC
+        }
+    }
+
+    fn legs() -> (SwapSimulation, SwapSimulation) {
+        let leg = |amount_out: u128| SwapSimulation { amount_out: U256::from(amount_out), price_before: 0.0, price_after: 0.0 };
+        // 1000 USDC buys 0.5 WETH, which sells for 1010 USDC
+        (leg(500_000_000_000_000_000), leg(1_010_000_000))
+    }
+
+    fn trade(flash: Option<FlashLoan>) -> PaperTrade {
+        let keys = (DexPairKey::default(), DexPairKey::default());
+        quoted_trade(keys.0, keys.1, &pair(), &ArbBounds::default(), flash, None, legs()).unwrap()
+    }
+
+    #[test]
+    fn inventory_profit_is_the_quote_gain() {
+        assert!((trade(None).profit - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn flash_fee_in_quote_comes_off_profit() {
+        let flash = FlashLoan {
+            source: FlashSource::AaveV3,
+            lender: Address::repeat_byte(0x40),
+            token: USDC,
+            amount: pair().trade_size,
+            fee: U256::from(500_000u64), // 0.05%
+        };
+        assert!((trade(Some(flash)).profit - 9.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn flash_fee_in_base_is_priced_at_the_buy_rate() {
+        let flash = FlashLoan {
+            source: FlashSource::V2FlashSwap,
+            lender: Address::repeat_byte(0x40),
+            token: WETH,
+            amount: U256::from(500_000_000_000_000_000u128),
+            // 0.001 WETH at 2000 USDC/WETH is 2 USDC
+            fee: U256::from(1_000_000_000_000_000u128),
+        };
+        assert!((trade(Some(flash)).profit - 8.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,616 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockNumber, H256, H256 as TxHash, I256, Log, TransactionReceipt, U64, U256},
+    utils::keccak256,
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    connection_manager::ConnectionManager,
+    constants::{
+        DEFAULT_PNL_LEDGER_PATH, DROPPED_TX_GRACE_SECS, IN_FLIGHT_POLL_INTERVAL_MS, RECENT_OPPORTUNITIES_LEN,
+    },
+    execution_context::ExecutionContext,
+    gas_strategy::native_value,
+    helpers::{arb_contract, to_f64_normalized},
+    metrics::METRICS,
+    nonce_manager::{NonceAttempts, NonceManager},
+    store::{self, StoreEvent},
+    types::{DexPairConfig, PriceTracker},
+};
+
+/// topic0 of ERC20 `Transfer(address,address,uint256)`
+static TRANSFER_TOPIC: Lazy<H256> = Lazy::new(|| H256::from(keccak256("Transfer(address,address,uint256)")));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeStatus {
+    Succeeded,
+    /// At least one transaction reverted
+    Reverted,
+    /// At least one transaction was never mined, nor anything replacing it
+    Dropped,
+    /// A cancellation took a transaction's nonce before it could run
+    Cancelled,
+}
+
+/// A trade that's been sent and is waiting on its receipts
+#[derive(Debug, Clone)]
+pub struct OpenTrade {
+    pub pair: DexPairConfig,
+    pub buy_factory: Address,
+    pub sell_factory: Address,
+    /// Simulated or quoted profit, in quote wei
+    pub expected_profit: U256,
+}
+
+/// One settled trade, as written to the ledger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeRecord {
+    /// Unix seconds of settlement
+    pub at: u64,
+    /// Every transaction of the trade, as finally mined
+    pub hashes: Vec<TxHash>,
+    pub base: Address,
+    pub quote: Address,
+    pub buy_factory: Address,
+    pub sell_factory: Address,
+    /// In whole quote units
+    pub expected_profit: f64,
+    /// Net quote and base transferred into the wallet and arb contract
+    pub quote_delta: f64,
+    pub base_delta: f64,
+    pub gas_used: U256,
+    pub gas_cost_wei: U256,
+    /// Gas valued in quote; `None` when no tracked pool prices it
+    pub gas_cost_quote: Option<f64>,
+    /// Quote delta, plus base delta at the tracked price, less gas
+    pub realized_profit: f64,
+    pub status: TradeStatus,
+    /// Block of the last receipt
+    pub block: Option<u64>,
+}
+
+/// Cumulative results of a group of trades
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PnlTotal {
+    pub trades: u64,
+    /// Reverted, dropped or cancelled
+    pub failed: u64,
+    pub expected_profit: f64,
+    pub realized_profit: f64,
+    pub gas_cost_wei: U256,
+}
+
+impl PnlTotal {
+    fn add(&mut self, record: &TradeRecord) {
+        self.trades += 1;
+        if record.status != TradeStatus::Succeeded {
+            self.failed += 1;
+        }
+        self.expected_profit += record.expected_profit;
+        self.realized_profit += record.realized_profit;
+        self.gas_cost_wei += record.gas_cost_wei;
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PairPnl {
+    pub base: Address,
+    pub quote: Address,
+    #[serde(flatten)]
+    pub total: PnlTotal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DexPnl {
+    pub buy_factory: Address,
+    pub sell_factory: Address,
+    pub quote: Address,
+    #[serde(flatten)]
+    pub total: PnlTotal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DayPnl {
+    /// UTC, `YYYY-MM-DD`
+    pub day: String,
+    pub quote: Address,
+    #[serde(flatten)]
+    pub total: PnlTotal,
+}
+
+/// Cumulative PnL along every axis, plus the latest trades
+#[derive(Debug, Clone, Serialize)]
+pub struct PnlSummary {
+    pub pairs: Vec<PairPnl>,
+    pub dexes: Vec<DexPnl>,
+    pub days: Vec<DayPnl>,
+    pub trades: Vec<TradeRecord>,
+}
+
+#[derive(Default)]
+struct LedgerState {
+    recent: VecDeque<TradeRecord>,
+    by_pair: BTreeMap<(Address, Address), PnlTotal>,
+    /// Keyed with the quote too, so totals never mix units
+    by_dexes: BTreeMap<(Address, Address, Address), PnlTotal>,
+    by_day: BTreeMap<(String, Address), PnlTotal>,
+    /// Running realized profit per quote token
+    realized: BTreeMap<Address, f64>,
+}
+
+impl LedgerState {
+    fn apply(&mut self, record: TradeRecord) {
+        self.by_pair.entry((record.base, record.quote)).or_default().add(&record);
+        self.by_dexes
+            .entry((record.buy_factory, record.sell_factory, record.quote))
+            .or_default()
+            .add(&record);
+        self.by_day.entry((utc_day(record.at), record.quote)).or_default().add(&record);
+        *self.realized.entry(record.quote).or_default() += record.realized_profit;
+
+        if self.recent.len() == RECENT_OPPORTUNITIES_LEN {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(record);
+    }
+
+    fn export_realized(&self, quote: Address) {
+        let realized = self.realized.get(&quote).copied().unwrap_or_default();
+        METRICS.realized_profit.with_label_values(&[&format!("{:?}", quote)]).set(realized);
+    }
+}
+
+/// Realized trades and their cumulative PnL per pair, per DEX combination
+/// and per day. Every record is appended to a JSON-lines file, which is
+/// replayed on startup to restore the totals.
+#[derive(Default)]
+pub struct PnlLedger {
+    path: Option<PathBuf>,
+    state: Mutex<LedgerState>,
+}
+
+impl PnlLedger {
+    /// Ledger kept in memory only
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ledger backed by `path`, replaying whatever it already holds
+    pub fn open(path: PathBuf) -> anyhow::Result<Self> {
+        let mut state = LedgerState::default();
+        if path.exists() {
+            let file = std::fs::File::open(&path)
+                .map_err(|e| anyhow::anyhow!("Failed to open PnL ledger {:?}: {:?}", path, e))?;
+            for (i, line) in BufReader::new(file).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str(&line) {
+                    Ok(record) => state.apply(record),
+                    Err(e) => tracing::warn!("Skipping unreadable PnL ledger line {} of {:?}: {:?}", i + 1, path, e),
+                }
+            }
+        }
+        for quote in state.realized.keys() {
+            state.export_realized(*quote);
+        }
+
+        Ok(Self { path: Some(path), state: Mutex::new(state) })
+    }
+
+    /// `PNL_LEDGER_PATH`, default `DEFAULT_PNL_LEDGER_PATH`; empty keeps
+    /// the ledger in memory
+    pub fn from_env() -> anyhow::Result<Self> {
+        let path = std::env::var("PNL_LEDGER_PATH").unwrap_or_else(|_| DEFAULT_PNL_LEDGER_PATH.to_string());
+        if path.trim().is_empty() {
+            return Ok(Self::new());
+        }
+        Self::open(PathBuf::from(path.trim()))
+    }
+
+    pub fn record(&self, record: TradeRecord) {
+        if let Some(path) = &self.path
+            && let Err(e) = append_line(path, &record)
+        {
+            tracing::warn!("Failed to append trade to PnL ledger {:?}: {:?}", path, e);
+        }
+        store::record(StoreEvent::Trade(record.clone()));
+
+        let quote = record.quote;
+        let mut state = self.state.lock().expect("pnl ledger lock poisoned");
+        state.apply(record);
+        state.export_realized(quote);
+    }
+
+    pub fn summary(&self) -> PnlSummary {
+        let state = self.state.lock().expect("pnl ledger lock poisoned");
+        PnlSummary {
+            pairs: state
+                .by_pair
+                .iter()
+                .map(|(&(base, quote), total)| PairPnl { base, quote, total: total.clone() })
+                .collect(),
+            dexes: state
+                .by_dexes
+                .iter()
+                .map(|(&(buy_factory, sell_factory, quote), total)| DexPnl {
+                    buy_factory,
+                    sell_factory,
+                    quote,
+                    total: total.clone(),
+                })
+                .collect(),
+            days: state
+                .by_day
+                .iter()
+                .map(|((day, quote), total)| DayPnl { day: day.clone(), quote: *quote, total: total.clone() })
+                .collect(),
+            trades: state.recent.iter().rev().cloned().collect(),
+        }
+    }
+}
+
+fn append_line(path: &PathBuf, record: &TradeRecord) -> anyhow::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// Follow a sent trade's transactions, through any fee bumps, until each
+/// is mined or dropped, then net their `Transfer` logs and gas into the
+/// ledger. Runs in the background.
+pub fn track_trade<M: Middleware + 'static>(ctx: &ExecutionContext<M>, hashes: Vec<TxHash>, trade: OpenTrade) {
+    let connection = ctx.connection.clone();
+    let nonces = ctx.nonces.clone();
+    let tracker = ctx.tracker.clone();
+    let ledger = ctx.pnl.clone();
+
+    tokio::spawn(async move {
+        match settle_trade(connection, &nonces, &tracker, hashes, trade).await {
+            Ok(record) => {
+                tracing::info!(
+                    "💰 Trade settled {:?}: realized {:.6} vs expected {:.6} {:?} (gas {} wei)",
+                    record.status,
+                    record.realized_profit,
+                    record.expected_profit,
+                    record.quote,
+                    record.gas_cost_wei
+                );
+                ledger.record(record);
+            }
+            Err(e) => tracing::warn!("Failed to settle trade: {:?}", e),
+        }
+    });
+}
+
+async fn settle_trade<M: Middleware + 'static>(
+    connection: Arc<ConnectionManager<M>>,
+    nonces: &NonceManager,
+    tracker: &PriceTracker,
+    hashes: Vec<TxHash>,
+    trade: OpenTrade,
+) -> anyhow::Result<TradeRecord> {
+    let provider = connection.provider();
+    let mut ours: Vec<Address> = provider.default_sender().into_iter().collect();
+    ours.extend(arb_contract());
+
+    let mut deltas: HashMap<Address, I256> = HashMap::new();
+    let (mut gas_used, mut gas_cost_wei) = (U256::zero(), U256::zero());
+    let mut status = TradeStatus::Succeeded;
+    let mut block = None;
+    let mut mined = Vec::with_capacity(hashes.len());
+
+    for hash in hashes {
+        let Some((receipt, cancelled)) = wait_for_receipt(&connection, nonces, hash).await else {
+            status = TradeStatus::Dropped;
+            continue;
+        };
+        if cancelled && status == TradeStatus::Succeeded {
+            status = TradeStatus::Cancelled;
+        }
+
+        let used = receipt.gas_used.unwrap_or_default();
+        gas_used += used;
+        gas_cost_wei += used * receipt.effective_gas_price.unwrap_or_default();
+        block = receipt.block_number.map(|b| b.as_u64());
+        if receipt.status != Some(U64::one()) && status == TradeStatus::Succeeded {
+            status = TradeStatus::Reverted;
+        }
+        for (token, delta) in transfer_deltas(&receipt.logs, &ours) {
+            *deltas.entry(token).or_insert_with(I256::zero) += delta;
+        }
+        mined.push(receipt.transaction_hash);
+    }
+
+    let (base, quote) = (&trade.pair.base, &trade.pair.quote);
+    let quote_delta = signed_to_f64(deltas.get(&quote.id).copied().unwrap_or_default(), quote.decimals)?;
+    let base_delta = signed_to_f64(deltas.get(&base.id).copied().unwrap_or_default(), base.decimals)?;
+
+    // Base left over (inventory legs that didn't both land) at the tracked price
+    let base_value = if base_delta == 0.0 {
+        0.0
+    } else {
+        match tracker.get_all_for_pair(base.id, quote.id).await {
+            Ok(prices) if !prices.is_empty() => {
+                base_delta * prices.iter().map(|(_, info)| info.price).sum::<f64>() / prices.len() as f64
+            }
+            _ => {
+                tracing::warn!("No tracked price for {:?}/{:?}; base delta left out of PnL", base.id, quote.id);
+                0.0
+            }
+        }
+    };
+
+    // Native per whole quote unit, inverted to value the gas
+    let unit = U256::exp10(quote.decimals);
+    let gas_cost_quote = match native_value(tracker, quote, unit).await {
+        Some(native) if !native.is_zero() => {
+            Some(to_f64_normalized(gas_cost_wei, 18)? / to_f64_normalized(native, 18)?)
+        }
+        _ => None,
+    };
+
+    Ok(TradeRecord {
+        at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        hashes: mined,
+        base: base.id,
+        quote: quote.id,
+        buy_factory: trade.buy_factory,
+        sell_factory: trade.sell_factory,
+        expected_profit: to_f64_normalized(trade.expected_profit, quote.decimals)?,
+        quote_delta,
+        base_delta,
+        gas_used,
+        gas_cost_wei,
+        gas_cost_quote,
+        realized_profit: quote_delta + base_value - gas_cost_quote.unwrap_or_default(),
+        status,
+        block,
+    })
+}
+
+/// Receipt of whichever transaction sent at `hash`'s nonce was mined, the
+/// original or any fee bump or cancellation, and whether it was a
+/// cancellation. `None` once the nonce is taken by none of them, or once
+/// the node hasn't known any of them for `DROPPED_TX_GRACE_SECS`; lookups
+/// that keep failing count toward the same grace period.
+async fn wait_for_receipt<M: Middleware + 'static>(
+    connection: &ConnectionManager<M>,
+    nonces: &NonceManager,
+    hash: TxHash,
+) -> Option<(TransactionReceipt, bool)> {
+    let mut ticker = tokio::time::interval(Duration::from_millis(IN_FLIGHT_POLL_INTERVAL_MS));
+    let mut attempts = NonceAttempts { hashes: vec![hash], ..Default::default() };
+    let mut nonce = None;
+    let mut unseen_since = Instant::now();
+
+    loop {
+        ticker.tick().await;
+        let provider = connection.provider();
+
+        // Merge rather than replace: the manager forgets old nonces eventually
+        if let Some(known) = nonces.attempts(hash) {
+            nonce = Some(known.nonce);
+            for h in known.hashes {
+                if !attempts.hashes.contains(&h) {
+                    attempts.hashes.push(h);
+                }
+            }
+            for h in known.cancellations {
+                if !attempts.cancellations.contains(&h) {
+                    attempts.cancellations.push(h);
+                }
+            }
+        }
+
+        // Read the chain nonce before the receipts, so a nonce seen taken
+        // with no receipt of ours can't be one that landed in between
+        let consumed = match (nonce, provider.default_sender()) {
+            (Some(nonce), Some(from)) => provider
+                .get_transaction_count(from, Some(BlockNumber::Latest.into()))
+                .await
+                .is_ok_and(|mined| mined > nonce),
+            _ => false,
+        };
+
+        let mut receipts_missing = true;
+        for h in &attempts.hashes {
+            match provider.get_transaction_receipt(*h).await {
+                Ok(Some(receipt)) => return Some((receipt, attempts.cancellations.contains(h))),
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::debug!("Receipt lookup for {:?} failed: {:?}", h, e);
+                    receipts_missing = false;
+                }
+            }
+        }
+
+        if consumed && receipts_missing {
+            return None;
+        }
+
+        // Neither mined nor replaced: a node that doesn't return a tx it
+        // only just accepted doesn't mean it's gone, so give it a grace
+        // period to have seen one of them before calling the nonce dropped
+        let mut seen = false;
+        for h in &attempts.hashes {
+            match provider.get_transaction(*h).await {
+                Ok(Some(_)) => {
+                    seen = true;
+                    break;
+                }
+                Ok(None) => {}
+                Err(e) => tracing::debug!("Transaction lookup for {:?} failed: {:?}", h, e),
+            }
+        }
+        if seen {
+            unseen_since = Instant::now();
+        } else if unseen_since.elapsed() >= Duration::from_secs(DROPPED_TX_GRACE_SECS) {
+            return None;
+        }
+    }
+}
+
+/// Net amount of each token moved into `ours`, from `Transfer` logs.
+/// Transfers among `ours` cancel out.
+fn transfer_deltas(logs: &[Log], ours: &[Address]) -> HashMap<Address, I256> {
+    let mut deltas: HashMap<Address, I256> = HashMap::new();
+
+    for log in logs {
+        if log.topics.len() != 3 || log.topics[0] != *TRANSFER_TOPIC || log.data.len() < 32 {
+            continue;
+        }
+        let from = Address::from(log.topics[1]);
+        let to = Address::from(log.topics[2]);
+        let amount = I256::from_raw(U256::from_big_endian(&log.data[..32]));
+
+        let delta = deltas.entry(log.address).or_insert_with(I256::zero);
+        if ours.contains(&to) {
+            *delta += amount;
+        }
+        if ours.contains(&from) {
+            *delta -= amount;
+        }
+    }
+
+    deltas
+}
+
+fn signed_to_f64(amount: I256, decimals: usize) -> anyhow::Result<f64> {
+    let (sign, abs) = amount.into_sign_and_abs();
+    let value = to_f64_normalized(abs, decimals)?;
+    Ok(if sign.is_negative() { -value } else { value })
+}
+
+/// `YYYY-MM-DD` of a unix timestamp, UTC
+fn utc_day(secs: u64) -> String {
+    // Days to civil date, after Howard Hinnant's `civil_from_days`
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer(token: Address, from: Address, to: Address, amount: u64) -> Log {
+        let mut data = [0u8; 32];
+        U256::from(amount).to_big_endian(&mut data);
+        Log {
+            address: token,
+            topics: vec![*TRANSFER_TOPIC, H256::from(from), H256::from(to)],
+            data: data.to_vec().into(),
+            ..Default::default()
+        }
+    }
+
+    fn record(quote: Address, at: u64, realized_profit: f64, status: TradeStatus) -> TradeRecord {
+        TradeRecord {
+            at,
+            hashes: vec![H256::repeat_byte(0x01)],
+            base: Address::repeat_byte(0xb0),
+            quote,
+            buy_factory: Address::repeat_byte(0xf1),
+            sell_factory: Address::repeat_byte(0xf2),
+            expected_profit: 1.0,
+            quote_delta: realized_profit,
+            base_delta: 0.0,
+            gas_used: U256::from(100_000u64),
+            gas_cost_wei: U256::from(1_000u64),
+            gas_cost_quote: Some(0.0),
+            realized_profit,
+            status,
+            block: Some(1),
+        }
+    }
+
+    #[test]
+    fn transfer_deltas_net_what_moves_in_and_out() {
+        let (token, other) = (Address::repeat_byte(0x70), Address::repeat_byte(0x71));
+        let (wallet, contract, pool) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xac), Address::repeat_byte(0x99));
+
+        let logs = vec![
+            transfer(token, wallet, pool, 1_000),
+            transfer(token, pool, contract, 1_250),
+            // Among ours: cancels out
+            transfer(other, wallet, contract, 500),
+        ];
+        let deltas = transfer_deltas(&logs, &[wallet, contract]);
+
+        assert_eq!(deltas[&token], I256::from(250));
+        assert_eq!(deltas[&other], I256::zero());
+    }
+
+    #[test]
+    fn transfer_deltas_skip_other_events() {
+        let ours = Address::repeat_byte(0xaa);
+        let mut approval = transfer(Address::repeat_byte(0x70), ours, Address::repeat_byte(0x99), 1);
+        approval.topics[0] = H256::repeat_byte(0x8c);
+        let mut truncated = transfer(Address::repeat_byte(0x70), Address::repeat_byte(0x99), ours, 1);
+        truncated.data = vec![0u8; 16].into();
+
+        assert!(transfer_deltas(&[approval, truncated], &[ours]).is_empty());
+    }
+
+    #[test]
+    fn utc_day_handles_epoch_leap_days_and_day_ends() {
+        assert_eq!(utc_day(0), "1970-01-01");
+        assert_eq!(utc_day(951_782_400), "2000-02-29");
+        assert_eq!(utc_day(1_709_251_199), "2024-02-29");
+        assert_eq!(utc_day(1_709_251_200), "2024-03-01");
+        assert_eq!(utc_day(1_767_225_599), "2025-12-31");
+    }
+
+    #[test]
+    fn ledger_replays_its_file_per_quote() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pnl.jsonl");
+        let (usdc, weth) = (Address::repeat_byte(0xc1), Address::repeat_byte(0xc2));
+
+        {
+            let ledger = PnlLedger::open(path.clone()).unwrap();
+            ledger.record(record(usdc, 1_709_251_199, 2.5, TradeStatus::Succeeded));
+            ledger.record(record(usdc, 1_709_251_200, -0.5, TradeStatus::Reverted));
+            ledger.record(record(weth, 1_709_251_200, 0.01, TradeStatus::Succeeded));
+        }
+        // A torn write at the end is skipped, not fatal
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"at\":").unwrap();
+
+        let ledger = PnlLedger::open(path).unwrap();
+        let state = ledger.state.lock().unwrap();
+        assert_eq!(state.realized[&usdc], 2.0);
+        assert_eq!(state.realized[&weth], 0.01);
+        drop(state);
+
+        let summary = ledger.summary();
+        let usdc_pair = summary.pairs.iter().find(|p| p.quote == usdc).unwrap();
+        assert_eq!((usdc_pair.total.trades, usdc_pair.total.failed), (2, 1));
+        let days: Vec<(&str, Address)> = summary.days.iter().map(|d| (d.day.as_str(), d.quote)).collect();
+        assert_eq!(days, vec![("2024-02-29", usdc), ("2024-03-01", usdc), ("2024-03-01", weth)]);
+        assert_eq!(summary.trades.len(), 3);
+        assert_eq!(summary.trades[0].quote, weth);
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    fmt,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use ethers::{
+    providers::{
+        Http, HttpClientError, JsonRpcClient, JsonRpcError, MockProvider, Provider, ProviderError, PubsubClient,
+        RpcError, Ws, WsClientError,
+    },
+    types::U256,
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    constants::{DEFAULT_RPC_CONNECT_TIMEOUT_MS, DEFAULT_RPC_REQUEST_TIMEOUT_MS},
+    metrics::METRICS,
+    rpc_pool::RpcEndpoint,
+};
+
+/// Timeouts applied to every provider the factory builds
+#[derive(Debug, Clone, Copy)]
+pub struct ProviderConfig {
+    /// Upper bound on establishing a WS connection
+    pub connect_timeout: Duration,
+    /// Upper bound on a single request, HTTP or WS
+    pub request_timeout: Duration,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_millis(DEFAULT_RPC_CONNECT_TIMEOUT_MS),
+            request_timeout: Duration::from_millis(DEFAULT_RPC_REQUEST_TIMEOUT_MS),
+        }
+    }
+}
+
+/// Builds providers on the caller's runtime.
+///
+/// Created once in `main` and passed down; nothing reaches a provider
+/// through a global.
+#[derive(Debug, Clone, Default)]
+pub struct ProviderFactory {
+    config: ProviderConfig,
+}
+
+impl ProviderFactory {
+    pub fn new(config: ProviderConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    /// Connect a websocket provider, failing after `connect_timeout`, whose
+    /// requests time out after `request_timeout`
+    pub async fn ws(&self, url: &str) -> anyhow::Result<Provider<TimedWs>> {
+        let endpoint = RpcEndpoint::parse(url)?.label();
+        let ws = tokio::time::timeout(self.config.connect_timeout, Ws::connect(url))
+            .await
+            .map_err(|_| anyhow::anyhow!("Timed out connecting to {}", endpoint))??;
+
+        Ok(Provider::new(TimedWs { inner: ws, timeout: self.config.request_timeout, endpoint }))
+    }
+
+    /// HTTP provider whose requests time out after `request_timeout`
+    pub fn http(&self, url: &str) -> anyhow::Result<Provider<MeteredHttp>> {
+        let endpoint = RpcEndpoint::parse(url)?.label();
+        let client = reqwest::Client::builder()
+            .timeout(self.config.request_timeout)
+            .build()?;
+
+        let inner = Http::new_with_client(url.parse::<reqwest::Url>()?, client);
+        Ok(Provider::new(MeteredHttp { inner, endpoint }))
+    }
+
+    /// Provider backed by a `MockProvider`, whose handle is returned to
+    /// queue responses
+    pub fn mock(&self) -> (Provider<MockProvider>, MockProvider) {
+        Provider::mocked()
+    }
+}
+
+/// Count, time and (on failure) tally one request against `endpoint`
+async fn metered<R, E>(endpoint: &str, method: &str, request: impl Future<Output = Result<R, E>>) -> Result<R, E> {
+    METRICS.rpc_calls.with_label_values(&[endpoint, method]).inc();
+
+    let started = Instant::now();
+    let res = request.await;
+    METRICS.rpc_latency_seconds.with_label_values(&[method]).observe(started.elapsed().as_secs_f64());
+    if res.is_err() {
+        METRICS.rpc_errors.with_label_values(&[endpoint, method]).inc();
+    }
+    res
+}
+
+/// Websocket transport with a per-request timeout; `Ws` alone waits for a
+/// reply forever. Every request is metered; subscriptions are passed
+/// through untouched.
+#[derive(Debug, Clone)]
+pub struct TimedWs {
+    inner: Ws,
+    timeout: Duration,
+    /// Endpoint label for metrics, without path or credentials
+    endpoint: String,
+}
+
+#[derive(Debug)]
+pub enum TimedWsError {
+    Ws(WsClientError),
+    Timeout { method: String, after: Duration },
+}
+
+impl fmt::Display for TimedWsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ws(e) => write!(f, "{}", e),
+            Self::Timeout { method, after } => write!(f, "{} timed out after {:?}", method, after),
+        }
+    }
+}
+
+impl std::error::Error for TimedWsError {}
+
+impl RpcError for TimedWsError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            Self::Ws(e) => e.as_error_response(),
+            Self::Timeout { .. } => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            Self::Ws(e) => e.as_serde_error(),
+            Self::Timeout { .. } => None,
+        }
+    }
+}
+
+impl From<TimedWsError> for ProviderError {
+    fn from(e: TimedWsError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(e))
+    }
+}
+
+#[async_trait::async_trait]
+impl JsonRpcClient for TimedWs {
+    type Error = TimedWsError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: fmt::Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        metered(&self.endpoint, method, async {
+            tokio::time::timeout(self.timeout, self.inner.request(method, params))
+                .await
+                .map_err(|_| TimedWsError::Timeout { method: method.to_string(), after: self.timeout })?
+                .map_err(TimedWsError::Ws)
+        })
+        .await
+    }
+}
+
+impl PubsubClient for TimedWs {
+    type NotificationStream = <Ws as PubsubClient>::NotificationStream;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        self.inner.subscribe(id).map_err(TimedWsError::Ws)
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), Self::Error> {
+        self.inner.unsubscribe(id).map_err(TimedWsError::Ws)
+    }
+}
+
+/// HTTP transport that meters every request, like `TimedWs`
+#[derive(Debug, Clone)]
+pub struct MeteredHttp {
+    inner: Http,
+    /// Endpoint label for metrics, without path or credentials
+    endpoint: String,
+}
+
+#[async_trait::async_trait]
+impl JsonRpcClient for MeteredHttp {
+    type Error = HttpClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: fmt::Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        metered(&self.endpoint, method, self.inner.request(method, params)).await
+    }
+}
@@ -0,0 +1,449 @@
+//! Append-only recording of everything ingestion receives, for replaying
+//! a production session exactly.
+//!
+//! A recording file starts with [`MAGIC`], then holds one frame per event:
+//!
+//! ```text
+//! kind: u8 | recorded_at_ms: u64 | len: u32 | payload: [u8; len]
+//! ```
+//!
+//! Integers are little-endian. A block payload is its header fields; a log
+//! payload is the raw log (address, topics, data and its chain position), so
+//! Swap, Sync and any other pool event replay byte for byte.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}},
+    time::{Duration, Instant},
+};
+
+use ethers::{
+    providers::Middleware,
+    types::{Address, Bytes, H256, Log, U64, U256},
+};
+use once_cell::sync::OnceCell;
+
+use crate::{
+    block_watcher::BlockWatcher,
+    constants::{DEFAULT_RECORD_MAX_BYTES, DEFAULT_RECORD_ROTATE_SECS},
+    dex_price_listener::multiplexer::LogMultiplexer,
+    helpers::env_u64,
+    types::BlockInfo,
+};
+
+/// First bytes of every recording file; the last byte is the format version
+pub const MAGIC: &[u8; 8] = b"ARBREC\n\x02";
+
+const KIND_BLOCK: u8 = 1;
+const KIND_LOG: u8 = 2;
+
+/// The process-wide recorder, when `RECORD_DIR` is set
+pub static RECORDER: OnceCell<EventRecorder> = OnceCell::new();
+
+/// One recorded event
+#[derive(Debug, Clone)]
+pub enum RecordedEvent {
+    /// A header handed to the block watcher
+    Block(BlockInfo),
+    /// A log handed to the multiplexer, `removed` ones included
+    Log(Log),
+}
+
+struct Segment {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    bytes: u64,
+    opened: Instant,
+}
+
+/// Writes every event to a segment file in `dir`, starting a new one once
+/// the current one reaches `max_bytes` or `rotate_every`. Segment names
+/// sort in recording order.
+pub struct EventRecorder {
+    dir: PathBuf,
+    max_bytes: u64,
+    rotate_every: Duration,
+    segment: Mutex<Option<Segment>>,
+    /// Segments opened by this process, so names never collide
+    segments: AtomicU64,
+}
+
+impl EventRecorder {
+    pub fn new(dir: PathBuf, max_bytes: u64, rotate_every: Duration) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| anyhow::anyhow!("Failed to create recording dir {:?}: {:?}", dir, e))?;
+        Ok(Self {
+            dir,
+            max_bytes,
+            rotate_every,
+            segment: Mutex::new(None),
+            segments: AtomicU64::new(0),
+        })
+    }
+
+    /// `RECORD_DIR` (unset or empty disables), `RECORD_MAX_BYTES` and
+    /// `RECORD_ROTATE_SECS`. Installs the recorder as [`RECORDER`].
+    pub fn init_from_env() -> anyhow::Result<Option<&'static EventRecorder>> {
+        let dir = std::env::var("RECORD_DIR").unwrap_or_default();
+        if dir.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let recorder = Self::new(
+            PathBuf::from(dir.trim()),
+            env_u64("RECORD_MAX_BYTES", DEFAULT_RECORD_MAX_BYTES),
+            Duration::from_secs(env_u64("RECORD_ROTATE_SECS", DEFAULT_RECORD_ROTATE_SECS)),
+        )?;
+        if RECORDER.set(recorder).is_err() {
+            anyhow::bail!("Recorder already initialized");
+        }
+        tracing::info!("⏺️ Recording ingested events to {}", dir.trim());
+        Ok(RECORDER.get())
+    }
+
+    pub fn record(&self, event: &RecordedEvent) {
+        let (kind, payload) = match event {
+            RecordedEvent::Block(block) => (KIND_BLOCK, encode_block(block)),
+            RecordedEvent::Log(log) => (KIND_LOG, encode_log(log)),
+        };
+        if let Err(e) = self.write_frame(kind, &payload) {
+            tracing::warn!("Failed to record event: {:?}", e);
+        }
+    }
+
+    fn write_frame(&self, kind: u8, payload: &[u8]) -> anyhow::Result<()> {
+        let mut segment = self.segment.lock().expect("recorder lock poisoned");
+
+        let full = segment
+            .as_ref()
+            .is_some_and(|s| s.bytes >= self.max_bytes || s.opened.elapsed() >= self.rotate_every);
+        if full && let Some(mut old) = segment.take() {
+            old.writer.flush()?;
+            tracing::info!("⏺️ Rotated recording {:?} at {} bytes", old.path, old.bytes);
+        }
+        if segment.is_none() {
+            *segment = Some(self.open_segment()?);
+        }
+        let segment = segment.as_mut().expect("segment just opened");
+
+        let at_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        segment.writer.write_all(&[kind])?;
+        segment.writer.write_all(&at_ms.to_le_bytes())?;
+        segment.writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+        segment.writer.write_all(payload)?;
+        segment.bytes += 13 + payload.len() as u64;
+        Ok(())
+    }
+
+    fn open_segment(&self) -> anyhow::Result<Segment> {
+        let started = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        let seq = self.segments.fetch_add(1, Ordering::Relaxed);
+        let path = self.dir.join(format!("events-{:016}-{:06}.rec", started, seq));
+        let file = OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(&path)
+            .map_err(|e| anyhow::anyhow!("Failed to create recording {:?}: {:?}", path, e))?;
+
+        let mut writer = BufWriter::with_capacity(64 * 1024, file);
+        writer.write_all(MAGIC)?;
+        Ok(Segment { writer, path, bytes: MAGIC.len() as u64, opened: Instant::now() })
+    }
+
+    /// Push buffered frames to disk
+    pub fn flush(&self) -> anyhow::Result<()> {
+        if let Some(segment) = self.segment.lock().expect("recorder lock poisoned").as_mut() {
+            segment.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Record `event` with the global recorder, if one is running
+pub fn record(event: impl FnOnce() -> RecordedEvent) {
+    if let Some(recorder) = RECORDER.get() {
+        recorder.record(&event());
+    }
+}
+
+/// Reads back one recording file
+pub struct RecordingReader {
+    reader: BufReader<File>,
+}
+
+impl RecordingReader {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut reader = BufReader::new(
+            File::open(path).map_err(|e| anyhow::anyhow!("Failed to open recording {:?}: {:?}", path, e))?,
+        );
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            anyhow::bail!("{:?} is not a recording (or a newer format)", path);
+        }
+        Ok(Self { reader })
+    }
+
+    /// Next event and when it was recorded (unix ms). `None` at the end; a
+    /// frame cut short by a crash also ends the file.
+    pub fn next_event(&mut self) -> anyhow::Result<Option<(u64, RecordedEvent)>> {
+        let mut head = [0u8; 13];
+        match self.reader.read_exact(&mut head) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let kind = head[0];
+        let at_ms = u64::from_le_bytes(head[1..9].try_into()?);
+        let len = u32::from_le_bytes(head[9..13].try_into()?) as usize;
+
+        let mut payload = vec![0u8; len];
+        match self.reader.read_exact(&mut payload) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut cursor = Cursor { buf: &payload };
+        let event = match kind {
+            KIND_BLOCK => RecordedEvent::Block(decode_block(&mut cursor)?),
+            KIND_LOG => RecordedEvent::Log(decode_log(&mut cursor)?),
+            other => anyhow::bail!("Unknown recorded event kind {}", other),
+        };
+        Ok(Some((at_ms, event)))
+    }
+}
+
+/// Recording files in `dir`, in recording order
+pub fn recordings_in(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to list recordings in {:?}: {:?}", dir, e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "rec"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Feed every recording in `dir` back through the block watcher and log
+/// multiplexer, in the order it was received. With `paced`, the original
+/// gaps between events are kept; otherwise it runs as fast as it can.
+pub async fn replay<M: Middleware + 'static>(
+    dir: &Path,
+    provider: Arc<M>,
+    block_watcher: &BlockWatcher,
+    multiplexer: &LogMultiplexer<M>,
+    paced: bool,
+) -> anyhow::Result<()> {
+    // Replayed events go through the same paths that record live ones
+    if RECORDER.get().is_some() {
+        anyhow::bail!("Refusing to replay while recording; unset RECORD_DIR");
+    }
+    let (mut blocks, mut logs) = (0u64, 0u64);
+    let mut last_at: Option<u64> = None;
+
+    for path in recordings_in(dir)? {
+        tracing::info!("⏯️ Replaying {:?}", path);
+        let mut reader = RecordingReader::open(&path)?;
+
+        while let Some((at_ms, event)) = reader.next_event()? {
+            if paced && let Some(last) = last_at {
+                tokio::time::sleep(Duration::from_millis(at_ms.saturating_sub(last))).await;
+            }
+            last_at = Some(at_ms);
+
+            match event {
+                RecordedEvent::Block(header) => {
+                    blocks += 1;
+                    if let Err(e) = block_watcher.ingest(provider.clone(), header, multiplexer).await {
+                        tracing::warn!("Replayed block failed: {:?}", e);
+                    }
+                }
+                RecordedEvent::Log(log) => {
+                    logs += 1;
+                    if let Err(e) = multiplexer.route(&log, provider.clone()).await {
+                        tracing::warn!("Replayed log failed: {:?}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    tracing::info!("⏹️ Replay complete: {} blocks, {} logs", blocks, logs);
+    Ok(())
+}
+
+fn encode_block(block: &BlockInfo) -> Vec<u8> {
+    let mut out = Vec::with_capacity(8 + 32 + 32 + 8 + 33 + 32);
+    out.extend_from_slice(&block.number.to_le_bytes());
+    out.extend_from_slice(block.hash.as_bytes());
+    out.extend_from_slice(block.parent_hash.as_bytes());
+    out.extend_from_slice(&block.timestamp.to_le_bytes());
+    put_opt_u256(&mut out, block.base_fee);
+    out.extend_from_slice(&u256_bytes(block.gas_limit));
+    out
+}
+
+fn decode_block(cursor: &mut Cursor) -> anyhow::Result<BlockInfo> {
+    Ok(BlockInfo {
+        number: cursor.u64()?,
+        hash: cursor.h256()?,
+        parent_hash: cursor.h256()?,
+        timestamp: cursor.u64()?,
+        base_fee: cursor.opt(|c| c.u256())?,
+        gas_limit: cursor.u256()?,
+    })
+}
+
+fn encode_log(log: &Log) -> Vec<u8> {
+    let mut out = Vec::with_capacity(20 + 1 + 32 * log.topics.len() + 4 + log.data.len() + 128);
+    out.extend_from_slice(log.address.as_bytes());
+    out.push(log.topics.len() as u8);
+    for topic in &log.topics {
+        out.extend_from_slice(topic.as_bytes());
+    }
+    out.extend_from_slice(&(log.data.len() as u32).to_le_bytes());
+    out.extend_from_slice(&log.data);
+
+    put_opt(&mut out, log.block_hash, |out, h| out.extend_from_slice(h.as_bytes()));
+    put_opt(&mut out, log.block_number, |out, n| out.extend_from_slice(&n.as_u64().to_le_bytes()));
+    put_opt(&mut out, log.transaction_hash, |out, h| out.extend_from_slice(h.as_bytes()));
+    put_opt(&mut out, log.transaction_index, |out, i| out.extend_from_slice(&i.as_u64().to_le_bytes()));
+    put_opt(&mut out, log.log_index, |out, i| out.extend_from_slice(&u256_bytes(i)));
+    put_opt(&mut out, log.transaction_log_index, |out, i| out.extend_from_slice(&u256_bytes(i)));
+    put_opt(&mut out, log.log_type.as_ref(), |out, t| {
+        out.extend_from_slice(&(t.len() as u32).to_le_bytes());
+        out.extend_from_slice(t.as_bytes());
+    });
+    put_opt(&mut out, log.removed, |out, r| out.push(r as u8));
+    out
+}
+
+fn decode_log(cursor: &mut Cursor) -> anyhow::Result<Log> {
+    let address = Address::from_slice(cursor.take(20)?);
+    let topic_count = cursor.take(1)?[0] as usize;
+    let topics = (0..topic_count).map(|_| cursor.h256()).collect::<anyhow::Result<Vec<_>>>()?;
+    let data_len = u32::from_le_bytes(cursor.take(4)?.try_into()?) as usize;
+    let data = Bytes::from(cursor.take(data_len)?.to_vec());
+
+    Ok(Log {
+        address,
+        topics,
+        data,
+        block_hash: cursor.opt(|c| c.h256())?,
+        block_number: cursor.opt(|c| c.u64().map(U64::from))?,
+        transaction_hash: cursor.opt(|c| c.h256())?,
+        transaction_index: cursor.opt(|c| c.u64().map(U64::from))?,
+        log_index: cursor.opt(|c| c.u256())?,
+        transaction_log_index: cursor.opt(|c| c.u256())?,
+        log_type: cursor.opt(|c| {
+            let len = u32::from_le_bytes(c.take(4)?.try_into()?) as usize;
+            Ok(String::from_utf8(c.take(len)?.to_vec())?)
+        })?,
+        removed: cursor.opt(|c| Ok(c.take(1)?[0] != 0))?,
+    })
+}
+
+fn u256_bytes(value: U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes
+}
+
+fn put_opt<T>(out: &mut Vec<u8>, value: Option<T>, put: impl FnOnce(&mut Vec<u8>, T)) {
+    match value {
+        Some(value) => {
+            out.push(1);
+            put(out, value);
+        }
+        None => out.push(0),
+    }
+}
+
+fn put_opt_u256(out: &mut Vec<u8>, value: Option<U256>) {
+    put_opt(out, value, |out, v| out.extend_from_slice(&u256_bytes(v)));
+}
+
+struct Cursor<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        if self.buf.len() < n {
+            anyhow::bail!("Recorded frame truncated");
+        }
+        let (head, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(head)
+    }
+
+    fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn h256(&mut self) -> anyhow::Result<H256> {
+        Ok(H256::from_slice(self.take(32)?))
+    }
+
+    fn u256(&mut self) -> anyhow::Result<U256> {
+        Ok(U256::from_big_endian(self.take(32)?))
+    }
+
+    fn opt<T>(&mut self, read: impl FnOnce(&mut Self) -> anyhow::Result<T>) -> anyhow::Result<Option<T>> {
+        match self.take(1)?[0] {
+            0 => Ok(None),
+            _ => read(self).map(Some),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_round_trips() {
+        let block = BlockInfo {
+            number: 65_000_123,
+            hash: H256::repeat_byte(0x11),
+            parent_hash: H256::repeat_byte(0x22),
+            timestamp: 1_760_000_000,
+            base_fee: Some(U256::from(31_000_000_000u64)),
+            gas_limit: U256::from(30_000_000u64),
+        };
+
+        let encoded = encode_block(&block);
+        let decoded = decode_block(&mut Cursor { buf: &encoded }).unwrap();
+        assert_eq!(decoded, block);
+    }
+
+    #[test]
+    fn log_round_trips_with_every_field_set() {
+        let log = Log {
+            address: Address::repeat_byte(0x30),
+            topics: vec![H256::repeat_byte(0x01), H256::repeat_byte(0x02), H256::repeat_byte(0x03)],
+            data: Bytes::from(vec![0xab; 64]),
+            block_hash: Some(H256::repeat_byte(0x11)),
+            block_number: Some(U64::from(65_000_123u64)),
+            transaction_hash: Some(H256::repeat_byte(0x44)),
+            transaction_index: Some(U64::from(7u64)),
+            log_index: Some(U256::from(42u64)),
+            transaction_log_index: Some(U256::from(3u64)),
+            log_type: Some("mined".to_string()),
+            removed: Some(true),
+        };
+
+        let encoded = encode_log(&log);
+        let mut cursor = Cursor { buf: &encoded };
+        let decoded = decode_log(&mut cursor).unwrap();
+        assert_eq!(decoded, log);
+        assert!(cursor.buf.is_empty());
+    }
+}
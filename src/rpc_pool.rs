@@ -0,0 +1,405 @@
+use std::{
+    sync::{Arc, atomic::{AtomicU64, AtomicUsize, Ordering}},
+    time::Instant,
+};
+
+use ethers::{
+    providers::{Middleware, Provider},
+    types::{Address, Block, BlockId, BlockNumber, Bytes, Filter, H256, Log, TransactionRequest, U64, transaction::eip2718::TypedTransaction},
+};
+use futures::future::{join_all, select_ok};
+
+use crate::provider_factory::{MeteredHttp, ProviderFactory, TimedWs};
+
+/// Transport of a configured RPC endpoint, inferred from the URL scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointKind {
+    Ws,
+    Http,
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcEndpoint {
+    pub url: String,
+    pub kind: EndpointKind,
+}
+
+impl RpcEndpoint {
+    pub fn parse(url: &str) -> anyhow::Result<Self> {
+        let url = url.trim().to_string();
+        let kind = if url.starts_with("ws://") || url.starts_with("wss://") {
+            EndpointKind::Ws
+        } else if url.starts_with("http://") || url.starts_with("https://") {
+            EndpointKind::Http
+        } else {
+            anyhow::bail!("Unsupported RPC URL scheme: {}", url);
+        };
+
+        Ok(Self { url, kind })
+    }
+
+    /// `scheme://host[:port]` without path, query or credentials, which
+    /// often carry API keys. Safe for logs and metric labels.
+    pub fn label(&self) -> String {
+        let (scheme, rest) = self.url.split_once("://").unwrap_or(("", &self.url));
+        let authority = rest.split(['/', '?']).next().unwrap_or_default();
+        let host = authority.rsplit('@').next().unwrap_or_default();
+        format!("{}://{}", scheme, host)
+    }
+}
+
+/// Rolling health of one endpoint
+#[derive(Debug, Default)]
+pub struct EndpointHealth {
+    successes: AtomicU64,
+    failures: AtomicU64,
+    consecutive_failures: AtomicU64,
+    latency_ms: AtomicU64,
+}
+
+impl EndpointHealth {
+    pub fn record_success(&self, latency_ms: u64) {
+        self.successes.fetch_add(1, Ordering::Relaxed);
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        // EWMA with alpha = 1/4
+        let prev = self.latency_ms.load(Ordering::Relaxed);
+        let next = if prev == 0 { latency_ms } else { (prev * 3 + latency_ms) / 4 };
+        self.latency_ms.store(next, Ordering::Relaxed);
+    }
+
+    pub fn record_failure(&self) {
+        self.failures.fetch_add(1, Ordering::Relaxed);
+        self.consecutive_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Higher is healthier: success ratio, minus latency and a penalty per
+    /// consecutive failure
+    pub fn score(&self) -> i64 {
+        let ok = self.successes.load(Ordering::Relaxed);
+        let failed = self.failures.load(Ordering::Relaxed);
+        let ratio = (ok + 1) as f64 / (ok + failed + 2) as f64;
+
+        (ratio * 1000.0) as i64
+            - self.latency_ms.load(Ordering::Relaxed) as i64
+            - self.consecutive_failures.load(Ordering::Relaxed) as i64 * 200
+    }
+}
+
+/// Set of RPC endpoints with health scoring.
+///
+/// WS endpoints back the subscriptions and are failed over in score order.
+/// HTTP endpoints serve raced reads (first answer wins) and quorum reads
+/// (two providers must agree).
+pub struct RpcPool {
+    factory: ProviderFactory,
+    endpoints: Vec<RpcEndpoint>,
+    health: Vec<EndpointHealth>,
+    http: Vec<(usize, Arc<Provider<MeteredHttp>>)>,
+    /// Index of the WS endpoint currently serving subscriptions
+    active_ws: AtomicUsize,
+    /// Index of the HTTP endpoint currently serving polling
+    active_http: AtomicUsize,
+    pub race_reads: bool,
+    pub quorum_reads: bool,
+}
+
+impl RpcPool {
+    pub fn new(
+        factory: ProviderFactory,
+        endpoints: Vec<RpcEndpoint>,
+        race_reads: bool,
+        quorum_reads: bool,
+    ) -> anyhow::Result<Self> {
+        if endpoints.is_empty() {
+            anyhow::bail!("At least one RPC endpoint is required");
+        }
+
+        let http = endpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.kind == EndpointKind::Http)
+            .map(|(i, e)| Ok((i, Arc::new(factory.http(&e.url)?))))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let health = endpoints.iter().map(|_| EndpointHealth::default()).collect();
+
+        Ok(Self {
+            factory,
+            endpoints,
+            health,
+            http,
+            active_ws: AtomicUsize::new(usize::MAX),
+            active_http: AtomicUsize::new(usize::MAX),
+            race_reads,
+            quorum_reads,
+        })
+    }
+
+    pub fn endpoints(&self) -> &[RpcEndpoint] {
+        &self.endpoints
+    }
+
+    pub fn health(&self, index: usize) -> Option<&EndpointHealth> {
+        self.health.get(index)
+    }
+
+    /// Endpoint indices of the given kind, healthiest first
+    pub fn ranked(&self, kind: EndpointKind) -> Vec<usize> {
+        let mut idx: Vec<usize> = self
+            .endpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.kind == kind)
+            .map(|(i, _)| i)
+            .collect();
+        idx.sort_by_key(|i| std::cmp::Reverse(self.health[*i].score()));
+        idx
+    }
+
+    /// Connect to the healthiest reachable WS endpoint.
+    ///
+    /// Called for the initial connection and on every reconnect; a reconnect
+    /// means the previously active endpoint dropped, so it is penalised first.
+    pub async fn connect_ws(&self) -> anyhow::Result<Provider<TimedWs>> {
+        let previous = self.active_ws.load(Ordering::Relaxed);
+        if let Some(health) = self.health.get(previous) {
+            health.record_failure();
+        }
+
+        for i in self.ranked(EndpointKind::Ws) {
+            let started = Instant::now();
+            match self.factory.ws(&self.endpoints[i].url).await {
+                Ok(provider) => {
+                    self.health[i].record_success(started.elapsed().as_millis() as u64);
+                    self.active_ws.store(i, Ordering::Relaxed);
+                    tracing::info!("🔌 Subscriptions on RPC endpoint #{}", i);
+                    return Ok(provider);
+                }
+                Err(e) => {
+                    self.health[i].record_failure();
+                    tracing::warn!("RPC endpoint #{} unreachable: {:?}", i, e);
+                }
+            }
+        }
+
+        anyhow::bail!("No WS RPC endpoint reachable")
+    }
+
+    /// Healthiest HTTP endpoint, for pubsub-free polling mode. Like
+    /// `connect_ws`, a repeated call penalises the endpoint it replaces.
+    pub async fn connect_http(&self) -> anyhow::Result<Provider<MeteredHttp>> {
+        let previous = self.active_http.load(Ordering::Relaxed);
+        if let Some(health) = self.health.get(previous) {
+            health.record_failure();
+        }
+
+        for i in self.ranked(EndpointKind::Http) {
+            let Some((_, provider)) = self.http.iter().find(|(j, _)| *j == i) else {
+                continue;
+            };
+
+            let started = Instant::now();
+            match provider.get_block_number().await {
+                Ok(_) => {
+                    self.health[i].record_success(started.elapsed().as_millis() as u64);
+                    self.active_http.store(i, Ordering::Relaxed);
+                    tracing::info!("🔌 Polling RPC endpoint #{}", i);
+                    return Ok(Provider::clone(provider));
+                }
+                Err(e) => {
+                    self.health[i].record_failure();
+                    tracing::warn!("RPC endpoint #{} unreachable: {:?}", i, e);
+                }
+            }
+        }
+
+        anyhow::bail!("No HTTP RPC endpoint reachable")
+    }
+
+    /// Feed the outcome of a call into the endpoint's health score. Call
+    /// counts and latency metrics are recorded by the transport itself.
+    async fn timed<T, E, F>(&self, index: usize, fut: F) -> Result<T, E>
+    where
+        F: std::future::Future<Output = Result<T, E>>,
+    {
+        let started = Instant::now();
+        let res = fut.await;
+        match &res {
+            Ok(_) => self.health[index].record_success(started.elapsed().as_millis() as u64),
+            Err(_) => self.health[index].record_failure(),
+        }
+        res
+    }
+
+    /// `eth_getLogs` raced across every HTTP endpoint; first success wins.
+    /// A node behind the end of the range answers with whatever it has, so
+    /// only endpoints whose head has reached it take part. An open-ended
+    /// range is pinned to the highest head any endpoint reports.
+    pub async fn race_get_logs(&self, filter: &Filter) -> anyhow::Result<Vec<Log>> {
+        if self.http.is_empty() {
+            anyhow::bail!("No HTTP endpoints configured for raced reads");
+        }
+
+        let mut filter = filter.clone();
+        let mut racers: Vec<&(usize, Arc<Provider<MeteredHttp>>)> = self.http.iter().collect();
+        if filter.get_block_hash().is_none() {
+            let heads = join_all(
+                self.http.iter().map(|(i, p)| self.timed(*i, p.get_block_number())),
+            )
+            .await;
+            let to_block = match filter.get_to_block() {
+                Some(to_block) => to_block,
+                None => {
+                    let head = heads.iter().filter_map(|h| h.as_ref().ok()).max().copied();
+                    let head = head.ok_or_else(|| anyhow::anyhow!("No endpoint reported a head for eth_getLogs"))?;
+                    filter = filter.to_block(head);
+                    head
+                }
+            };
+            racers = self
+                .http
+                .iter()
+                .zip(heads)
+                .filter(|(_, head)| head.as_ref().is_ok_and(|head| *head >= to_block))
+                .map(|(endpoint, _)| endpoint)
+                .collect();
+            if racers.is_empty() {
+                anyhow::bail!("No endpoint has reached block {} for eth_getLogs", to_block);
+            }
+        }
+
+        let filter = &filter;
+        let calls = racers.into_iter().map(|(i, p)| {
+            Box::pin(async move { self.timed(*i, p.get_logs(filter)).await })
+        });
+
+        let (logs, _) = select_ok(calls)
+            .await
+            .map_err(|e| anyhow::anyhow!("All endpoints failed eth_getLogs: {:?}", e))?;
+
+        Ok(dedup_logs(logs))
+    }
+
+    /// Block header raced across every HTTP endpoint. An endpoint that
+    /// doesn't have the block yet (a lagging node asked for a fresh head)
+    /// counts as failed, so the race waits for one that does. Calls are
+    /// metered under the method sent: `eth_getBlockByHash` for a hash.
+    pub async fn race_get_block(&self, id: BlockId) -> anyhow::Result<Block<H256>> {
+        if self.http.is_empty() {
+            anyhow::bail!("No HTTP endpoints configured for raced reads");
+        }
+
+        let calls = self.http.iter().map(|(i, p)| {
+            Box::pin(async move {
+                match self.timed(*i, p.get_block(id)).await {
+                    Ok(Some(block)) => Ok(block),
+                    Ok(None) => Err(anyhow::anyhow!("Endpoint #{} doesn't have block {:?} yet", i, id)),
+                    Err(e) => Err(anyhow::anyhow!("Endpoint #{} failed: {:?}", i, e)),
+                }
+            })
+        });
+
+        let (block, _) = select_ok(calls)
+            .await
+            .map_err(|e| anyhow::anyhow!("No endpoint returned block {:?}: {:?}", id, e))?;
+
+        Ok(block)
+    }
+
+    /// Latest block number raced across every HTTP endpoint
+    pub async fn race_block_number(&self) -> anyhow::Result<U64> {
+        if self.http.is_empty() {
+            anyhow::bail!("No HTTP endpoints configured for raced reads");
+        }
+
+        let calls = self.http.iter().map(|(i, p)| {
+            Box::pin(async move { self.timed(*i, p.get_block_number()).await })
+        });
+
+        let (number, _) = select_ok(calls)
+            .await
+            .map_err(|e| anyhow::anyhow!("All endpoints failed eth_blockNumber: {:?}", e))?;
+
+        Ok(number)
+    }
+
+    /// Run `eth_call` on the two healthiest HTTP endpoints at a block both
+    /// have seen, and fail unless the results match byte for byte
+    pub async fn cross_checked_call(&self, to: Address, data: Bytes) -> anyhow::Result<Bytes> {
+        let mut ranked = self.ranked(EndpointKind::Http);
+        ranked.truncate(2);
+        if ranked.len() < 2 {
+            anyhow::bail!("Quorum reads need at least two HTTP endpoints");
+        }
+
+        let providers: Vec<(usize, Arc<Provider<MeteredHttp>>)> = ranked
+            .iter()
+            .filter_map(|i| self.http.iter().find(|(j, _)| j == i).cloned())
+            .collect();
+
+        let heads = join_all(
+            providers.iter().map(|(i, p)| self.timed(*i, p.get_block_number())),
+        )
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+        let block: BlockId = BlockNumber::Number(*heads.iter().min().expect("two heads")).into();
+
+        let tx: TypedTransaction = TransactionRequest::new().to(to).data(data).into();
+        let results = join_all(
+            providers.iter().map(|(i, p)| self.timed(*i, p.call(&tx, Some(block)))),
+        )
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+        if results[0] != results[1] {
+            anyhow::bail!("Providers disagree on eth_call to {:?} at {:?}", to, block);
+        }
+
+        Ok(results[0].clone())
+    }
+}
+
+/// Drop repeated logs (same block hash and log index), keeping order
+pub fn dedup_logs(logs: Vec<Log>) -> Vec<Log> {
+    let mut seen = std::collections::HashSet::new();
+    logs.into_iter()
+        .filter(|l| seen.insert((l.block_hash, l.transaction_hash, l.log_index)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Json, Router, routing::post};
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::{metrics::METRICS, provider_factory::ProviderFactory};
+
+    /// JSON-RPC endpoint on a free local port that knows no blocks
+    fn empty_node() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new().route(
+            "/",
+            post(|Json(req): Json<Value>| async move { Json(json!({ "jsonrpc": "2.0", "id": req["id"], "result": null })) }),
+        );
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        url
+    }
+
+    #[tokio::test]
+    async fn race_get_block_is_metered_under_the_method_sent() {
+        let endpoint = RpcEndpoint::parse(&empty_node()).unwrap();
+        let label = endpoint.label();
+        let pool = RpcPool::new(ProviderFactory::default(), vec![endpoint], true, false).unwrap();
+        let calls = |method: &str| METRICS.rpc_calls.with_label_values(&[&label, method]).get();
+
+        assert!(pool.race_get_block(H256::repeat_byte(0x11).into()).await.is_err());
+        assert_eq!((calls("eth_getBlockByHash"), calls("eth_getBlockByNumber")), (1, 0));
+
+        assert!(pool.race_get_block(BlockNumber::Number(65_000_000u64.into()).into()).await.is_err());
+        assert_eq!((calls("eth_getBlockByHash"), calls("eth_getBlockByNumber")), (1, 1));
+    }
+}
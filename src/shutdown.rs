@@ -0,0 +1,209 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use ethers::{providers::Middleware, types::{BlockNumber, H256}};
+use futures::future::BoxFuture;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    connection_manager::ConnectionManager,
+    constants::{DROPPED_TX_GRACE_SECS, IN_FLIGHT_POLL_INTERVAL_MS},
+    metrics::METRICS,
+    store,
+};
+
+type FlushHook = Box<dyn FnOnce() -> BoxFuture<'static, anyhow::Result<()>> + Send>;
+
+/// Coordinates a graceful stop.
+///
+/// The token is cancelled on SIGINT/SIGTERM; tasks watching it stop taking
+/// new work. Sent transactions are tracked until mined or dropped so
+/// shutdown can wait for them; the drained token is cancelled once they
+/// are, and flush hooks (persistence, metrics) run last.
+pub struct Shutdown {
+    token: CancellationToken,
+    drained: CancellationToken,
+    in_flight: DashMap<H256, Instant>,
+    settled: Notify,
+    hooks: Mutex<Vec<(String, FlushHook)>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            token: CancellationToken::new(),
+            drained: CancellationToken::new(),
+            in_flight: DashMap::new(),
+            settled: Notify::new(),
+            hooks: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Cancelled after `drain_in_flight`, for tasks that keep in-flight
+    /// transactions moving (fee bumps, cancellations) until then
+    pub fn drained_token(&self) -> CancellationToken {
+        self.drained.clone()
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Stop accepting new work
+    pub fn trigger(&self) {
+        self.token.cancel();
+    }
+
+    /// Resolve on Ctrl-C, or SIGTERM (what `docker stop` sends)
+    pub async fn wait_for_signal() -> anyhow::Result<()> {
+        #[cfg(unix)]
+        {
+            let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+            tokio::select! {
+                res = tokio::signal::ctrl_c() => res?,
+                _ = sigterm.recv() => {}
+            }
+        }
+
+        #[cfg(not(unix))]
+        tokio::signal::ctrl_c().await?;
+
+        Ok(())
+    }
+
+    /// Register a callback to run once everything else has stopped
+    pub fn on_shutdown<F>(&self, name: impl Into<String>, hook: F)
+    where
+        F: FnOnce() -> BoxFuture<'static, anyhow::Result<()>> + Send + 'static,
+    {
+        self.hooks
+            .lock()
+            .expect("shutdown hooks lock poisoned")
+            .push((name.into(), Box::new(hook)));
+    }
+
+    /// Follow a sent transaction until it is mined, its nonce is taken by
+    /// another transaction (replaced), or the node hasn't known it for
+    /// `DROPPED_TX_GRACE_SECS` (dropped). A node that doesn't return a tx
+    /// it only just accepted doesn't mean it's gone, and lookups that keep
+    /// failing count toward the same grace period. Each poll uses the
+    /// connection's current provider, so a reconnect doesn't strand it.
+    pub fn track_tx<M: Middleware + 'static>(self: &Arc<Self>, tx_hash: H256, connection: Arc<ConnectionManager<M>>) {
+        self.in_flight.insert(tx_hash, Instant::now());
+        let this = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_millis(IN_FLIGHT_POLL_INTERVAL_MS));
+            let mut sent_at = None;
+            let mut unseen_since = Instant::now();
+            loop {
+                ticker.tick().await;
+                let provider = connection.provider();
+
+                // Read the chain nonce before the receipt, so a nonce seen
+                // taken with no receipt can't be ours landing in between
+                let consumed = match sent_at {
+                    Some((from, nonce)) => provider
+                        .get_transaction_count(from, Some(BlockNumber::Latest.into()))
+                        .await
+                        .is_ok_and(|mined| mined > nonce),
+                    None => false,
+                };
+
+                let receipt_missing = match provider.get_transaction_receipt(tx_hash).await {
+                    Ok(Some(receipt)) => {
+                        METRICS.record_receipt(&receipt);
+                        store::record_receipt(&receipt);
+                        break;
+                    }
+                    Ok(None) => true,
+                    Err(e) => {
+                        tracing::debug!("Receipt lookup for {:?} failed: {:?}", tx_hash, e);
+                        false
+                    }
+                };
+
+                if consumed && receipt_missing {
+                    tracing::info!("Transaction {:?} replaced at its nonce", tx_hash);
+                    break;
+                }
+
+                match provider.get_transaction(tx_hash).await {
+                    Ok(Some(tx)) => {
+                        sent_at = Some((tx.from, tx.nonce));
+                        unseen_since = Instant::now();
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::debug!("Transaction lookup for {:?} failed: {:?}", tx_hash, e),
+                }
+                if unseen_since.elapsed() >= Duration::from_secs(DROPPED_TX_GRACE_SECS) {
+                    tracing::info!("Transaction {:?} dropped or unreachable; no longer tracked", tx_hash);
+                    break;
+                }
+            }
+
+            this.settle(tx_hash);
+        });
+    }
+
+    pub fn in_flight_count(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    fn settle(&self, tx_hash: H256) {
+        self.in_flight.remove(&tx_hash);
+        self.settled.notify_waiters();
+    }
+
+    /// Wait until no transaction is in flight, or `deadline` passes, then
+    /// cancel the drained token. Returns the number still pending.
+    pub async fn drain_in_flight(&self, deadline: tokio::time::Instant) -> usize {
+        loop {
+            // Register for wakeups before checking, so a settle in between isn't missed
+            let settled = self.settled.notified();
+            tokio::pin!(settled);
+            settled.as_mut().enable();
+
+            if self.in_flight.is_empty() {
+                break;
+            }
+
+            tracing::info!("⏳ Waiting for {} in-flight transaction(s)", self.in_flight.len());
+            if tokio::time::timeout_at(deadline, settled).await.is_err() {
+                break;
+            }
+        }
+
+        let pending = self.in_flight.len();
+        if pending > 0 {
+            tracing::warn!("⚠️ Shutdown timeout with {} transaction(s) still pending", pending);
+        }
+        self.drained.cancel();
+        pending
+    }
+
+    /// Run every flush hook in registration order
+    pub async fn run_hooks(&self) {
+        let hooks = std::mem::take(&mut *self.hooks.lock().expect("shutdown hooks lock poisoned"));
+
+        for (name, hook) in hooks {
+            if let Err(e) = hook().await {
+                tracing::warn!("Shutdown hook {} failed: {:?}", name, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use ethers::{
+    abi::{AbiDecode, ParamType},
+    providers::{Middleware, MiddlewareError},
+    types::{BlockNumber, Bytes, U256, transaction::eip2718::TypedTransaction},
+};
+
+use crate::{
+    bindings::flash_arbitrageur::FlashArbitrageurErrors,
+    fork_sim::{ForkState, SimTx, simulate_bundle},
+    metrics::METRICS,
+};
+
+/// `Error(string)` selector
+const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// `Panic(uint256)` selector
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Why a call reverted, decoded from its revert data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertReason {
+    /// `require(cond, "message")` / `revert("message")`
+    Error(String),
+    /// Compiler-inserted `Panic(uint256)`
+    Panic(U256),
+    /// One of the arb contract's custom errors, rendered with its arguments
+    Custom(String),
+    /// Revert without data (`revert()`, out of gas, ...)
+    Empty,
+    /// Data matching nothing we know
+    Unknown(Bytes),
+}
+
+impl RevertReason {
+    pub fn decode(data: &[u8]) -> Self {
+        if data.is_empty() {
+            return Self::Empty;
+        }
+        if data.len() < 4 {
+            return Self::Unknown(Bytes::from(data.to_vec()));
+        }
+
+        let (selector, args) = data.split_at(4);
+        if selector == ERROR_STRING_SELECTOR
+            && let Ok(tokens) = ethers::abi::decode(&[ParamType::String], args)
+            && let Some(message) = tokens.into_iter().next().and_then(|t| t.into_string())
+        {
+            return Self::Error(message);
+        }
+        if selector == PANIC_SELECTOR
+            && let Ok(tokens) = ethers::abi::decode(&[ParamType::Uint(256)], args)
+            && let Some(code) = tokens.into_iter().next().and_then(|t| t.into_uint())
+        {
+            return Self::Panic(code);
+        }
+        if let Ok(error) = FlashArbitrageurErrors::decode(data) {
+            return Self::Custom(format!("{:?}", error));
+        }
+
+        Self::Unknown(Bytes::from(data.to_vec()))
+    }
+
+    /// Low-cardinality label for metrics: the kind, plus the panic code,
+    /// custom error name or selector. Messages only go to the log.
+    pub fn label(&self) -> String {
+        match self {
+            Self::Error(_) => "error".to_string(),
+            Self::Panic(code) => match panic_description(*code) {
+                Some(_) => format!("panic:{:#04x}", code.low_u64()),
+                None => "panic:unknown".to_string(),
+            },
+            Self::Custom(error) => error.split(['(', ' ']).next().unwrap_or("custom").to_string(),
+            Self::Empty => "empty".to_string(),
+            Self::Unknown(data) => format!("unknown:{}", hex_selector(data)),
+        }
+    }
+}
+
+impl std::fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error(message) => write!(f, "Error({:?})", message),
+            Self::Panic(code) => {
+                write!(f, "Panic({:#x}: {})", code, panic_description(*code).unwrap_or("unknown panic code"))
+            }
+            Self::Custom(error) => write!(f, "{}", error),
+            Self::Empty => write!(f, "reverted without data"),
+            Self::Unknown(data) => write!(f, "unknown revert data {}", data),
+        }
+    }
+}
+
+fn hex_selector(data: &[u8]) -> String {
+    data.iter().take(4).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Solidity's documented panic codes
+fn panic_description(code: U256) -> Option<&'static str> {
+    if code > U256::from(u8::MAX) {
+        return None;
+    }
+    Some(match code.low_u64() {
+        0x00 => "generic compiler panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "corrupt storage byte array",
+        0x31 => "pop on empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to zero-initialized function",
+        _ => return None,
+    })
+}
+
+/// Revert data carried by a failed call, if the node returned any
+pub fn revert_data<E: MiddlewareError>(error: &E) -> Option<Bytes> {
+    error.as_error_response().and_then(|e| e.as_revert_data())
+}
+
+/// Why a pre-trade simulation rejected a transaction
+#[derive(Debug)]
+pub enum SimulationError {
+    Reverted(RevertReason),
+    BelowMinProfit { profit: U256, min_profit: U256 },
+    /// The call failed for a reason other than a revert (transport, node)
+    Call(String),
+}
+
+impl std::fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Reverted(reason) => write!(f, "simulation reverted: {}", reason),
+            Self::BelowMinProfit { profit, min_profit } => {
+                write!(f, "simulated profit {} below minimum {}", profit, min_profit)
+            }
+            Self::Call(e) => write!(f, "simulation call failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+/// `eth_call` the exact transaction at `block` (latest by default) and
+/// return the profit it reports. Reverts are decoded, logged and counted;
+/// a profit below `min_profit` is rejected too.
+pub async fn simulate_tx<M: Middleware + 'static>(
+    provider: &M,
+    tx: &TypedTransaction,
+    min_profit: U256,
+    block: Option<BlockNumber>,
+) -> Result<U256, SimulationError> {
+    let block = block.unwrap_or(BlockNumber::Latest);
+    let output = match provider.call(tx, Some(block.into())).await {
+        Ok(output) => output,
+        Err(e) => {
+            let Some(data) = revert_data(&e) else {
+                return Err(SimulationError::Call(e.to_string()));
+            };
+            let reason = RevertReason::decode(&data);
+            METRICS.simulation_rejections.with_label_values(&[&reason.label()]).inc();
+            tracing::warn!("🧪 Simulation reverted: {}", reason);
+            return Err(SimulationError::Reverted(reason));
+        }
+    };
+
+    check_profit(&output, min_profit)
+}
+
+/// Same checks as `simulate_tx`, run in the local EVM on `state` instead
+/// of over RPC. `victim` executes first when backrunning a pending swap.
+pub fn simulate_local(
+    state: &ForkState,
+    tx: &TypedTransaction,
+    victim: Option<SimTx>,
+    min_profit: U256,
+    relaxed: bool,
+) -> Result<U256, SimulationError> {
+    let bundle: Vec<SimTx> = victim
+        .into_iter()
+        .chain(std::iter::once(SimTx::from_typed(tx).map_err(|e| SimulationError::Call(e.to_string()))?))
+        .collect();
+    let outcomes = simulate_bundle(state, &bundle, relaxed).map_err(|e| SimulationError::Call(e.to_string()))?;
+    let ours = outcomes.last().expect("bundle ends with our tx");
+
+    tracing::debug!(
+        "🧪 Local simulation on #{}: gas {}, {} accounts changed, took {:?}",
+        state.block().number,
+        ours.gas_used,
+        ours.state_diff.len(),
+        ours.elapsed
+    );
+
+    if let Some(reason) = &ours.revert {
+        METRICS.simulation_rejections.with_label_values(&[&reason.label()]).inc();
+        tracing::warn!("🧪 Local simulation reverted: {}", reason);
+        return Err(SimulationError::Reverted(reason.clone()));
+    }
+
+    check_profit(&ours.output, min_profit)
+}
+
+/// The arb entrypoint returns the realized profit as its first word
+fn check_profit(output: &[u8], min_profit: U256) -> Result<U256, SimulationError> {
+    let profit = if output.len() >= 32 {
+        U256::from_big_endian(&output[..32])
+    } else {
+        U256::zero()
+    };
+
+    if profit < min_profit {
+        METRICS.simulation_rejections.with_label_values(&["below_min_profit"]).inc();
+        return Err(SimulationError::BelowMinProfit { profit, min_profit });
+    }
+
+    tracing::debug!("🧪 Simulation ok, profit {}", profit);
+    Ok(profit)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::{Token, encode};
+
+    use super::*;
+
+    fn revert(selector: [u8; 4], args: &[Token]) -> Vec<u8> {
+        [selector.to_vec(), encode(args)].concat()
+    }
+
+    #[test]
+    fn labels_never_carry_the_revert_message() {
+        let message = "UniswapV2: INSUFFICIENT_OUTPUT_AMOUNT for 0xdeadbeef at block 65000000";
+        let reason = RevertReason::decode(&revert(ERROR_STRING_SELECTOR, &[Token::String(message.into())]));
+        assert_eq!(reason, RevertReason::Error(message.into()));
+        assert_eq!(reason.label(), "error");
+        assert!(reason.to_string().contains(message));
+    }
+
+    #[test]
+    fn panics_are_labelled_by_known_code_only() {
+        let panic = |code: u64| RevertReason::decode(&revert(PANIC_SELECTOR, &[Token::Uint(code.into())]));
+        assert_eq!(panic(0x11).label(), "panic:0x11");
+        assert_eq!(panic(0x01).label(), "panic:0x01");
+        assert_eq!(panic(0x99).label(), "panic:unknown");
+        assert_eq!(panic(0x111).label(), "panic:unknown");
+    }
+
+    #[test]
+    fn other_reverts_are_labelled_by_kind_and_selector() {
+        assert_eq!(RevertReason::decode(&[]).label(), "empty");
+        assert_eq!(RevertReason::decode(&[0xde, 0xad, 0xbe, 0xef, 0x01]).label(), "unknown:deadbeef");
+    }
+}
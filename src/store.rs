@@ -0,0 +1,559 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, RecvTimeoutError, SyncSender, TrySendError, sync_channel},
+    time::{Duration, Instant},
+};
+
+use dashmap::{DashMap, mapref::entry::Entry};
+use ethers::types::{Address, H256 as TxHash, TransactionReceipt, U64, U256};
+use once_cell::sync::OnceCell;
+use rusqlite::{Connection, params};
+use tokio::sync::oneshot;
+
+use crate::{
+    constants::{
+        DEFAULT_STORE_BATCH_SIZE, DEFAULT_STORE_FLUSH_MS, DEFAULT_STORE_PATH, DEFAULT_STORE_PRICE_SAMPLE_MS,
+        STORE_QUEUE_LEN,
+    },
+    controls::OpportunityRecord,
+    helpers::env_u64,
+    metrics::METRICS,
+    pnl::TradeRecord,
+    types::{LogPosition, PoolContext},
+};
+
+/// The process-wide store, once opened. Recording before then, or without
+/// a store configured, is a no-op.
+pub static STORE: OnceCell<Store> = OnceCell::new();
+
+/// Schema, one entry per version. Applied in order on open; the version
+/// reached is kept in `PRAGMA user_version`. Never edit a released entry,
+/// only append.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    "
+    CREATE TABLE pools (
+        factory TEXT NOT NULL,
+        pool TEXT NOT NULL,
+        dex TEXT NOT NULL,
+        base TEXT NOT NULL,
+        quote TEXT NOT NULL,
+        token0_is_base INTEGER NOT NULL,
+        first_seen INTEGER NOT NULL,
+        PRIMARY KEY (factory, pool)
+    );
+    CREATE TABLE price_updates (
+        id INTEGER PRIMARY KEY,
+        at INTEGER NOT NULL,
+        factory TEXT NOT NULL,
+        pool TEXT NOT NULL,
+        price REAL NOT NULL,
+        block INTEGER,
+        log_index INTEGER
+    );
+    CREATE INDEX price_updates_pool_at ON price_updates (pool, at);
+    CREATE TABLE opportunities (
+        id INTEGER PRIMARY KEY,
+        at INTEGER NOT NULL,
+        block INTEGER NOT NULL,
+        base TEXT NOT NULL,
+        quote TEXT NOT NULL,
+        buy_factory TEXT NOT NULL,
+        sell_factory TEXT NOT NULL,
+        buy_price REAL NOT NULL,
+        sell_price REAL NOT NULL,
+        spread REAL NOT NULL,
+        threshold REAL NOT NULL,
+        tx_hash TEXT,
+        outcome TEXT NOT NULL
+    );
+    CREATE INDEX opportunities_at ON opportunities (at);
+    CREATE TABLE transactions (
+        hash TEXT PRIMARY KEY,
+        at INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        nonce TEXT NOT NULL,
+        target TEXT,
+        replaces TEXT
+    );
+    CREATE TABLE receipts (
+        hash TEXT PRIMARY KEY,
+        at INTEGER NOT NULL,
+        block INTEGER,
+        status INTEGER,
+        gas_used TEXT,
+        effective_gas_price TEXT
+    );
+    CREATE TABLE trades (
+        id INTEGER PRIMARY KEY,
+        at INTEGER NOT NULL,
+        hashes TEXT NOT NULL,
+        base TEXT NOT NULL,
+        quote TEXT NOT NULL,
+        buy_factory TEXT NOT NULL,
+        sell_factory TEXT NOT NULL,
+        expected_profit REAL NOT NULL,
+        quote_delta REAL NOT NULL,
+        base_delta REAL NOT NULL,
+        gas_used TEXT NOT NULL,
+        gas_cost_wei TEXT NOT NULL,
+        gas_cost_quote REAL,
+        realized_profit REAL NOT NULL,
+        status TEXT NOT NULL,
+        block INTEGER
+    );
+    CREATE INDEX trades_at ON trades (at);
+    ",
+];
+
+/// One row to write
+#[derive(Debug, Clone)]
+pub enum StoreEvent {
+    Pool(PoolContext),
+    Price {
+        at: u64,
+        factory: Address,
+        pool: Address,
+        price: f64,
+        position: Option<LogPosition>,
+    },
+    Opportunity(OpportunityRecord),
+    Transaction {
+        at: u64,
+        hash: TxHash,
+        /// "send", "bump" or "cancel"
+        kind: &'static str,
+        nonce: U256,
+        target: Option<Address>,
+        replaces: Option<TxHash>,
+    },
+    Receipt {
+        at: u64,
+        hash: TxHash,
+        block: Option<u64>,
+        status: Option<u64>,
+        gas_used: Option<U256>,
+        effective_gas_price: Option<U256>,
+    },
+    Trade(TradeRecord),
+}
+
+impl StoreEvent {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Pool(_) => "pool",
+            Self::Price { .. } => "price",
+            Self::Opportunity(_) => "opportunity",
+            Self::Transaction { .. } => "transaction",
+            Self::Receipt { .. } => "receipt",
+            Self::Trade(_) => "trade",
+        }
+    }
+
+    /// Our own transactions and their outcomes, which nothing else records
+    fn must_keep(&self) -> bool {
+        matches!(self, Self::Transaction { .. } | Self::Receipt { .. } | Self::Trade(_))
+    }
+}
+
+enum Command {
+    Write(Box<StoreEvent>),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Embedded SQLite history of pools, sampled prices, opportunities,
+/// transactions, receipts and settled trades.
+///
+/// Callers only enqueue; a dedicated thread writes in batches of
+/// `STORE_BATCH_SIZE` rows, or every `STORE_FLUSH_MS`, one transaction per
+/// batch. When the queue is full, pools, prices and opportunities are
+/// dropped (and counted) rather than stalling the hot path; transactions,
+/// receipts and trades wait for room off the caller's task instead.
+pub struct Store {
+    tx: SyncSender<Command>,
+    /// Last price sample per pool
+    sampled: DashMap<Address, Instant>,
+    sample_every: Duration,
+}
+
+impl Store {
+    /// Open (or create) the database at `path`, migrate it, and start the writer
+    pub fn open(path: &Path, batch_size: usize, flush_every: Duration, sample_every: Duration) -> anyhow::Result<Self> {
+        let mut conn = Connection::open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open store {:?}: {:?}", path, e))?;
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")?;
+        migrate(&mut conn)?;
+
+        let (tx, rx) = sync_channel(STORE_QUEUE_LEN);
+        std::thread::Builder::new()
+            .name("store-writer".into())
+            .spawn(move || write_loop(conn, rx, batch_size.max(1), flush_every))?;
+
+        Ok(Self { tx, sampled: DashMap::new(), sample_every })
+    }
+
+    /// `STORE_PATH` (default `DEFAULT_STORE_PATH`; empty disables),
+    /// `STORE_BATCH_SIZE`, `STORE_FLUSH_MS` and `STORE_PRICE_SAMPLE_MS`.
+    /// Installs the store as [`STORE`].
+    pub fn init_from_env() -> anyhow::Result<Option<&'static Store>> {
+        let path = std::env::var("STORE_PATH").unwrap_or_else(|_| DEFAULT_STORE_PATH.to_string());
+        if path.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let path = PathBuf::from(path.trim());
+        let store = Self::open(
+            &path,
+            env_u64("STORE_BATCH_SIZE", DEFAULT_STORE_BATCH_SIZE) as usize,
+            Duration::from_millis(env_u64("STORE_FLUSH_MS", DEFAULT_STORE_FLUSH_MS)),
+            Duration::from_millis(env_u64("STORE_PRICE_SAMPLE_MS", DEFAULT_STORE_PRICE_SAMPLE_MS)),
+        )?;
+        if STORE.set(store).is_err() {
+            anyhow::bail!("Store already initialized");
+        }
+        tracing::info!("🗄️ Recording history to {:?}", path);
+        Ok(STORE.get())
+    }
+
+    pub fn record(&self, event: StoreEvent) {
+        let event = match self.tx.try_send(Command::Write(Box::new(event))) {
+            Ok(()) => return,
+            Err(TrySendError::Full(Command::Write(event))) => event,
+            Err(TrySendError::Full(Command::Flush(_))) => return,
+            Err(TrySendError::Disconnected(_)) => {
+                tracing::warn!("Store writer has stopped; event dropped");
+                return;
+            }
+        };
+
+        if !event.must_keep() {
+            METRICS.store_dropped_events.with_label_values(&[event.kind()]).inc();
+            tracing::warn!("Store queue full; {} event dropped", event.kind());
+            return;
+        }
+
+        // Wait for room without blocking the caller's task
+        let tx = self.tx.clone();
+        let send = move || {
+            if tx.send(Command::Write(event)).is_err() {
+                tracing::warn!("Store writer has stopped; event dropped");
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn_blocking(send)),
+            Err(_) => send(),
+        }
+    }
+
+    /// Whether a price for `pool` is due for sampling; marks it sampled if so
+    fn sample(&self, pool: Address) -> bool {
+        let now = Instant::now();
+        match self.sampled.entry(pool) {
+            Entry::Occupied(mut last) => {
+                if now.duration_since(*last.get()) < self.sample_every {
+                    return false;
+                }
+                last.insert(now);
+            }
+            Entry::Vacant(last) => {
+                last.insert(now);
+            }
+        }
+        true
+    }
+
+    /// Wait until everything enqueued so far is written
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        let (done, written) = oneshot::channel();
+        // Blocking send: a flush must not be dropped like an event
+        let tx = self.tx.clone();
+        tokio::task::spawn_blocking(move || tx.send(Command::Flush(done)))
+            .await?
+            .map_err(|_| anyhow::anyhow!("Store writer has stopped"))?;
+        written.await.map_err(|_| anyhow::anyhow!("Store writer stopped before flushing"))
+    }
+}
+
+/// Record `event` in the global store, if one is open
+pub fn record(event: StoreEvent) {
+    if let Some(store) = STORE.get() {
+        store.record(event);
+    }
+}
+
+/// Record a decoded pool price, at most once per `STORE_PRICE_SAMPLE_MS` per pool
+pub fn record_price(pool: &PoolContext, price: f64, position: Option<LogPosition>) {
+    if let Some(store) = STORE.get()
+        && store.sample(pool.pair.pair)
+    {
+        store.record(StoreEvent::Price {
+            at: now(),
+            factory: pool.dex_factory,
+            pool: pool.pair.pair,
+            price,
+            position,
+        });
+    }
+}
+
+pub fn record_receipt(receipt: &TransactionReceipt) {
+    record(StoreEvent::Receipt {
+        at: now(),
+        hash: receipt.transaction_hash,
+        block: receipt.block_number.map(|b| b.as_u64()),
+        status: receipt.status.map(|s: U64| s.as_u64()),
+        gas_used: receipt.gas_used,
+        effective_gas_price: receipt.effective_gas_price,
+    });
+}
+
+/// Unix seconds
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn migrate(conn: &mut Connection) -> anyhow::Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        anyhow::bail!("Store schema v{} is newer than this build (v{})", version, MIGRATIONS.len());
+    }
+
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+        tracing::info!("🗄️ Store migrated to schema v{}", i + 1);
+    }
+    Ok(())
+}
+
+fn write_loop(mut conn: Connection, rx: Receiver<Command>, batch_size: usize, flush_every: Duration) {
+    let mut batch: Vec<StoreEvent> = Vec::with_capacity(batch_size);
+    let mut deadline = Instant::now() + flush_every;
+
+    loop {
+        let command = rx.recv_timeout(deadline.saturating_duration_since(Instant::now()));
+        let flushed = match command {
+            Ok(Command::Write(event)) => {
+                batch.push(*event);
+                if batch.len() < batch_size {
+                    continue;
+                }
+                None
+            }
+            Ok(Command::Flush(done)) => Some(done),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => {
+                write_batch(&mut conn, &mut batch);
+                return;
+            }
+        };
+
+        write_batch(&mut conn, &mut batch);
+        deadline = Instant::now() + flush_every;
+        if let Some(done) = flushed {
+            let _ = done.send(());
+        }
+    }
+}
+
+fn write_batch(conn: &mut Connection, batch: &mut Vec<StoreEvent>) {
+    if batch.is_empty() {
+        return;
+    }
+
+    let result = (|| -> rusqlite::Result<()> {
+        let tx = conn.transaction()?;
+        for event in batch.iter() {
+            write_event(&tx, event)?;
+        }
+        tx.commit()
+    })();
+    if let Err(e) = result {
+        tracing::warn!("Failed to write {} store events: {:?}", batch.len(), e);
+    }
+    batch.clear();
+}
+
+fn write_event(conn: &Connection, event: &StoreEvent) -> rusqlite::Result<()> {
+    let hex = |a: &Address| format!("{:?}", a);
+    let hash = |h: &TxHash| format!("{:?}", h);
+
+    match event {
+        StoreEvent::Pool(pool) => {
+            conn.prepare_cached(
+                "INSERT OR IGNORE INTO pools (factory, pool, dex, base, quote, token0_is_base, first_seen)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?
+            .execute(params![
+                hex(&pool.dex_factory),
+                hex(&pool.pair.pair),
+                pool.dex_name,
+                hex(&pool.pair.base.id),
+                hex(&pool.pair.quote.id),
+                pool.token0_is_base,
+                now(),
+            ])?;
+        }
+        StoreEvent::Price { at, factory, pool, price, position } => {
+            conn.prepare_cached(
+                "INSERT INTO price_updates (at, factory, pool, price, block, log_index)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?
+            .execute(params![
+                at,
+                hex(factory),
+                hex(pool),
+                price,
+                position.map(|p| p.block_number),
+                position.map(|p| p.log_index),
+            ])?;
+        }
+        StoreEvent::Opportunity(opp) => {
+            conn.prepare_cached(
+                "INSERT INTO opportunities (at, block, base, quote, buy_factory, sell_factory, buy_price,
+                 sell_price, spread, threshold, tx_hash, outcome)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            )?
+            .execute(params![
+                opp.at,
+                opp.block,
+                hex(&opp.base),
+                hex(&opp.quote),
+                hex(&opp.buy_factory),
+                hex(&opp.sell_factory),
+                opp.buy_price,
+                opp.sell_price,
+                opp.spread,
+                opp.threshold,
+                opp.tx_hash.as_ref().map(hash),
+                opp.outcome,
+            ])?;
+        }
+        StoreEvent::Transaction { at, hash: h, kind, nonce, target, replaces } => {
+            conn.prepare_cached(
+                "INSERT OR REPLACE INTO transactions (hash, at, kind, nonce, target, replaces)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?
+            .execute(params![
+                hash(h),
+                at,
+                kind,
+                nonce.to_string(),
+                target.as_ref().map(hex),
+                replaces.as_ref().map(hash),
+            ])?;
+        }
+        StoreEvent::Receipt { at, hash: h, block, status, gas_used, effective_gas_price } => {
+            conn.prepare_cached(
+                "INSERT OR REPLACE INTO receipts (hash, at, block, status, gas_used, effective_gas_price)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?
+            .execute(params![
+                hash(h),
+                at,
+                block,
+                status,
+                gas_used.map(|g| g.to_string()),
+                effective_gas_price.map(|p| p.to_string()),
+            ])?;
+        }
+        StoreEvent::Trade(trade) => {
+            conn.prepare_cached(
+                "INSERT INTO trades (at, hashes, base, quote, buy_factory, sell_factory, expected_profit,
+                 quote_delta, base_delta, gas_used, gas_cost_wei, gas_cost_quote, realized_profit, status, block)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            )?
+            .execute(params![
+                trade.at,
+                trade.hashes.iter().map(hash).collect::<Vec<_>>().join(","),
+                hex(&trade.base),
+                hex(&trade.quote),
+                hex(&trade.buy_factory),
+                hex(&trade.sell_factory),
+                trade.expected_profit,
+                trade.quote_delta,
+                trade.base_delta,
+                trade.gas_used.to_string(),
+                trade.gas_cost_wei.to_string(),
+                trade.gas_cost_quote,
+                trade.realized_profit,
+                format!("{:?}", trade.status).to_lowercase(),
+                trade.block,
+            ])?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn migrate_brings_a_new_database_to_the_latest_version_once() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+        assert_eq!(count(&conn, "trades"), 0);
+
+        // Already current: nothing is re-applied
+        migrate(&mut conn).unwrap();
+    }
+
+    #[test]
+    fn migrate_refuses_a_newer_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1).unwrap();
+
+        let err = migrate(&mut conn).unwrap_err();
+        assert!(err.to_string().contains("newer than this build"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn queued_rows_are_written_on_flush() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.db");
+        let store = Store::open(&path, 100, Duration::from_secs(3_600), Duration::ZERO).unwrap();
+
+        let hash = TxHash::repeat_byte(0x42);
+        store.record(StoreEvent::Transaction {
+            at: 1,
+            hash,
+            kind: "send",
+            nonce: U256::from(7u64),
+            target: Some(Address::repeat_byte(0x01)),
+            replaces: None,
+        });
+        store.record(StoreEvent::Receipt {
+            at: 2,
+            hash,
+            block: Some(65_000_000),
+            status: Some(1),
+            gas_used: Some(U256::from(21_000u64)),
+            effective_gas_price: Some(U256::from(30_000_000_000u64)),
+        });
+
+        // Below the batch size and long before the deadline: nothing yet
+        let reader = Connection::open(&path).unwrap();
+        assert_eq!(count(&reader, "transactions"), 0);
+
+        store.flush().await.unwrap();
+        assert_eq!(count(&reader, "transactions"), 1);
+        let (nonce, gas_used): (String, String) = reader
+            .query_row("SELECT t.nonce, r.gas_used FROM transactions t JOIN receipts r USING (hash)", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((nonce.as_str(), gas_used.as_str()), ("7", "21000"));
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    alert::Alerter,
+    connection_manager::Backoff,
+    constants::SUPERVISOR_HEALTHY_AFTER_SECS,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    /// Waiting out the backoff before the next restart
+    Restarting,
+    /// Restarted more than the allowed number of times in a row; still
+    /// being retried, but an alert has been raised
+    Escalated,
+    /// Finished after shutdown was requested; not restarted
+    Stopped,
+}
+
+/// Snapshot of one supervised task
+#[derive(Debug, Clone)]
+pub struct TaskStatus {
+    pub name: String,
+    pub state: TaskState,
+    /// Total restarts since the task was first spawned
+    pub restarts: u32,
+    /// Restarts since the task last ran healthily
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    pub last_started: Instant,
+}
+
+/// Spawns long-running tasks by name and keeps them alive.
+///
+/// A task that returns (with or without an error) or panics is restarted
+/// with exponential backoff. A run that lasts longer than
+/// `SUPERVISOR_HEALTHY_AFTER_SECS` resets the failure streak; once the
+/// streak exceeds `max_restarts` the alerter is notified. Nothing is
+/// restarted once the shutdown token is cancelled; tasks are expected to
+/// watch the same token and return.
+pub struct Supervisor {
+    tasks: DashMap<String, TaskStatus>,
+    handles: std::sync::Mutex<Vec<JoinHandle<()>>>,
+    backoff: Backoff,
+    max_restarts: u32,
+    alerter: Arc<dyn Alerter>,
+    shutdown: CancellationToken,
+}
+
+impl Supervisor {
+    pub fn new(
+        backoff: Backoff,
+        max_restarts: u32,
+        alerter: Arc<dyn Alerter>,
+        shutdown: CancellationToken,
+    ) -> Self {
+        Self {
+            tasks: DashMap::new(),
+            handles: std::sync::Mutex::new(Vec::new()),
+            backoff,
+            max_restarts,
+            alerter,
+            shutdown,
+        }
+    }
+
+    /// Spawn `factory()` under supervision. `factory` is called again for
+    /// every restart, so it must build all per-run state afresh.
+    pub fn spawn<F, Fut>(self: &Arc<Self>, name: impl Into<String>, factory: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        self.spawn_until(name, self.shutdown.clone(), factory);
+    }
+
+    /// Like `spawn`, but the task stops for good once `stop` is cancelled.
+    /// `stop` should be a child of the shutdown token, and the task should
+    /// watch it and return.
+    pub fn spawn_until<F, Fut>(self: &Arc<Self>, name: impl Into<String>, stop: CancellationToken, factory: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        let name = name.into();
+        let this = self.clone();
+
+        let handle = tokio::spawn(async move {
+            loop {
+                this.set_running(&name);
+                let started = Instant::now();
+
+                // Run in its own task so a panic surfaces as a JoinError
+                let outcome = match tokio::spawn(factory()).await {
+                    Ok(Ok(())) => "exited".to_string(),
+                    Ok(Err(e)) => format!("{:?}", e),
+                    Err(e) => format!("panicked: {}", e),
+                };
+
+                if stop.is_cancelled() {
+                    this.set_stopped(&name);
+                    tracing::info!("Task {} stopped", name);
+                    break;
+                }
+
+                let healthy = started.elapsed() >= Duration::from_secs(SUPERVISOR_HEALTHY_AFTER_SECS);
+                let streak = this.record_failure(&name, &outcome, healthy);
+
+                tracing::warn!("🔁 Task {} stopped ({}); restart #{} pending", name, outcome, streak);
+
+                if streak == this.max_restarts + 1 {
+                    this.escalate(&name, &outcome).await;
+                }
+
+                tokio::select! {
+                    _ = tokio::time::sleep(this.backoff.delay(streak.saturating_sub(1))) => {}
+                    _ = stop.cancelled() => {
+                        this.set_stopped(&name);
+                        break;
+                    }
+                }
+            }
+        });
+
+        let mut handles = self.handles.lock().expect("supervisor handles lock poisoned");
+        handles.retain(|h| !h.is_finished());
+        handles.push(handle);
+    }
+
+    /// Wait for every supervised task to stop after shutdown, until
+    /// `deadline`. Returns false if some were still running.
+    pub async fn wait_stopped(&self, deadline: tokio::time::Instant) -> bool {
+        let handles = std::mem::take(&mut *self.handles.lock().expect("supervisor handles lock poisoned"));
+        tokio::time::timeout_at(deadline, futures::future::join_all(handles))
+            .await
+            .is_ok()
+    }
+
+    /// Status of every supervised task, sorted by name
+    pub fn statuses(&self) -> Vec<TaskStatus> {
+        let mut all: Vec<TaskStatus> = self.tasks.iter().map(|t| t.value().clone()).collect();
+        all.sort_by(|a, b| a.name.cmp(&b.name));
+        all
+    }
+
+    pub fn status(&self, name: &str) -> Option<TaskStatus> {
+        self.tasks.get(name).map(|t| t.value().clone())
+    }
+
+    fn set_stopped(&self, name: &str) {
+        if let Some(mut entry) = self.tasks.get_mut(name) {
+            entry.state = TaskState::Stopped;
+        }
+    }
+
+    fn set_running(&self, name: &str) {
+        let mut entry = self.tasks.entry(name.to_string()).or_insert_with(|| TaskStatus {
+            name: name.to_string(),
+            state: TaskState::Running,
+            restarts: 0,
+            consecutive_failures: 0,
+            last_error: None,
+            last_started: Instant::now(),
+        });
+
+        if entry.state != TaskState::Escalated {
+            entry.state = TaskState::Running;
+        }
+        entry.last_started = Instant::now();
+    }
+
+    /// Record a stop and return the current failure streak
+    fn record_failure(&self, name: &str, outcome: &str, healthy: bool) -> u32 {
+        let mut entry = self.tasks.get_mut(name).expect("task registered in set_running");
+
+        if healthy {
+            entry.consecutive_failures = 0;
+            entry.state = TaskState::Running;
+        }
+        entry.restarts += 1;
+        entry.consecutive_failures += 1;
+        entry.last_error = Some(outcome.to_string());
+        if entry.state != TaskState::Escalated {
+            entry.state = TaskState::Restarting;
+        }
+
+        entry.consecutive_failures
+    }
+
+    async fn escalate(&self, name: &str, outcome: &str) {
+        if let Some(mut entry) = self.tasks.get_mut(name) {
+            entry.state = TaskState::Escalated;
+        }
+
+        let message = format!(
+            "Task {} failed {} times in a row; last error: {}",
+            name,
+            self.max_restarts + 1,
+            outcome
+        );
+        tracing::error!("🚨 {}", message);
+        self.alerter.alert(&message).await;
+    }
+}
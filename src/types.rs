@@ -1,7 +1,12 @@
-use ethers::types::{Address, U256};
-use std::collections::HashMap;
+use ethers::types::{Address, Block, H256, Log, U256};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::Instant,
+};
 use tokio::sync::RwLock;
 
+use crate::{constants::PRICE_HISTORY_LEN, flash_loan::FlashLoan};
+
 use serde::{Serialize, Deserialize};
 
 
@@ -10,6 +15,7 @@ pub struct PriceInfo {
     pub base: Address,
     pub quote: Address,
     pub price: f64, // canonical: quote per base
+    pub updated_at: Instant,
 }
 
 /// Unique key: (dex factory address, pair contract address)
@@ -34,12 +40,6 @@ pub struct Token {
     pub decimals: usize 
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum DexType {
-    V2,
-    V3,
-    QuickSwap
-}
 #[derive(Debug, Clone,Serialize, Deserialize)]
 pub struct BalancerPoolInfo {
     
@@ -50,8 +50,11 @@ pub struct BalancerPoolInfo {
 pub struct DexConfig {
     pub name: String,
     pub factory: Address,
+    /// Router the venue's swaps go through
+    pub router: Address,
     pub pairs: Vec<DexPairConfig>,
-    pub dex_type: DexType
+    /// Registry key of the `DexAdapter` driving this venue
+    pub adapter: String
 }
 
 #[derive(Debug, Clone)]
@@ -61,12 +64,193 @@ pub struct ArbOpportunity {
     pub base_asset: Address,
     pub buy_dex: Address,
     pub sell_dex: Address,
+    /// Worst outcomes the contract accepts before reverting
+    pub bounds: ArbBounds,
+    /// Where the contract borrows `trade_amt` (or the buy leg's base)
+    pub flash: FlashLoan,
+}
+
+/// Slippage limits encoded into the arb payload, derived from the
+/// simulated output of each leg
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ArbBounds {
+    /// Minimum base received on the buy leg
+    pub min_base_out: U256,
+    /// Minimum quote received on the sell leg
+    pub min_quote_out: U256,
+    /// Minimum quote left over after repaying `trade_amt`
+    pub min_profit: U256,
+    /// Latest block timestamp the trade may land at
+    pub deadline: U256,
+}
+
+impl ArbBounds {
+    /// Allow each leg `slippage_bps` below its simulated output. The final
+    /// profit floor is what the sell leg leaves at that tolerance after
+    /// repaying `trade_amt` and the flash fee, but never less than
+    /// `profit_floor`.
+    pub fn from_quotes(
+        trade_amt: U256,
+        base_out: U256,
+        quote_out: U256,
+        flash_fee: U256,
+        slippage_bps: u64,
+        profit_floor: U256,
+        deadline: U256,
+    ) -> Self {
+        let tolerate = |amount: U256| amount * U256::from(10_000u64.saturating_sub(slippage_bps)) / U256::from(10_000);
+        let min_quote_out = tolerate(quote_out);
+
+        Self {
+            min_base_out: tolerate(base_out),
+            min_quote_out,
+            min_profit: min_quote_out.saturating_sub(trade_amt + flash_fee).max(profit_floor),
+            deadline,
+        }
+    }
+}
+
+/// Header fields workers need from each new block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockInfo {
+    pub number: u64,
+    pub hash: H256,
+    pub parent_hash: H256,
+    pub timestamp: u64,
+    pub base_fee: Option<U256>,
+    pub gas_limit: U256,
+}
+
+impl<TX> TryFrom<&Block<TX>> for BlockInfo {
+    type Error = anyhow::Error;
+
+    fn try_from(block: &Block<TX>) -> anyhow::Result<Self> {
+        Ok(Self {
+            number: block
+                .number
+                .ok_or_else(|| anyhow::anyhow!("Block has no number (pending?)"))?
+                .as_u64(),
+            hash: block
+                .hash
+                .ok_or_else(|| anyhow::anyhow!("Block has no hash (pending?)"))?,
+            parent_hash: block.parent_hash,
+            timestamp: block.timestamp.as_u64(),
+            base_fee: block.base_fee_per_gas,
+            gas_limit: block.gas_limit,
+        })
+    }
+}
+
+/// What the block watcher broadcasts to workers
+#[derive(Debug, Clone)]
+pub enum BlockEvent {
+    /// A new canonical head
+    NewBlock(BlockInfo),
+    /// The canonical chain switched branches. `removed` are the orphaned
+    /// blocks (oldest first), `added` the new branch up to the new head.
+    Reorg {
+        common_ancestor: u64,
+        removed: Vec<BlockInfo>,
+        added: Vec<BlockInfo>,
+    },
+}
+
+/// Per-pool decoding context, resolved once when the pool is first watched
+#[derive(Debug, Clone)]
+pub struct PoolContext {
+    pub dex_name: String,
+    pub dex_factory: Address,
+    pub pair: DexPairConfig,
+    pub token0_is_base: bool,
+    pub token0_decimals: usize,
+    pub token1_decimals: usize,
+}
+
+/// Parameters for a single exact-input swap leg
+#[derive(Debug, Clone)]
+pub struct SwapParams {
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amount_in: U256,
+    pub min_amount_out: U256,
+    pub recipient: Address,
+    pub deadline: U256,
+}
+
+/// Outcome of an exact-input swap simulated against a pool's current state
+#[derive(Debug, Clone)]
+pub struct SwapSimulation {
+    pub amount_out: U256,
+    /// Quote-per-base price before and after the swap lands
+    pub price_before: f64,
+    pub price_after: f64,
+}
+
+/// Where a price update came from on chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogPosition {
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub log_index: u64,
+}
+
+impl LogPosition {
+    /// Position of a mined log; `None` for logs without block data
+    pub fn of(log: &Log) -> Option<Self> {
+        Some(Self {
+            block_number: log.block_number?.as_u64(),
+            block_hash: log.block_hash?,
+            log_index: log.log_index?.as_u64(),
+        })
+    }
+}
+
+/// One pool's recent prices, ordered by chain position. Untagged entries
+/// (startup snapshots) sort first and are never rolled back.
+#[derive(Debug, Default)]
+struct PriceHistory {
+    entries: VecDeque<(Option<LogPosition>, PriceInfo)>,
 }
 
+impl PriceHistory {
+    fn sort_key(at: &Option<LogPosition>) -> (u64, u64) {
+        at.map_or((0, 0), |p| (p.block_number, p.log_index))
+    }
+
+    fn insert(&mut self, at: Option<LogPosition>, info: PriceInfo) {
+        let key = Self::sort_key(&at);
+        let idx = self
+            .entries
+            .iter()
+            .rposition(|(p, _)| Self::sort_key(p) <= key)
+            .map_or(0, |i| i + 1);
+        self.entries.insert(idx, (at, info));
+
+        while self.entries.len() > PRICE_HISTORY_LEN {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Drop entries from the given blocks; returns how many were dropped
+    fn remove_blocks(&mut self, hashes: &HashSet<H256>) -> usize {
+        let before = self.entries.len();
+        self.entries
+            .retain(|(at, _)| at.is_none_or(|p| !hashes.contains(&p.block_hash)));
+        before - self.entries.len()
+    }
+
+    fn current(&self) -> Option<&PriceInfo> {
+        self.entries.back().map(|(_, info)| info)
+    }
+}
 
 /// Shared, async-friendly PriceTracker (Arc around this struct in main)
+///
+/// Keeps a short per-pool history tagged with block hashes so updates from
+/// orphaned blocks can be rolled back.
+#[derive(Default)]
 pub struct PriceTracker {
-    inner: RwLock<HashMap<DexPairKey, PriceInfo>>,
+    inner: RwLock<HashMap<DexPairKey, PriceHistory>>,
 }
 
 impl PriceTracker {
@@ -84,15 +268,54 @@ impl PriceTracker {
         base: Address, 
         quote: Address, 
         price: f64
+    ) -> anyhow::Result<()> {
+        self.update_at(dex_factory, pair_address, base, quote, price, None).await
+    }
+
+    /// update canonical price, tagged with the log it was decoded from
+    pub async fn update_at(
+        &self, 
+        dex_factory: Address, 
+        pair_address: Address, 
+        base: Address, 
+        quote: Address, 
+        price: f64,
+        at: Option<LogPosition>
     ) -> anyhow::Result<()> {
         let key = DexPairKey { dex_factory, pair_address };
-        let info = PriceInfo { base, quote, price };
+        let info = PriceInfo { base, quote, price, updated_at: Instant::now() };
         let mut map = self.inner.write().await;
-        map.insert(key, info);
+        map.entry(key).or_default().insert(at, info);
 
         Ok(())
     }
 
+    /// Forget one pool's updates from a block (a `removed: true` log)
+    pub async fn remove_block(&self, key: DexPairKey, block_hash: H256) -> usize {
+        let mut map = self.inner.write().await;
+        let hashes = HashSet::from([block_hash]);
+        let removed = map.get_mut(&key).map_or(0, |h| h.remove_blocks(&hashes));
+        map.retain(|_, h| !h.entries.is_empty());
+        removed
+    }
+
+    /// Roll every pool back past updates from orphaned blocks. Pools left
+    /// without a canonical price disappear until their next update.
+    pub async fn rollback(&self, orphaned: &HashSet<H256>) -> usize {
+        let mut map = self.inner.write().await;
+        let removed = map.values_mut().map(|h| h.remove_blocks(orphaned)).sum();
+        map.retain(|_, h| !h.entries.is_empty());
+        removed
+    }
+
+    /// Current price of every tracked pool
+    pub async fn snapshot(&self) -> Vec<(DexPairKey, PriceInfo)> {
+        let map = self.inner.read().await;
+        map.iter()
+            .filter_map(|(k, h)| h.current().map(|info| (*k, info.clone())))
+            .collect()
+    }
+
     /// get all price infos for a given token pair (base/quote) across DEXes
     pub async fn get_all_for_pair(
         &self, 
@@ -102,9 +325,82 @@ impl PriceTracker {
         let map = self.inner.read().await;
         Ok(
             map.iter()
+            .filter_map(|(k, h)| h.current().map(|info| (k, info)))
             .filter(|(_, info)| info.base == base && info.quote == quote)
             .map(|(k, v)| (*k, v.clone()))
             .collect()
         )
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(block_number: u64, log_index: u64) -> Option<LogPosition> {
+        Some(LogPosition { block_number, block_hash: H256::from_low_u64_be(block_number), log_index })
+    }
+
+    fn key(byte: u8) -> DexPairKey {
+        DexPairKey { dex_factory: Address::repeat_byte(0xf0), pair_address: Address::repeat_byte(byte) }
+    }
+
+    async fn update(tracker: &PriceTracker, pool: DexPairKey, price: f64, position: Option<LogPosition>) {
+        let (base, quote) = (Address::repeat_byte(0xb0), Address::repeat_byte(0xc0));
+        tracker
+            .update_at(pool.dex_factory, pool.pair_address, base, quote, price, position)
+            .await
+            .unwrap();
+    }
+
+    async fn price(tracker: &PriceTracker, pool: DexPairKey) -> Option<f64> {
+        tracker.snapshot().await.into_iter().find(|(k, _)| *k == pool).map(|(_, info)| info.price)
+    }
+
+    #[tokio::test]
+    async fn rollback_restores_the_last_price_before_the_fork() {
+        let tracker = PriceTracker::new();
+        let (a, b) = (key(0x0a), key(0x0b));
+
+        update(&tracker, a, 1.0, None).await;
+        update(&tracker, a, 1.1, at(10, 0)).await;
+        // Arrives late but sorts by chain position
+        update(&tracker, a, 1.3, at(12, 0)).await;
+        update(&tracker, a, 1.2, at(11, 3)).await;
+        update(&tracker, b, 2.0, at(12, 1)).await;
+        assert_eq!(price(&tracker, a).await, Some(1.3));
+
+        let orphaned = HashSet::from([H256::from_low_u64_be(11), H256::from_low_u64_be(12)]);
+        assert_eq!(tracker.rollback(&orphaned).await, 3);
+        assert_eq!(price(&tracker, a).await, Some(1.1));
+        // Nothing canonical left for b
+        assert_eq!(price(&tracker, b).await, None);
+
+        // Startup snapshots are never rolled back
+        let orphaned = HashSet::from([H256::from_low_u64_be(10)]);
+        assert_eq!(tracker.rollback(&orphaned).await, 1);
+        assert_eq!(price(&tracker, a).await, Some(1.0));
+    }
+
+    #[tokio::test]
+    async fn remove_block_forgets_one_pools_updates_from_that_block() {
+        let tracker = PriceTracker::new();
+        let (a, b) = (key(0x0a), key(0x0b));
+
+        update(&tracker, a, 1.0, at(10, 0)).await;
+        update(&tracker, a, 1.1, at(11, 0)).await;
+        update(&tracker, a, 1.2, at(11, 4)).await;
+        update(&tracker, b, 2.0, at(11, 1)).await;
+
+        let removed = H256::from_low_u64_be(11);
+        assert_eq!(tracker.remove_block(a, removed).await, 2);
+        assert_eq!(price(&tracker, a).await, Some(1.0));
+        assert_eq!(price(&tracker, b).await, Some(2.0));
+
+        // A repeat of the same removed log is a no-op
+        assert_eq!(tracker.remove_block(a, removed).await, 0);
+
+        assert_eq!(tracker.remove_block(b, removed).await, 1);
+        assert!(tracker.snapshot().await.iter().all(|(k, _)| *k != b));
+    }
+}